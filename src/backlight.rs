@@ -0,0 +1,89 @@
+use crate::config::{Backlight, Temperature};
+use crate::transition;
+use std::process::Command;
+
+/// Drives the hardware backlight, separately from hyprsunset's gamma. A
+/// percentage equal to the last one applied isn't re-sent.
+#[derive(Debug, Default)]
+pub struct BacklightBackend {
+    last: Option<u8>,
+}
+
+impl BacklightBackend {
+    /// Sets `percent` with `set` (`brightnessctl` in the daemon) unless it's
+    /// already applied.
+    pub fn apply(
+        &mut self,
+        percent: u8,
+        set: impl FnOnce(u8) -> Result<(), Box<dyn std::error::Error>>,
+    ) {
+        if self.last == Some(percent) {
+            return;
+        }
+        match set(percent) {
+            Ok(()) => {
+                self.last = Some(percent);
+                log::info!("Set backlight to {percent}%");
+            }
+            Err(e) => log::error!("Error setting backlight: {e}"),
+        }
+    }
+}
+
+fn brightness_args(percent: u8) -> Vec<String> {
+    vec!["set".to_string(), format!("{percent}%")]
+}
+
+pub fn set_brightness(percent: u8) -> Result<(), String> {
+    let args = brightness_args(percent);
+    log::debug!("Running: brightnessctl {}", args.join(" "));
+    let output = Command::new("brightnessctl")
+        .args(&args)
+        .output()
+        .map_err(|e| format!("brightnessctl failed: {e}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("brightnessctl failed: {}", stderr.trim()))
+    }
+}
+
+/// Backlight percentage while showing `temp`, following the temperature
+/// from `backlight.night` to `backlight.day`.
+pub fn percent_at(backlight: &Backlight, temp: u16, temperature: &Temperature) -> u8 {
+    transition::follow_temperature(backlight.day, backlight.night, temp, temperature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brightnessctl_sets_a_percentage() {
+        assert_eq!(brightness_args(40), ["set", "40%"]);
+        assert_eq!(brightness_args(100), ["set", "100%"]);
+    }
+
+    #[test]
+    fn backlight_follows_the_temperature_between_day_and_night() {
+        let backlight = Backlight {
+            enabled: true,
+            day: 100,
+            night: 40,
+        };
+        let temperature = Temperature::default();
+
+        assert_eq!(percent_at(&backlight, 6500, &temperature), 100);
+        assert_eq!(percent_at(&backlight, 1500, &temperature), 40);
+        assert_eq!(percent_at(&backlight, 4000, &temperature), 70);
+        // Beyond either end (e.g. a manual set) it holds the nearest level
+        assert_eq!(percent_at(&backlight, 1000, &temperature), 40);
+
+        let flat = Temperature {
+            night: 6500,
+            ..Temperature::default()
+        };
+        assert_eq!(percent_at(&backlight, 6500, &flat), 100);
+    }
+}
@@ -0,0 +1,186 @@
+/// Approximate sRGB color of a blackbody radiator at `kelvin`, using Tanner
+/// Helland's curve fit (valid roughly 1000K–40000K).
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "channel values are clamped to 0..=255 before casting"
+)]
+pub fn kelvin_to_rgb(kelvin: u16) -> (u8, u8, u8) {
+    let t = f64::from(kelvin) / 100.0;
+
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_727_446 * (t - 60.0).powf(-0.133_204_759_2)
+    };
+    let green = if t <= 66.0 {
+        99.470_802_586_1f64.mul_add(t.ln(), -161.119_568_166_1)
+    } else {
+        288.122_169_528_3 * (t - 60.0).powf(-0.075_514_849_2)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_731_223_1f64.mul_add((t - 10.0).ln(), -305.044_792_730_7)
+    };
+
+    let channel = |v: f64| v.clamp(0.0, 255.0).round() as u8;
+    (channel(red), channel(green), channel(blue))
+}
+
+/// Approximate melanopic daylight efficacy ratio (CIE S 026 mel-DER) of
+/// blackbody light, as (Kelvin, ratio) points. D65 is 1.0 by definition and
+/// CIE illuminant A (2856K) is 0.455; the remaining points are rough
+/// blackbody estimates.
+const MELANOPIC_POINTS: [(f64, f64); 7] = [
+    (1000.0, 0.02),
+    (1500.0, 0.11),
+    (2000.0, 0.23),
+    (2856.0, 0.455),
+    (4000.0, 0.68),
+    (5000.0, 0.85),
+    (6500.0, 1.0),
+];
+
+/// A 0–1 proxy for how melatonin-suppressing light at `kelvin` is, relative
+/// to daylight (6500K and above is 1.0). `OFF` counts as daylight.
+/// Informational only: it ignores brightness and the display's actual
+/// spectrum.
+pub fn melanopic_factor(kelvin: u16) -> f64 {
+    let kelvin = if kelvin == crate::hyprctl::OFF {
+        f64::from(crate::hyprctl::IDENTITY_TEMPERATURE)
+    } else {
+        f64::from(kelvin)
+    };
+
+    let (first_k, first_ratio) = MELANOPIC_POINTS[0];
+    if kelvin <= first_k {
+        return first_ratio * kelvin / first_k;
+    }
+    MELANOPIC_POINTS
+        .windows(2)
+        .find(|pair| kelvin <= pair[1].0)
+        .map_or(1.0, |pair| {
+            let ((k0, r0), (k1, r1)) = (pair[0], pair[1]);
+            (r1 - r0).mul_add((kelvin - k0) / (k1 - k0), r0)
+        })
+}
+
+/// Grid size `image_kelvin` samples along each axis of larger images.
+const MATCH_SAMPLES: u32 = 64;
+
+/// Estimated color temperature of `image`: the `nearest_kelvin` to its
+/// average color, sampled on an even grid of at most `MATCH_SAMPLES` points
+/// per axis. `None` for an empty or all-black image.
+pub fn image_kelvin(image: &image::RgbImage) -> Option<u16> {
+    let (width, height) = image.dimensions();
+    let step = |len: u32| usize::try_from((len / MATCH_SAMPLES).max(1)).unwrap_or(1);
+
+    let mut sum = (0.0, 0.0, 0.0);
+    for y in (0..height).step_by(step(height)) {
+        for x in (0..width).step_by(step(width)) {
+            let image::Rgb([r, g, b]) = *image.get_pixel(x, y);
+            sum.0 += f64::from(r);
+            sum.1 += f64::from(g);
+            sum.2 += f64::from(b);
+        }
+    }
+    nearest_kelvin(sum)
+}
+
+/// The temperature (1000K–10000K, in 10K steps) whose `kelvin_to_rgb`
+/// chromaticity is closest to that of `rgb`. Only the channels' proportions
+/// matter, so `rgb` may be a sum rather than an average. `None` for black.
+pub fn nearest_kelvin((red, green, blue): (f64, f64, f64)) -> Option<u16> {
+    let chromaticity = |red: f64, green: f64, blue: f64| {
+        let total = red + green + blue;
+        (total > 0.0).then(|| (red / total, green / total))
+    };
+    let target = chromaticity(red, green, blue)?;
+
+    (100..=1000u16)
+        .map(|step| step * 10)
+        .min_by(|&first, &second| {
+            let distance = |kelvin| {
+                let (r, g, b) = kelvin_to_rgb(kelvin);
+                chromaticity(f64::from(r), f64::from(g), f64::from(b))
+                    .map_or(f64::INFINITY, |(x, y)| (x - target.0).hypot(y - target.1))
+            };
+            distance(first).total_cmp(&distance(second))
+        })
+}
+
+/// Whether a terminal advertising `colorterm` (the `COLORTERM` variable)
+/// supports 24-bit color.
+pub fn supports_truecolor(colorterm: Option<&str>) -> bool {
+    matches!(colorterm, Some("truecolor" | "24bit"))
+}
+
+/// ANSI escape sequence that sets the background to `rgb`.
+pub fn ansi_background((r, g, b): (u8, u8, u8)) -> String {
+    format!("\x1b[48;2;{r};{g};{b}m")
+}
+
+pub const ANSI_RESET: &str = "\x1b[0m";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daylight_is_near_white() {
+        let (r, g, b) = kelvin_to_rgb(6500);
+        assert_eq!(r, 255);
+        assert!(g > 240);
+        assert!(b > 240);
+    }
+
+    #[test]
+    fn solid_image_matches_its_blackbody_temperature() {
+        for kelvin in [1800, 3000, 4500, 6500] {
+            let (r, g, b) = kelvin_to_rgb(kelvin);
+            let image = image::RgbImage::from_pixel(200, 120, image::Rgb([r, g, b]));
+            let matched = image_kelvin(&image).expect("colored image");
+            assert!(
+                matched.abs_diff(kelvin) <= 100,
+                "{kelvin}K matched as {matched}K"
+            );
+        }
+
+        let black = image::RgbImage::from_pixel(4, 4, image::Rgb([0, 0, 0]));
+        assert_eq!(image_kelvin(&black), None);
+    }
+
+    #[test]
+    fn candlelight_has_no_blue() {
+        assert_eq!(kelvin_to_rgb(1500).2, 0);
+        assert!(kelvin_to_rgb(1500).1 < kelvin_to_rgb(3000).1);
+    }
+
+    #[test]
+    fn melanopic_factor_follows_reference_points() {
+        assert!((melanopic_factor(6500) - 1.0).abs() < 1e-9);
+        assert!((melanopic_factor(10000) - 1.0).abs() < 1e-9);
+        assert!((melanopic_factor(2856) - 0.455).abs() < 1e-9);
+        assert!((melanopic_factor(crate::hyprctl::OFF) - 1.0).abs() < 1e-9);
+
+        let candle = melanopic_factor(1900);
+        assert!(candle > 0.11 && candle < 0.23);
+        assert!(melanopic_factor(3400) > melanopic_factor(2700));
+    }
+
+    #[test]
+    fn truecolor_detection() {
+        assert!(supports_truecolor(Some("truecolor")));
+        assert!(supports_truecolor(Some("24bit")));
+        assert!(!supports_truecolor(Some("")));
+        assert!(!supports_truecolor(None));
+    }
+
+    #[test]
+    fn ansi_background_format() {
+        assert_eq!(ansi_background((255, 128, 0)), "\x1b[48;2;255;128;0m");
+    }
+}
@@ -1,5 +1,7 @@
+use chrono::Weekday;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
@@ -8,13 +10,27 @@ pub enum Mode {
     #[default]
     Auto,
     Fixed,
+    /// Interpolate between the `[[keyframes]]` entries.
+    Manual,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(default)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Location {
     pub latitude: f64,
     pub longitude: f64,
+    /// Bundled city preset (e.g. `"berlin"`), used when the coordinates are
+    /// unset.
+    pub city: Option<String>,
+    /// Approximate the longitude from the system's standard UTC offset when
+    /// it's unset and nothing else gives one. The latitude can't be derived
+    /// and stays as configured (0 gives the equator's day length).
+    pub from_timezone: bool,
+    /// In auto mode, look the location up with geoclue, then IP
+    /// geolocation, when neither coordinates nor a city are given. Only the
+    /// daemon looks it up (again on every reload); other commands reuse what
+    /// it cached. Set to false to keep the configured coordinates.
+    pub detect: bool,
 }
 
 impl Default for Location {
@@ -22,15 +38,72 @@ impl Default for Location {
         Self {
             latitude: 0.0,
             longitude: 0.0,
+            city: None,
+            from_timezone: false,
+            detect: true,
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(default)]
+impl Location {
+    /// Whether no coordinates were configured (absent, or both 0).
+    pub fn is_unset(&self) -> bool {
+        (self.latitude, self.longitude) == (0.0, 0.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Auto {
+    /// Solar elevation (degrees) that counts as sunrise/sunset. The standard
+    /// -0.833 accounts for atmospheric refraction and the solar disc radius.
+    pub sun_elevation_degrees: f64,
+    /// Fixed wakeup time (HH:MM) replacing sunrise on Saturdays and Sundays.
+    pub weekend_wakeup: Option<String>,
+    /// Evening transition start relative to solar noon (`solar_noon+4h`,
+    /// `solar_noon-90m`), replacing sunset.
+    pub evening_start: Option<String>,
+    /// Nights shorter than this many minutes (sunset to the next morning) are
+    /// skipped, holding the day temperature through them (0 = never skip).
+    pub min_night_minutes: u32,
+    /// What to do on days the sun never crosses `sun_elevation_degrees`.
+    pub polar: Polar,
+}
+
+impl Default for Auto {
+    fn default() -> Self {
+        Self {
+            sun_elevation_degrees: -0.833,
+            weekend_wakeup: None,
+            evening_start: None,
+            min_night_minutes: 0,
+            polar: Polar::Hold,
+        }
+    }
+}
+
+/// Auto-mode behavior during polar day (the sun never sets) and polar night
+/// (it never rises).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Polar {
+    /// Hold the day temperature through polar day and night through polar night.
+    #[default]
+    Hold,
+    /// Follow the fixed-mode wakeup/bedtime schedule instead.
+    Fixed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Schedule {
     pub wakeup: String,
     pub bedtime: String,
+    /// Optional file of per-date/per-weekday wakeup/bedtime overrides (fixed mode).
+    pub file: Option<String>,
+    /// Fixed-mode intermediate temperatures between wakeup and bedtime (e.g.
+    /// an evening wind-down), each reached at its time after a transition.
+    pub stops: Vec<Keyframe>,
 }
 
 impl Default for Schedule {
@@ -38,31 +111,150 @@ impl Default for Schedule {
         Self {
             wakeup: "07:00".to_string(),
             bedtime: "22:00".to_string(),
+            file: None,
+            stops: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepRounding {
+    #[default]
+    Nearest,
+    Floor,
+    Ceil,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Transition {
-    pub duration_minutes: u32,
+    pub duration_minutes: DurationMinutes,
     pub easing: String,
+    /// `[progress, kelvin]` points used when `easing = "lut"`, interpolated
+    /// linearly. Progress must increase from 0.0 to 1.0 and kelvin stay
+    /// within 1000-20000K. The kelvin values only shape the curve: they are
+    /// rescaled onto each transition's own start and target.
+    pub easing_lut: Vec<(f64, f64)>,
+    /// Blend a short smoothstep into both ends of the eased curve so the
+    /// transition doesn't stop abruptly, whatever the easing.
+    pub auto_smooth_endpoints: bool,
+    /// Quantize intermediate temperatures to multiples of this many Kelvin (0 = off).
+    pub temperature_step: u16,
+    pub temperature_step_rounding: StepRounding,
 }
 
 impl Default for Transition {
     fn default() -> Self {
         Self {
-            duration_minutes: 60,
+            duration_minutes: DurationMinutes::Both(60),
             easing: "smooth".to_string(),
+            easing_lut: Vec::new(),
+            auto_smooth_endpoints: false,
+            temperature_step: 0,
+            temperature_step_rounding: StepRounding::Nearest,
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(default)]
+/// `transition.duration_minutes`: one length for both transitions, or a
+/// `{ to_day = N, to_night = M }` table, e.g. a short sunrise and a long sunset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum DurationMinutes {
+    Both(u32),
+    PerPhase(PhaseDurations),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhaseDurations {
+    pub to_day: u32,
+    pub to_night: u32,
+}
+
+impl DurationMinutes {
+    /// Minutes of the transition from night to day.
+    pub const fn to_day(self) -> u32 {
+        match self {
+            Self::Both(minutes)
+            | Self::PerPhase(PhaseDurations {
+                to_day: minutes, ..
+            }) => minutes,
+        }
+    }
+
+    /// Minutes of the transition from day to night.
+    pub const fn to_night(self) -> u32 {
+        match self {
+            Self::Both(minutes)
+            | Self::PerPhase(PhaseDurations {
+                to_night: minutes, ..
+            }) => minutes,
+        }
+    }
+
+    /// The longer of the two transitions.
+    pub fn longest(self) -> u32 {
+        self.to_day().max(self.to_night())
+    }
+}
+
+// Not `#[serde(untagged)]`: that would hide which key of a mistyped table
+// was unknown
+impl<'de> Deserialize<'de> for DurationMinutes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = DurationMinutes;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("minutes or a { to_day, to_night } table")
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                u32::try_from(value)
+                    .map(DurationMinutes::Both)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(value), &self))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                u32::try_from(value)
+                    .map(DurationMinutes::Both)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(value), &self))
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                PhaseDurations::deserialize(serde::de::value::MapAccessDeserializer::new(map))
+                    .map(DurationMinutes::PerPhase)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+impl From<u32> for DurationMinutes {
+    fn from(minutes: u32) -> Self {
+        Self::Both(minutes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "RawTemperature")]
 pub struct Temperature {
     pub day: u16,
     pub night: u16,
+    /// Night relative to the day value in effect (e.g. -4500 for 4500K below
+    /// day), replacing `night`. A weekday's explicit `night` still wins.
+    pub night_offset: Option<i32>,
+    /// `[temperature.monday]`-style tables overriding day/night on specific weekdays.
+    #[serde(flatten)]
+    pub weekdays: HashMap<Weekday, WeekdayTemperature>,
 }
 
 impl Default for Temperature {
@@ -70,18 +262,214 @@ impl Default for Temperature {
         Self {
             day: 6500,
             night: 1500,
+            night_offset: None,
+            weekdays: HashMap::new(),
+        }
+    }
+}
+
+/// Kelvin range a relative night has to resolve into.
+const RELATIVE_NIGHT_RANGE: std::ops::RangeInclusive<i32> = 1000..=20000;
+
+impl Temperature {
+    /// The day/night temperatures in effect on `weekday`, falling back to the
+    /// base values for anything not overridden.
+    pub fn on(&self, weekday: Weekday) -> Self {
+        let overrides = self.weekdays.get(&weekday);
+        let day = overrides.and_then(|o| o.day).unwrap_or(self.day);
+        Self {
+            day,
+            night: overrides
+                .and_then(|o| o.night)
+                .unwrap_or_else(|| self.night_for(day)),
+            night_offset: None,
+            weekdays: HashMap::new(),
         }
     }
+
+    /// The base night temperature, with `night_offset` applied to `day`.
+    pub fn resolved_night(&self) -> u16 {
+        self.night_for(self.day)
+    }
+
+    fn night_for(&self, day: u16) -> u16 {
+        self.night_offset
+            .and_then(|offset| relative_night(day, offset).ok())
+            .unwrap_or(self.night)
+    }
+
+    /// Checks `night_offset` resolves into range against the base day and
+    /// every weekday day that doesn't set its own night.
+    pub fn validate_night_offset(&self) -> Result<(), String> {
+        let Some(offset) = self.night_offset else {
+            return Ok(());
+        };
+        let weekday_days = self
+            .weekdays
+            .values()
+            .filter(|o| o.night.is_none())
+            .filter_map(|o| o.day);
+        std::iter::once(self.day)
+            .chain(weekday_days)
+            .try_for_each(|day| relative_night(day, offset).map(|_| ()))
+    }
+}
+
+fn relative_night(day: u16, offset: i32) -> Result<u16, String> {
+    let night = i32::from(day) + offset;
+    if RELATIVE_NIGHT_RANGE.contains(&night) {
+        u16::try_from(night).map_err(|e| e.to_string())
+    } else {
+        Err(format!(
+            "night_offset {offset} puts night at {night}K for day {day}K; it must land within \
+             {}-{}K",
+            RELATIVE_NIGHT_RANGE.start(),
+            RELATIVE_NIGHT_RANGE.end()
+        ))
+    }
+}
+
+/// `[temperature]` as written. The weekday tables are flattened in, so
+/// `deny_unknown_fields` can't apply; any other key is rejected by name here.
+#[derive(Deserialize)]
+struct RawTemperature {
+    day: Option<u16>,
+    night: Option<u16>,
+    night_offset: Option<i32>,
+    #[serde(flatten)]
+    weekdays: HashMap<String, toml::Value>,
+}
+
+impl TryFrom<RawTemperature> for Temperature {
+    type Error = String;
+
+    fn try_from(raw: RawTemperature) -> Result<Self, Self::Error> {
+        let defaults = Self::default();
+        let weekdays = raw
+            .weekdays
+            .into_iter()
+            .map(|(key, value)| {
+                let weekday = key.parse::<Weekday>().map_err(|_| {
+                    format!(
+                        "unknown field `{key}`, expected `day`, `night`, `night_offset` or a \
+                         weekday table such as `[temperature.friday]`"
+                    )
+                })?;
+                let overrides = value
+                    .try_into()
+                    .map_err(|e| format!("in `[temperature.{key}]`: {e}"))?;
+                Ok((weekday, overrides))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            day: raw.day.unwrap_or(defaults.day),
+            night: raw.night.unwrap_or(defaults.night),
+            night_offset: raw.night_offset,
+            weekdays,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeekdayTemperature {
+    pub day: Option<u16>,
+    pub night: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseMode {
+    /// Stop ticking entirely; the last status is only re-stamped so it
+    /// doesn't read as stale.
+    #[default]
+    Full,
+    /// Keep computing and writing status, but don't call hyprctl.
+    SetsOnly,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendFailure {
+    /// Log the failure and retry on later ticks.
+    #[default]
+    Continue,
+    /// Exit with an error, e.g. so a service manager restarts the session.
+    Exit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusFormat {
+    /// `key=value` lines.
+    #[default]
+    Text,
+    /// One JSON object with the same keys.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockDetection {
+    #[default]
+    Off,
+    /// Query logind's `LockedHint` for the current session.
+    Logind,
+    /// Run `daemon.lock_command`; exit status 0 means locked.
+    Command,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "independent on/off daemon settings"
+)]
 pub struct Daemon {
     pub tick_interval_seconds: u64,
     pub status_file: String,
+    /// How the status file is written; readers understand both.
+    pub status_format: StatusFormat,
     pub optimize_updates: bool,
+    /// With `optimize_updates`, skip sets that move less than this many
+    /// kelvin from the last one sent; the final target is always sent.
+    pub min_update_delta: u16,
+    /// Deprecated: status write frequency in ticks. Used only when
+    /// `status_update_interval_seconds` is unset.
     pub status_update_interval: u64,
+    /// Minimum wall-clock seconds between status file writes (0 = every tick).
+    pub status_update_interval_seconds: Option<u64>,
     pub state_file: String,
+    pub pause_mode: PauseMode,
+    /// Optional CSV log of applied temperatures (`timestamp,temp,phase`).
+    pub history_file: Option<String>,
+    /// Number of most recent lines kept in `history_file`.
+    pub history_max_lines: usize,
+    /// Skip transitions while the session is locked, applying on unlock.
+    pub lock_detection: LockDetection,
+    pub lock_command: String,
+    /// Maximum number of concurrent hyprctl processes.
+    pub max_hyprctl_in_flight: usize,
+    /// Seconds a hyprctl call may take before it is killed and counted as failed.
+    pub hyprctl_timeout_seconds: u64,
+    /// Seconds to hold the initial temperature after launch before following
+    /// the schedule.
+    pub startup_grace_seconds: u64,
+    /// Attempts made at the first temperature set after launch, retrying
+    /// quickly while hyprsunset finishes starting up.
+    pub initial_set_attempts: u32,
+    /// What to do once every `initial_set_attempts` attempt has failed.
+    pub on_backend_failure: BackendFailure,
+    /// Stretch each sleep so the next tick lands on the start of a minute.
+    pub align_to_minute: bool,
+    /// Reload automatically when the config file changes, as on SIGHUP.
+    /// Read at startup.
+    pub watch_config: bool,
+    /// Set a neutral temperature on shutdown instead of leaving the display
+    /// at whatever the transition last set.
+    pub restore_on_exit: bool,
+    /// Temperature `restore_on_exit` sets; the day temperature when unset.
+    pub restore_temperature: Option<u16>,
 }
 
 impl Default for Daemon {
@@ -89,22 +477,351 @@ impl Default for Daemon {
         Self {
             tick_interval_seconds: 5,
             status_file: "/tmp/candela.status".to_string(),
+            status_format: StatusFormat::Text,
             optimize_updates: true,
+            min_update_delta: 1,
             status_update_interval: 1,
+            status_update_interval_seconds: None,
             state_file: "~/.cache/candela/state.toml".to_string(),
+            pause_mode: PauseMode::Full,
+            history_file: None,
+            history_max_lines: 1000,
+            lock_detection: LockDetection::Off,
+            lock_command: String::new(),
+            max_hyprctl_in_flight: crate::hyprctl::DEFAULT_MAX_IN_FLIGHT,
+            hyprctl_timeout_seconds: crate::hyprctl::DEFAULT_TIMEOUT_SECONDS,
+            startup_grace_seconds: 0,
+            initial_set_attempts: 5,
+            on_backend_failure: BackendFailure::Continue,
+            align_to_minute: false,
+            watch_config: false,
+            restore_on_exit: false,
+            restore_temperature: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    pub enabled: bool,
+    /// Shell command run when the day phase begins.
+    pub day: String,
+    /// Shell command run when the night phase begins.
+    pub night: String,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            day: String::new(),
+            night: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Cycle {
+    /// Temperatures `candela cycle` steps through, wrapping at the end.
+    pub steps: Vec<u16>,
+    /// Where the index of the last applied step is kept.
+    pub state_file: String,
+}
+
+impl Default for Cycle {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            state_file: "~/.cache/candela/cycle".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Power {
+    /// Factor the daemon's tick interval is stretched by while a battery is
+    /// discharging; 1 disables the behaviour.
+    pub battery_tick_multiplier: u32,
+}
+
+impl Default for Power {
+    fn default() -> Self {
+        Self {
+            battery_tick_multiplier: 1,
         }
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Backlight {
+    /// Also drive the hardware backlight through `brightnessctl`.
+    pub enabled: bool,
+    /// Backlight percentage at the day temperature.
+    pub day: u8,
+    /// Backlight percentage at the night temperature.
+    pub night: u8,
+}
+
+impl Default for Backlight {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            day: 100,
+            night: 60,
+        }
+    }
+}
+
+/// Safety net for gamma: every gamma value sent is raised to `floor`, so a
+/// misconfigured night can't black out the screen.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Brightness {
+    /// Lowest gamma percentage ever sent.
+    pub floor: u8,
+    /// Allow a `floor` of 0; without it a 0 floor is raised to the default.
+    pub allow_black: bool,
+}
+
+impl Default for Brightness {
+    fn default() -> Self {
+        Self {
+            floor: crate::hyprctl::DEFAULT_GAMMA_FLOOR,
+            allow_black: false,
+        }
+    }
+}
+
+/// hyprsunset gamma, following the temperature transition. Leaving the
+/// `[gamma]` block out means gamma is never touched.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Gamma {
+    /// Gamma percentage (0-100) at the day temperature.
+    pub day: u8,
+    /// Gamma percentage (0-100) at the night temperature.
+    pub night: u8,
+}
+
+impl Default for Gamma {
+    fn default() -> Self {
+        Self {
+            day: 100,
+            night: 100,
+        }
+    }
+}
+
+/// An extra `[[schedules]]` entry: another curve, ticked by the same daemon
+/// and applied through `command` rather than hyprsunset. A section it sets
+/// replaces the main config's (its unset keys take the defaults); sections it
+/// leaves out are inherited.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamedSchedule {
+    /// Keys the schedule's status fields (`schedule.<name>.temp`).
+    pub name: String,
+    /// Run via `sh -c` with `CANDELA_SCHEDULE` and `CANDELA_TEMPERATURE` set
+    /// whenever the schedule's temperature changes.
+    pub command: String,
+    pub mode: Option<Mode>,
+    pub schedule: Option<Schedule>,
+    pub transition: Option<Transition>,
+    pub temperature: Option<Temperature>,
+}
+
+impl NamedSchedule {
+    /// The full config this schedule runs under, given the main `base`.
+    pub fn config(&self, base: &Config) -> Config {
+        let mut config = base.clone();
+        config.schedules = Vec::new();
+        if let Some(mode) = &self.mode {
+            config.mode = mode.clone();
+        }
+        if let Some(schedule) = &self.schedule {
+            config.schedule = schedule.clone();
+        }
+        if let Some(transition) = &self.transition {
+            config.transition = transition.clone();
+        }
+        if let Some(temperature) = &self.temperature {
+            config.temperature = temperature.clone();
+        }
+        config
+    }
+}
+
+/// A `(time, temperature)` point of the manual-mode day.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+    /// Time of day (HH:MM).
+    pub time: String,
+    pub temperature: u16,
+}
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub mode: Mode,
     pub location: Location,
+    pub auto: Auto,
     pub schedule: Schedule,
     pub transition: Transition,
     pub temperature: Temperature,
     pub daemon: Daemon,
+    pub hooks: Hooks,
+    pub cycle: Cycle,
+    pub power: Power,
+    pub backlight: Backlight,
+    pub brightness: Brightness,
+    pub gamma: Option<Gamma>,
+    /// Manual-mode keyframes, in any order.
+    pub keyframes: Vec<Keyframe>,
+    /// Additional named schedules driven through their own commands.
+    pub schedules: Vec<NamedSchedule>,
+}
+
+/// Kelvin range hyprsunset accepts; 0 (identity) is allowed separately.
+pub const TEMPERATURE_RANGE: std::ops::RangeInclusive<u16> = 1000..=20000;
+
+impl Config {
+    /// Clamps every temperature (day/night base and per weekday, keyframes,
+    /// stops, the restore temperature and each `[[schedules]]` entry's) into
+    /// the range hyprsunset accepts, keeps the gamma floor within 1-100% (0
+    /// only with `allow_black`), raises the hyprctl timeout to a second, and
+    /// flags nights set above days. Returns one warning per clamped or
+    /// suspicious value; nothing here is fatal.
+    pub fn validate(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut clamp = |name: String, kelvin: &mut u16| {
+            if *kelvin == crate::hyprctl::OFF || TEMPERATURE_RANGE.contains(kelvin) {
+                return;
+            }
+            let clamped = (*kelvin).clamp(*TEMPERATURE_RANGE.start(), *TEMPERATURE_RANGE.end());
+            warnings.push(format!(
+                "{name} = {kelvin}K is outside hyprsunset's {}-{}K range; using {clamped}K",
+                TEMPERATURE_RANGE.start(),
+                TEMPERATURE_RANGE.end()
+            ));
+            *kelvin = clamped;
+        };
+        clamp_temperatures("temperature", &mut self.temperature, &mut clamp);
+        clamp_stops("keyframes", &mut self.keyframes, &mut clamp);
+        clamp_stops("schedule.stops", &mut self.schedule.stops, &mut clamp);
+        if let Some(kelvin) = &mut self.daemon.restore_temperature {
+            clamp("daemon.restore_temperature".to_string(), kelvin);
+        }
+        for (i, entry) in self.schedules.iter_mut().enumerate() {
+            if let Some(temperature) = &mut entry.temperature {
+                clamp_temperatures(
+                    &format!("schedules[{i}].temperature"),
+                    temperature,
+                    &mut clamp,
+                );
+            }
+            if let Some(schedule) = &mut entry.schedule {
+                let list = format!("schedules[{i}].schedule.stops");
+                clamp_stops(&list, &mut schedule.stops, &mut clamp);
+            }
+        }
+
+        let brightness = &mut self.brightness;
+        if brightness.floor == 0 && !brightness.allow_black {
+            let default = crate::hyprctl::DEFAULT_GAMMA_FLOOR;
+            warnings.push(format!(
+                "brightness.floor = 0% can black out the screen; set brightness.allow_black = \
+                 true to allow it. Using {default}%"
+            ));
+            brightness.floor = default;
+        } else if brightness.floor > 100 {
+            warnings.push(format!(
+                "brightness.floor = {}% is above 100%; using 100%",
+                brightness.floor
+            ));
+            brightness.floor = 100;
+        }
+
+        if self.daemon.hyprctl_timeout_seconds == 0 {
+            warnings.push(
+                "daemon.hyprctl_timeout_seconds = 0 would fail every hyprctl call; using 1"
+                    .to_string(),
+            );
+            self.daemon.hyprctl_timeout_seconds = 1;
+        }
+
+        let mut overridden: Vec<Weekday> = self.temperature.weekdays.keys().copied().collect();
+        overridden.sort_by_key(Weekday::num_days_from_monday);
+        let base = (
+            String::new(),
+            self.temperature.day,
+            self.temperature.resolved_night(),
+        );
+        let days = overridden.into_iter().map(|weekday| {
+            let temperature = self.temperature.on(weekday);
+            (
+                format!(" on {}", weekday_name(weekday)),
+                temperature.day,
+                temperature.night,
+            )
+        });
+        for (when, day, night) in std::iter::once(base).chain(days) {
+            if day != crate::hyprctl::OFF && night > day {
+                warnings.push(format!(
+                    "Night ({night}K) is above day ({day}K){when}; nights will be bluer than days"
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+/// Runs `clamp` over the day/night temperatures of `temperature`, base and
+/// per weekday, naming each under `table`.
+fn clamp_temperatures(
+    table: &str,
+    temperature: &mut Temperature,
+    clamp: &mut impl FnMut(String, &mut u16),
+) {
+    clamp(format!("{table}.day"), &mut temperature.day);
+    clamp(format!("{table}.night"), &mut temperature.night);
+    let mut weekdays: Vec<_> = temperature.weekdays.iter_mut().collect();
+    weekdays.sort_by_key(|(weekday, _)| weekday.num_days_from_monday());
+    for (weekday, temperature) in weekdays {
+        let table = format!("{table}.{}", weekday_name(*weekday));
+        if let Some(day) = &mut temperature.day {
+            clamp(format!("{table}.day"), day);
+        }
+        if let Some(night) = &mut temperature.night {
+            clamp(format!("{table}.night"), night);
+        }
+    }
+}
+
+/// Runs `clamp` over the temperature of each keyframe in `list`.
+fn clamp_stops(list: &str, keyframes: &mut [Keyframe], clamp: &mut impl FnMut(String, &mut u16)) {
+    for (i, keyframe) in keyframes.iter_mut().enumerate() {
+        clamp(
+            format!("{list}[{i}].temperature"),
+            &mut keyframe.temperature,
+        );
+    }
+}
+
+const fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
 }
 
 pub fn find_config() -> Option<PathBuf> {
@@ -117,14 +834,78 @@ pub fn find_config() -> Option<PathBuf> {
     config_locations.into_iter().find(|path| path.exists())
 }
 
-pub fn load(path: Option<&str>) -> Config {
-    let mut config: Config = path.map_or_else(Config::default, |p| {
-        let content = std::fs::read_to_string(p).unwrap_or_default();
-        toml::from_str(&content).unwrap_or_else(|e| {
-            log::warn!("Error parsing config: {e}");
-            Config::default()
-        })
-    });
+/// The config file to load and whether it was asked for explicitly, in
+/// order: `--config`, `config.toml` in `--config-dir`, `$CANDELA_CONFIG`,
+/// `$XDG_CONFIG_HOME/candela/config.toml`, then `find_config`. A missing
+/// explicit file is an error when loaded rather than skipped.
+pub fn resolve_path(flag: Option<&str>, dir: Option<&str>) -> Option<(PathBuf, bool)> {
+    resolve_path_with(flag, dir, |name| std::env::var_os(name), find_config)
+}
+
+fn resolve_path_with(
+    flag: Option<&str>,
+    dir: Option<&str>,
+    var: impl Fn(&str) -> Option<std::ffi::OsString>,
+    fallback: impl FnOnce() -> Option<PathBuf>,
+) -> Option<(PathBuf, bool)> {
+    let explicit = flag
+        .map(PathBuf::from)
+        .or_else(|| dir.map(|dir| PathBuf::from(dir).join("config.toml")))
+        .or_else(|| {
+            var("CANDELA_CONFIG")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+        });
+    if let Some(path) = explicit {
+        return Some((path, true));
+    }
+    var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|home| home.is_absolute())
+        .map(|home| home.join("candela/config.toml"))
+        .filter(|path| path.exists())
+        .or_else(fallback)
+        .map(|path| (path, false))
+}
+
+/// Reads the config file, treating a missing file as "no config" but
+/// surfacing any other I/O error (permissions, not a file, ...).
+fn read_config_file(path: &str) -> std::io::Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// FNV-1a hash of the config file's contents (empty when missing or
+/// unreadable). Stable across builds, so the CLI can compare it with the hash
+/// the daemon reported.
+pub fn file_hash(path: Option<&str>) -> u64 {
+    let content = path
+        .and_then(|p| read_config_file(p).ok().flatten())
+        .unwrap_or_default();
+    content.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Loads the config at `path`. A missing file falls back to defaults, unless
+/// the path was given `explicit`ly (`--config`), in which case it's an error.
+pub fn load(path: Option<&str>, explicit: bool) -> Result<Config, String> {
+    let content = match path.map(|p| (p, read_config_file(p))) {
+        None => String::new(),
+        Some((p, Ok(None))) if explicit => return Err(format!("Config file {p} does not exist")),
+        Some((_, Ok(content))) => content.unwrap_or_default(),
+        Some((p, Err(e))) => {
+            log::error!("Config file {p} exists but cannot be read ({e}); using defaults");
+            String::new()
+        }
+    };
+    // A typo'd key or malformed value is an error rather than a silent
+    // fall back to defaults; toml's message names the line and field
+    let mut config: Config = toml::from_str(&content)
+        .map_err(|e| format!("Invalid config {}: {e}", path.unwrap_or_default()))?;
 
     // Apply defaults for any missing or empty daemon fields
     if config.daemon.tick_interval_seconds == 0 {
@@ -138,81 +919,832 @@ pub fn load(path: Option<&str>) -> Config {
     }
 
     apply_env(&mut config);
-    config
+    Ok(config)
 }
 
 fn apply_env(config: &mut Config) {
-    if let Ok(val) = std::env::var("CANDELA_MODE") {
+    apply_env_with(config, |name| std::env::var(name).ok());
+}
+
+#[allow(clippy::too_many_lines, reason = "one block per environment variable")]
+fn apply_env_with(config: &mut Config, var: impl Fn(&str) -> Option<String>) {
+    if let Some(val) = var("CANDELA_MODE") {
         match val.to_lowercase().as_str() {
             "auto" => config.mode = Mode::Auto,
             "fixed" => config.mode = Mode::Fixed,
+            "manual" => config.mode = Mode::Manual,
             _ => {}
         }
     }
 
-    if let Ok(val) = std::env::var("CANDELA_LATITUDE") {
+    if let Some(val) = var("CANDELA_LATITUDE") {
         if let Ok(lat) = val.parse() {
             config.location.latitude = lat;
         }
     }
 
-    if let Ok(val) = std::env::var("CANDELA_LONGITUDE") {
+    if let Some(val) = var("CANDELA_LONGITUDE") {
         if let Ok(lon) = val.parse() {
             config.location.longitude = lon;
         }
     }
 
-    if let Ok(val) = std::env::var("CANDELA_DAY_TEMP") {
+    if let Some(val) = var("CANDELA_CITY") {
+        config.location.city = Some(val);
+    }
+
+    if let Some(val) = var("CANDELA_LOCATION_DETECT") {
+        config.location.detect = val.to_lowercase() == "true";
+    }
+
+    if let Some(val) = var("CANDELA_LOCATION_FROM_TIMEZONE") {
+        config.location.from_timezone = val.to_lowercase() == "true";
+    }
+
+    if let Some(val) = var("CANDELA_SUN_ELEVATION") {
+        if let Ok(elevation) = val.parse() {
+            config.auto.sun_elevation_degrees = elevation;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_WEEKEND_WAKEUP") {
+        config.auto.weekend_wakeup = Some(val);
+    }
+
+    if let Some(val) = var("CANDELA_EVENING_START") {
+        config.auto.evening_start = Some(val);
+    }
+
+    if let Some(val) = var("CANDELA_MIN_NIGHT_MINUTES") {
+        if let Ok(minutes) = val.parse() {
+            config.auto.min_night_minutes = minutes;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_POLAR") {
+        match val.to_lowercase().as_str() {
+            "hold" => config.auto.polar = Polar::Hold,
+            "fixed" => config.auto.polar = Polar::Fixed,
+            _ => {}
+        }
+    }
+
+    if let Some(val) = var("CANDELA_DAY_TEMP") {
         if let Ok(temp) = val.parse() {
             config.temperature.day = temp;
         }
     }
 
-    if let Ok(val) = std::env::var("CANDELA_NIGHT_TEMP") {
+    if let Some(val) = var("CANDELA_NIGHT_TEMP") {
         if let Ok(temp) = val.parse() {
             config.temperature.night = temp;
         }
     }
 
-    if let Ok(val) = std::env::var("CANDELA_TRANSITION_DURATION") {
+    if let Some(val) = var("CANDELA_NIGHT_OFFSET") {
+        if let Ok(offset) = val.parse() {
+            config.temperature.night_offset = Some(offset);
+        }
+    }
+
+    if let Some(val) = var("CANDELA_TRANSITION_DURATION") {
         if let Ok(dur) = val.parse() {
-            config.transition.duration_minutes = dur;
+            config.transition.duration_minutes = DurationMinutes::Both(dur);
         }
     }
 
-    if let Ok(val) = std::env::var("CANDELA_EASING") {
+    if let Some(val) = var("CANDELA_EASING") {
         config.transition.easing = val;
     }
 
-    if let Ok(val) = std::env::var("CANDELA_TICK_INTERVAL") {
+    if let Some(val) = var("CANDELA_AUTO_SMOOTH_ENDPOINTS") {
+        config.transition.auto_smooth_endpoints = val.to_lowercase() == "true";
+    }
+
+    if let Some(val) = var("CANDELA_TEMPERATURE_STEP") {
+        if let Ok(step) = val.parse() {
+            config.transition.temperature_step = step;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_TEMPERATURE_STEP_ROUNDING") {
+        match val.to_lowercase().as_str() {
+            "nearest" => config.transition.temperature_step_rounding = StepRounding::Nearest,
+            "floor" => config.transition.temperature_step_rounding = StepRounding::Floor,
+            "ceil" => config.transition.temperature_step_rounding = StepRounding::Ceil,
+            _ => {}
+        }
+    }
+
+    if let Some(val) = var("CANDELA_TICK_INTERVAL") {
         if let Ok(interval) = val.parse() {
             config.daemon.tick_interval_seconds = interval;
         }
     }
 
-    if let Ok(val) = std::env::var("CANDELA_STATUS_FILE") {
+    if let Some(val) = var("CANDELA_STATUS_FILE") {
         config.daemon.status_file = val;
     }
 
-    if let Ok(val) = std::env::var("CANDELA_WAKEUP") {
+    if let Some(val) = var("CANDELA_WAKEUP") {
         config.schedule.wakeup = val;
     }
 
-    if let Ok(val) = std::env::var("CANDELA_BEDTIME") {
+    if let Some(val) = var("CANDELA_BEDTIME") {
         config.schedule.bedtime = val;
     }
 
-    if let Ok(val) = std::env::var("CANDELA_OPTIMIZE_UPDATES") {
+    if let Some(val) = var("CANDELA_SCHEDULE_FILE") {
+        config.schedule.file = Some(val);
+    }
+
+    if let Some(val) = var("CANDELA_OPTIMIZE_UPDATES") {
         config.daemon.optimize_updates = val.to_lowercase() != "false";
     }
 
-    if let Ok(val) = std::env::var("CANDELA_STATUS_UPDATE_INTERVAL") {
+    if let Some(val) = var("CANDELA_MIN_UPDATE_DELTA") {
+        if let Ok(delta) = val.parse() {
+            config.daemon.min_update_delta = delta;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_STATUS_UPDATE_INTERVAL") {
         if let Ok(interval) = val.parse() {
             config.daemon.status_update_interval = interval;
         }
     }
 
-    if let Ok(val) = std::env::var("CANDELA_STATE_FILE") {
+    if let Some(val) = var("CANDELA_STATUS_UPDATE_INTERVAL_SECONDS") {
+        if let Ok(seconds) = val.parse() {
+            config.daemon.status_update_interval_seconds = Some(seconds);
+        }
+    }
+
+    if let Some(val) = var("CANDELA_STATE_FILE") {
         config.daemon.state_file = val;
     }
+
+    if let Some(val) = var("CANDELA_HISTORY_FILE") {
+        config.daemon.history_file = Some(val);
+    }
+
+    if let Some(val) = var("CANDELA_HISTORY_MAX_LINES") {
+        if let Ok(lines) = val.parse() {
+            config.daemon.history_max_lines = lines;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_LOCK_DETECTION") {
+        match val.to_lowercase().as_str() {
+            "off" => config.daemon.lock_detection = LockDetection::Off,
+            "logind" => config.daemon.lock_detection = LockDetection::Logind,
+            "command" => config.daemon.lock_detection = LockDetection::Command,
+            _ => {}
+        }
+    }
+
+    if let Some(val) = var("CANDELA_LOCK_COMMAND") {
+        config.daemon.lock_command = val;
+    }
+
+    if let Some(val) = var("CANDELA_HYPRCTL_TIMEOUT") {
+        if let Ok(seconds) = val.parse() {
+            config.daemon.hyprctl_timeout_seconds = seconds;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_STARTUP_GRACE") {
+        if let Ok(seconds) = val.parse() {
+            config.daemon.startup_grace_seconds = seconds;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_INITIAL_SET_ATTEMPTS") {
+        if let Ok(attempts) = val.parse() {
+            config.daemon.initial_set_attempts = attempts;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_ALIGN_TO_MINUTE") {
+        config.daemon.align_to_minute = val.to_lowercase() == "true";
+    }
+
+    if let Some(val) = var("CANDELA_WATCH_CONFIG") {
+        config.daemon.watch_config = val.to_lowercase() == "true";
+    }
+
+    if let Some(val) = var("CANDELA_RESTORE_ON_EXIT") {
+        config.daemon.restore_on_exit = val.to_lowercase() == "true";
+    }
+
+    if let Some(val) = var("CANDELA_RESTORE_TEMPERATURE") {
+        if let Ok(kelvin) = val.parse() {
+            config.daemon.restore_temperature = Some(kelvin);
+        }
+    }
+
+    if let Some(val) = var("CANDELA_GAMMA_DAY") {
+        if let Ok(percent) = val.parse() {
+            config.gamma.get_or_insert_with(Gamma::default).day = percent;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_GAMMA_NIGHT") {
+        if let Ok(percent) = val.parse() {
+            config.gamma.get_or_insert_with(Gamma::default).night = percent;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_BACKLIGHT_ENABLED") {
+        config.backlight.enabled = val.to_lowercase() == "true";
+    }
+
+    if let Some(val) = var("CANDELA_BACKLIGHT_DAY") {
+        if let Ok(percent) = val.parse() {
+            config.backlight.day = percent;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_BACKLIGHT_NIGHT") {
+        if let Ok(percent) = val.parse() {
+            config.backlight.night = percent;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_BRIGHTNESS_FLOOR") {
+        if let Ok(percent) = val.parse() {
+            config.brightness.floor = percent;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_BRIGHTNESS_ALLOW_BLACK") {
+        config.brightness.allow_black = val.to_lowercase() == "true";
+    }
+
+    if let Some(val) = var("CANDELA_BATTERY_TICK_MULTIPLIER") {
+        if let Ok(multiplier) = val.parse() {
+            config.power.battery_tick_multiplier = multiplier;
+        }
+    }
+
+    if let Some(val) = var("CANDELA_PAUSE_MODE") {
+        match val.to_lowercase().as_str() {
+            "full" => config.daemon.pause_mode = PauseMode::Full,
+            "sets_only" => config.daemon.pause_mode = PauseMode::SetsOnly,
+            _ => {}
+        }
+    }
+
+    if let Some(val) = var("CANDELA_ON_BACKEND_FAILURE") {
+        match val.to_lowercase().as_str() {
+            "continue" => config.daemon.on_backend_failure = BackendFailure::Continue,
+            "exit" => config.daemon.on_backend_failure = BackendFailure::Exit,
+            _ => {}
+        }
+    }
+
+    if let Some(val) = var("CANDELA_STATUS_FORMAT") {
+        match val.to_lowercase().as_str() {
+            "text" => config.daemon.status_format = StatusFormat::Text,
+            "json" => config.daemon.status_format = StatusFormat::Json,
+            _ => {}
+        }
+    }
+}
+
+/// Renders `config` as the `CANDELA_*=value` lines `apply_env` understands,
+/// e.g. for a systemd `EnvironmentFile`. Unset options and options without an
+/// environment variable are listed as comments.
+#[allow(clippy::too_many_lines, reason = "one line per environment variable")]
+pub fn env_lines(config: &Config) -> Vec<String> {
+    let optional = |name: &str, value: &Option<String>| {
+        value
+            .as_ref()
+            .map_or_else(|| format!("# {name} is unset"), |v| env_line(name, v))
+    };
+
+    vec![
+        env_line("CANDELA_MODE", serde_name(&config.mode)),
+        env_line("CANDELA_LATITUDE", config.location.latitude),
+        env_line("CANDELA_LONGITUDE", config.location.longitude),
+        optional("CANDELA_CITY", &config.location.city),
+        env_line("CANDELA_LOCATION_DETECT", config.location.detect),
+        env_line(
+            "CANDELA_LOCATION_FROM_TIMEZONE",
+            config.location.from_timezone,
+        ),
+        env_line("CANDELA_SUN_ELEVATION", config.auto.sun_elevation_degrees),
+        optional("CANDELA_WEEKEND_WAKEUP", &config.auto.weekend_wakeup),
+        optional("CANDELA_EVENING_START", &config.auto.evening_start),
+        env_line("CANDELA_MIN_NIGHT_MINUTES", config.auto.min_night_minutes),
+        env_line("CANDELA_POLAR", serde_name(&config.auto.polar)),
+        env_line("CANDELA_WAKEUP", &config.schedule.wakeup),
+        env_line("CANDELA_BEDTIME", &config.schedule.bedtime),
+        optional("CANDELA_SCHEDULE_FILE", &config.schedule.file),
+        match config.transition.duration_minutes {
+            DurationMinutes::Both(minutes) => env_line("CANDELA_TRANSITION_DURATION", minutes),
+            DurationMinutes::PerPhase(durations) => format!(
+                "# CANDELA_TRANSITION_DURATION can't express to_day = {}, to_night = {}",
+                durations.to_day, durations.to_night
+            ),
+        },
+        env_line("CANDELA_EASING", &config.transition.easing),
+        env_line(
+            "CANDELA_AUTO_SMOOTH_ENDPOINTS",
+            config.transition.auto_smooth_endpoints,
+        ),
+        env_line(
+            "CANDELA_TEMPERATURE_STEP",
+            config.transition.temperature_step,
+        ),
+        env_line(
+            "CANDELA_TEMPERATURE_STEP_ROUNDING",
+            serde_name(&config.transition.temperature_step_rounding),
+        ),
+        env_line("CANDELA_DAY_TEMP", config.temperature.day),
+        env_line("CANDELA_NIGHT_TEMP", config.temperature.night),
+        config.temperature.night_offset.map_or_else(
+            || "# CANDELA_NIGHT_OFFSET is unset".to_string(),
+            |offset| env_line("CANDELA_NIGHT_OFFSET", offset),
+        ),
+        env_line("CANDELA_TICK_INTERVAL", config.daemon.tick_interval_seconds),
+        env_line("CANDELA_STATUS_FILE", &config.daemon.status_file),
+        env_line("CANDELA_OPTIMIZE_UPDATES", config.daemon.optimize_updates),
+        env_line("CANDELA_MIN_UPDATE_DELTA", config.daemon.min_update_delta),
+        env_line(
+            "CANDELA_STATUS_UPDATE_INTERVAL",
+            config.daemon.status_update_interval,
+        ),
+        config.daemon.status_update_interval_seconds.map_or_else(
+            || "# CANDELA_STATUS_UPDATE_INTERVAL_SECONDS is unset".to_string(),
+            |seconds| env_line("CANDELA_STATUS_UPDATE_INTERVAL_SECONDS", seconds),
+        ),
+        env_line("CANDELA_STATE_FILE", &config.daemon.state_file),
+        env_line("CANDELA_PAUSE_MODE", serde_name(&config.daemon.pause_mode)),
+        env_line(
+            "CANDELA_STATUS_FORMAT",
+            serde_name(&config.daemon.status_format),
+        ),
+        optional("CANDELA_HISTORY_FILE", &config.daemon.history_file),
+        env_line("CANDELA_HISTORY_MAX_LINES", config.daemon.history_max_lines),
+        env_line(
+            "CANDELA_LOCK_DETECTION",
+            serde_name(&config.daemon.lock_detection),
+        ),
+        env_line("CANDELA_LOCK_COMMAND", &config.daemon.lock_command),
+        env_line(
+            "CANDELA_HYPRCTL_TIMEOUT",
+            config.daemon.hyprctl_timeout_seconds,
+        ),
+        env_line("CANDELA_STARTUP_GRACE", config.daemon.startup_grace_seconds),
+        env_line(
+            "CANDELA_INITIAL_SET_ATTEMPTS",
+            config.daemon.initial_set_attempts,
+        ),
+        env_line(
+            "CANDELA_ON_BACKEND_FAILURE",
+            serde_name(&config.daemon.on_backend_failure),
+        ),
+        env_line("CANDELA_ALIGN_TO_MINUTE", config.daemon.align_to_minute),
+        env_line("CANDELA_WATCH_CONFIG", config.daemon.watch_config),
+        env_line("CANDELA_RESTORE_ON_EXIT", config.daemon.restore_on_exit),
+        config.daemon.restore_temperature.map_or_else(
+            || "# CANDELA_RESTORE_TEMPERATURE is unset".to_string(),
+            |kelvin| env_line("CANDELA_RESTORE_TEMPERATURE", kelvin),
+        ),
+        format!(
+            "# daemon.max_hyprctl_in_flight = {} has no environment variable",
+            config.daemon.max_hyprctl_in_flight
+        ),
+        format!(
+            "# transition.easing_lut = {:?} has no environment variable",
+            config.transition.easing_lut
+        ),
+        format!(
+            "# temperature weekday overrides ({}) have no environment variable",
+            config.temperature.weekdays.len()
+        ),
+        format!(
+            "# keyframes ({}) have no environment variable",
+            config.keyframes.len()
+        ),
+        format!(
+            "# schedules ({}) have no environment variable",
+            config.schedules.len()
+        ),
+        format!(
+            "# hooks.enabled = {} has no environment variable",
+            config.hooks.enabled
+        ),
+        format!(
+            "# hooks.day = {:?} has no environment variable",
+            config.hooks.day
+        ),
+        format!(
+            "# hooks.night = {:?} has no environment variable",
+            config.hooks.night
+        ),
+        format!(
+            "# cycle.steps = {:?} has no environment variable",
+            config.cycle.steps
+        ),
+        format!(
+            "# cycle.state_file = {:?} has no environment variable",
+            config.cycle.state_file
+        ),
+        env_line(
+            "CANDELA_BATTERY_TICK_MULTIPLIER",
+            config.power.battery_tick_multiplier,
+        ),
+        config.gamma.as_ref().map_or_else(
+            || "# CANDELA_GAMMA_DAY is unset".to_string(),
+            |gamma| env_line("CANDELA_GAMMA_DAY", gamma.day),
+        ),
+        config.gamma.as_ref().map_or_else(
+            || "# CANDELA_GAMMA_NIGHT is unset".to_string(),
+            |gamma| env_line("CANDELA_GAMMA_NIGHT", gamma.night),
+        ),
+        env_line("CANDELA_BACKLIGHT_ENABLED", config.backlight.enabled),
+        env_line("CANDELA_BACKLIGHT_DAY", config.backlight.day),
+        env_line("CANDELA_BACKLIGHT_NIGHT", config.backlight.night),
+        env_line("CANDELA_BRIGHTNESS_FLOOR", config.brightness.floor),
+        env_line(
+            "CANDELA_BRIGHTNESS_ALLOW_BLACK",
+            config.brightness.allow_black,
+        ),
+    ]
+}
+
+/// `NAME=value`, double-quoting values that contain whitespace or shell
+/// metacharacters.
+fn env_line(name: &str, value: impl std::fmt::Display) -> String {
+    let value = value.to_string();
+    if value
+        .chars()
+        .any(|c| c.is_whitespace() || "\"'\\$#`".contains(c))
+    {
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("{name}=\"{escaped}\"")
+    } else {
+        format!("{name}={value}")
+    }
+}
+
+/// The serialized name of a unit enum variant (e.g. `sets_only`).
+fn serde_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_config_file_reads_as_none() {
+        let path = std::env::temp_dir().join("candela-no-such-config.toml");
+
+        let result = read_config_file(&path.to_string_lossy());
+
+        assert!(matches!(result, Ok(None)));
+    }
+
+    #[test]
+    fn unreadable_config_file_is_an_error() {
+        // A directory exists but cannot be read as a file
+        let path = std::env::temp_dir();
+
+        let result = read_config_file(&path.to_string_lossy());
+
+        assert!(result.is_err());
+    }
+
+    fn parse_env_lines(lines: &[String]) -> std::collections::HashMap<String, String> {
+        lines
+            .iter()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, value)| {
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .map_or_else(
+                        || value.to_string(),
+                        |v| v.replace("\\\"", "\"").replace("\\\\", "\\"),
+                    );
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn env_lines_round_trip_through_apply_env() {
+        let mut config = Config::default();
+        config.mode = Mode::Fixed;
+        config.location.latitude = 48.516;
+        config.auto.weekend_wakeup = Some("09:30".to_string());
+        config.transition.easing = "ease_in_out".to_string();
+        config.transition.temperature_step_rounding = StepRounding::Ceil;
+        config.temperature.night = 2700;
+        config.daemon.pause_mode = PauseMode::SetsOnly;
+        config.daemon.lock_detection = LockDetection::Command;
+        config.daemon.lock_command = r#"pgrep -x "hyprlock""#.to_string();
+
+        let lines = env_lines(&config);
+        assert!(lines.contains(&"CANDELA_MODE=fixed".to_string()));
+        assert!(lines.contains(&"CANDELA_PAUSE_MODE=sets_only".to_string()));
+        assert!(lines.contains(&r#"CANDELA_LOCK_COMMAND="pgrep -x \"hyprlock\"""#.to_string()));
+        assert!(lines.contains(&"# CANDELA_HISTORY_FILE is unset".to_string()));
+        assert!(lines.iter().any(|line| {
+            line.starts_with("# hooks.day") && line.ends_with("no environment variable")
+        }));
+
+        let vars = parse_env_lines(&lines);
+        let mut restored = Config::default();
+        apply_env_with(&mut restored, |name| vars.get(name).cloned());
+
+        assert_eq!(restored.mode, config.mode);
+        assert_eq!(restored.location, config.location);
+        assert_eq!(restored.auto, config.auto);
+        assert_eq!(restored.schedule, config.schedule);
+        assert_eq!(restored.transition, config.transition);
+        assert_eq!(restored.temperature, config.temperature);
+        assert_eq!(restored.daemon.pause_mode, config.daemon.pause_mode);
+        assert_eq!(restored.daemon.lock_detection, config.daemon.lock_detection);
+        assert_eq!(restored.daemon.lock_command, config.daemon.lock_command);
+        assert_eq!(restored.daemon.history_file, config.daemon.history_file);
+    }
+
+    #[test]
+    fn file_hash_tracks_contents() {
+        let path = std::env::temp_dir().join(format!("candela-hash-{}.toml", std::process::id()));
+        let path_str = path.to_string_lossy().into_owned();
+
+        std::fs::write(&path, "mode = \"auto\"\n").unwrap();
+        let before = file_hash(Some(&path_str));
+        assert_eq!(file_hash(Some(&path_str)), before);
+
+        std::fs::write(&path, "mode = \"fixed\"\n").unwrap();
+        let after = file_hash(Some(&path_str));
+        let _ = std::fs::remove_file(&path);
+
+        assert_ne!(before, after);
+        assert_eq!(file_hash(None), file_hash(Some(&path_str)));
+    }
+
+    #[test]
+    fn weekday_temperature_override_applies_only_on_its_day() {
+        let config: Config = toml::from_str(
+            "[temperature]\nday = 6500\nnight = 1500\n\n[temperature.monday]\nday = 5000\n",
+        )
+        .unwrap();
+
+        let monday = config.temperature.on(Weekday::Mon);
+        assert_eq!((monday.day, monday.night), (5000, 1500));
+        let tuesday = config.temperature.on(Weekday::Tue);
+        assert_eq!((tuesday.day, tuesday.night), (6500, 1500));
+    }
+
+    #[test]
+    fn relative_night_follows_the_day_in_effect() {
+        let mut config: Config = toml::from_str(
+            "[temperature]\nday = 6500\nnight_offset = -4500\n\n[temperature.monday]\n\
+             day = 5500\n\n[temperature.friday]\nday = 4000\nnight = 3000\n",
+        )
+        .unwrap();
+        assert_eq!(config.temperature.validate_night_offset(), Ok(()));
+        assert_eq!(config.temperature.resolved_night(), 2000);
+
+        let night = |temperature: &Temperature, weekday| temperature.on(weekday).night;
+        assert_eq!(night(&config.temperature, Weekday::Tue), 2000);
+        assert_eq!(night(&config.temperature, Weekday::Mon), 1000);
+        assert_eq!(night(&config.temperature, Weekday::Fri), 3000);
+
+        config
+            .temperature
+            .weekdays
+            .get_mut(&Weekday::Mon)
+            .unwrap()
+            .day = Some(5000);
+        let err = config.temperature.validate_night_offset().unwrap_err();
+        assert!(err.contains("500K"), "{err}");
+    }
+
+    #[test]
+    fn out_of_range_temperatures_are_clamped_with_a_warning() {
+        let mut config: Config = toml::from_str(
+            "[temperature]\nday = 25000\nnight = 500\n\n[temperature.friday]\nnight = 20001\n",
+        )
+        .unwrap();
+
+        let warnings = config.validate();
+        assert_eq!(config.temperature.day, 20000);
+        assert_eq!(config.temperature.night, 1000);
+        assert_eq!(config.temperature.on(Weekday::Fri).night, 20000);
+        assert_eq!(
+            warnings[0],
+            "temperature.day = 25000K is outside hyprsunset's 1000-20000K range; using 20000K"
+        );
+        assert!(warnings[2].starts_with("temperature.friday.night = 20001K"));
+        // Friday's night clamps to 20000K, which is still at or below its day
+        assert_eq!(warnings.len(), 3);
+    }
+
+    #[test]
+    fn every_temperature_is_clamped() {
+        let mut config: Config = toml::from_str(
+            "keyframes = [{ time = \"07:00\", temperature = 900 }]\n\n\
+             [schedule]\nstops = [{ time = \"21:00\", temperature = 30000 }]\n\n\
+             [daemon]\nrestore_temperature = 100\n\n\
+             [[schedules]]\nname = \"desk\"\n\
+             [schedules.temperature]\nnight = 500\n\
+             [schedules.schedule]\nstops = [{ time = \"22:00\", temperature = 25000 }]\n",
+        )
+        .unwrap();
+
+        let warnings = config.validate();
+        assert_eq!(config.keyframes[0].temperature, 1000);
+        assert_eq!(config.schedule.stops[0].temperature, 20000);
+        assert_eq!(config.daemon.restore_temperature, Some(1000));
+        let desk = &config.schedules[0];
+        assert_eq!(desk.temperature.as_ref().map(|t| t.night), Some(1000));
+        assert_eq!(
+            desk.schedule.as_ref().map(|s| s.stops[0].temperature),
+            Some(20000)
+        );
+        let names: Vec<_> = warnings
+            .iter()
+            .filter_map(|warning| warning.split(' ').next())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "keyframes[0].temperature",
+                "schedule.stops[0].temperature",
+                "daemon.restore_temperature",
+                "schedules[0].temperature.night",
+                "schedules[0].schedule.stops[0].temperature",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_zero_brightness_floor_needs_allow_black() {
+        let mut config: Config = toml::from_str("[brightness]\nfloor = 0\n").unwrap();
+        let warnings = config.validate();
+        assert_eq!(config.brightness.floor, crate::hyprctl::DEFAULT_GAMMA_FLOOR);
+        assert_eq!(warnings.len(), 1);
+
+        let mut config: Config =
+            toml::from_str("[brightness]\nfloor = 0\nallow_black = true\n").unwrap();
+        assert!(config.validate().is_empty());
+        assert_eq!(config.brightness.floor, 0);
+    }
+
+    #[test]
+    fn zero_hyprctl_timeout_is_raised_to_a_second() {
+        let mut config: Config = toml::from_str("[daemon]\nhyprctl_timeout_seconds = 0\n").unwrap();
+
+        let warnings = config.validate();
+        assert_eq!(config.daemon.hyprctl_timeout_seconds, 1);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("daemon.hyprctl_timeout_seconds = 0"));
+    }
+
+    #[test]
+    fn night_above_day_warns_without_failing() {
+        let mut config: Config = toml::from_str(
+            "[temperature]\nday = 4000\nnight = 5000\n\n[temperature.sunday]\nday = 6000\n",
+        )
+        .unwrap();
+
+        let warnings = config.validate();
+        assert_eq!(
+            warnings,
+            ["Night (5000K) is above day (4000K); nights will be bluer than days"]
+        );
+        assert_eq!(
+            (config.temperature.day, config.temperature.night),
+            (4000, 5000)
+        );
+
+        let mut off: Config = toml::from_str("[temperature]\nday = 0\nnight = 3000\n").unwrap();
+        assert!(off.validate().is_empty());
+        assert!(Config::default().validate().is_empty());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected_with_their_line() {
+        for (content, field) in [
+            ("mode = \"fixed\"\ntemprature = 3000\n", "temprature"),
+            (
+                "[schedule]\nwakeup = \"07:00\"\nbedtim = \"22:00\"\n",
+                "bedtim",
+            ),
+            ("[temperature]\nday = 6500\nnigth = 2000\n", "nigth"),
+            ("[temperature.friday]\ndya = 5000\n", "dya"),
+            ("[[keyframes]]\ntime = \"07:00\"\ntemp = 3000\n", "temp"),
+        ] {
+            let error = toml::from_str::<Config>(content).unwrap_err().to_string();
+            assert!(error.contains(field), "{error}");
+            assert!(error.contains("line"), "{error}");
+        }
+    }
+
+    #[test]
+    fn duration_minutes_is_a_scalar_or_per_phase_table() {
+        let scalar: Config = toml::from_str("[transition]\nduration_minutes = 45\n").unwrap();
+        assert_eq!(
+            scalar.transition.duration_minutes,
+            DurationMinutes::Both(45)
+        );
+        assert_eq!(scalar.transition.duration_minutes.to_night(), 45);
+
+        let content = "[transition]\nduration_minutes = { to_day = 20, to_night = 90 }\n";
+        let per_phase: Config = toml::from_str(content).unwrap();
+        let minutes = per_phase.transition.duration_minutes;
+        assert_eq!(
+            (minutes.to_day(), minutes.to_night(), minutes.longest()),
+            (20, 90, 90)
+        );
+        let printed = toml::to_string(&per_phase).unwrap();
+        let reparsed: Config = toml::from_str(&printed).unwrap();
+        assert_eq!(reparsed.transition, per_phase.transition);
+        assert!(env_lines(&per_phase)
+            .iter()
+            .any(|line| line.starts_with("# CANDELA_TRANSITION")));
+
+        for content in [
+            "[transition]\nduration_minutes = { to_day = 20, to_nigth = 90 }\n",
+            "[transition]\nduration_minutes = { to_day = 20 }\n",
+            "[transition]\nduration_minutes = -5\n",
+        ] {
+            assert!(toml::from_str::<Config>(content).is_err(), "{content}");
+        }
+        let typo = "[transition]\nduration_minutes = { to_day = 20, to_nigth = 90 }\n";
+        let error = toml::from_str::<Config>(typo).unwrap_err().to_string();
+        assert!(error.contains("to_nigth"), "{error}");
+    }
+
+    #[test]
+    fn malformed_config_is_an_error() {
+        let path = std::env::temp_dir().join(format!("candela-bad-{}.toml", std::process::id()));
+        let path_str = path.to_string_lossy().into_owned();
+
+        std::fs::write(&path, "[temperature]\nday = \"warm\"\n").unwrap();
+        let malformed = load(Some(&path_str), false);
+        std::fs::write(&path, "[temperature]\nday = 5000\n").unwrap();
+        let valid = load(Some(&path_str), false);
+        let _ = std::fs::remove_file(&path);
+
+        let error = malformed.unwrap_err();
+        assert!(error.contains(&path_str), "{error}");
+        assert!(error.contains("line 2"), "{error}");
+        assert!(valid.is_ok());
+    }
+
+    #[test]
+    fn config_path_resolves_in_order() {
+        let home = std::env::temp_dir().join(format!("candela-xdg-{}", std::process::id()));
+        std::fs::create_dir_all(home.join("candela")).unwrap();
+        let xdg_config = home.join("candela/config.toml");
+        std::fs::write(&xdg_config, "").unwrap();
+
+        let env = |candela: Option<&str>| {
+            let home = home.clone();
+            let candela = candela.map(std::ffi::OsString::from);
+            move |name: &str| match name {
+                "CANDELA_CONFIG" => candela.clone(),
+                "XDG_CONFIG_HOME" => Some(home.clone().into_os_string()),
+                _ => None,
+            }
+        };
+        let fallback = || Some(PathBuf::from("candela.toml"));
+        let resolve = |flag, dir, candela| resolve_path_with(flag, dir, env(candela), fallback);
+
+        let flag = resolve(Some("a.toml"), Some("/etc/candela"), Some("b.toml"));
+        assert_eq!(flag, Some((PathBuf::from("a.toml"), true)));
+        let dir = resolve(None, Some("/etc/candela"), Some("b.toml"));
+        assert_eq!(dir, Some((PathBuf::from("/etc/candela/config.toml"), true)));
+        let candela = resolve(None, None, Some("b.toml"));
+        assert_eq!(candela, Some((PathBuf::from("b.toml"), true)));
+        assert_eq!(resolve(None, None, None), Some((xdg_config.clone(), false)));
+
+        std::fs::remove_file(&xdg_config).unwrap();
+        let missing = resolve(None, None, Some(""));
+        let _ = std::fs::remove_dir_all(&home);
+        assert_eq!(missing, Some((PathBuf::from("candela.toml"), false)));
+    }
+
+    #[test]
+    fn explicit_missing_config_is_an_error() {
+        let path = std::env::temp_dir().join("candela-no-such-config.toml");
+        let path = path.to_string_lossy();
+
+        assert!(load(Some(&path), true).is_err());
+        assert!(load(Some(&path), false).is_ok());
+        assert!(load(None, false).is_ok());
+    }
 }
@@ -0,0 +1,454 @@
+use std::io::{Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+/// Where the system bus listens unless `DBUS_SYSTEM_BUS_ADDRESS` says otherwise.
+const SYSTEM_BUS_SOCKET: &str = "/run/dbus/system_bus_socket";
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 8;
+
+/// A method call or reply argument. Only the types geoclue needs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Path(String),
+    U32(u32),
+    F64(f64),
+    Variant(Box<Self>),
+}
+
+impl Value {
+    const fn signature(&self) -> &'static str {
+        match self {
+            Self::Str(_) => "s",
+            Self::Path(_) => "o",
+            Self::U32(_) => "u",
+            Self::F64(_) => "d",
+            Self::Variant(_) => "v",
+        }
+    }
+}
+
+/// A method to call: destination, object path, interface and member.
+pub struct Call<'a> {
+    pub destination: &'a str,
+    pub path: &'a str,
+    pub interface: &'a str,
+    pub member: &'a str,
+}
+
+/// A blocking connection to the system bus that speaks just enough of the
+/// wire protocol (little-endian, `EXTERNAL` auth) for simple method calls.
+pub struct Connection<S = UnixStream> {
+    stream: S,
+    serial: u32,
+}
+
+impl Connection {
+    /// Connects and authenticates to the system bus. Every read gives up
+    /// after `timeout`.
+    pub fn system(timeout: Duration) -> Option<Self> {
+        let address = std::env::var("DBUS_SYSTEM_BUS_ADDRESS").ok();
+        let path = address
+            .as_deref()
+            .and_then(|address| address.strip_prefix("unix:path="))
+            .map_or(SYSTEM_BUS_SOCKET, |path| {
+                path.split(',').next().unwrap_or(path)
+            });
+        let stream = UnixStream::connect(path).ok()?;
+        stream.set_read_timeout(Some(timeout)).ok()?;
+        let uid = std::fs::metadata("/proc/self").ok()?.uid();
+        let mut connection = Self { stream, serial: 0 };
+        connection.authenticate(uid)?;
+        connection.call(
+            &Call {
+                destination: "org.freedesktop.DBus",
+                path: "/org/freedesktop/DBus",
+                interface: "org.freedesktop.DBus",
+                member: "Hello",
+            },
+            &[],
+        )?;
+        Some(connection)
+    }
+}
+
+impl<S: Read + Write> Connection<S> {
+    fn authenticate(&mut self, uid: u32) -> Option<()> {
+        // The uid's decimal digits, hex-encoded: ASCII '0'-'9' are 0x30-0x39
+        let hex_uid: String = uid
+            .to_string()
+            .chars()
+            .flat_map(|digit| ['3', digit])
+            .collect();
+        self.stream
+            .write_all(format!("\0AUTH EXTERNAL {hex_uid}\r\n").as_bytes())
+            .ok()?;
+        self.read_line()?.starts_with("OK ").then_some(())?;
+        self.stream.write_all(b"BEGIN\r\n").ok()
+    }
+
+    /// One `\r\n`-terminated line of the auth handshake, read bytewise so
+    /// nothing of the first message is consumed.
+    fn read_line(&mut self) -> Option<String> {
+        let mut line = Vec::new();
+        let mut byte = [0];
+        while !line.ends_with(b"\r\n") {
+            self.stream.read_exact(&mut byte).ok()?;
+            line.push(byte[0]);
+        }
+        String::from_utf8(line).ok()
+    }
+
+    /// Calls `call` with `args` and returns the reply's arguments, or `None`
+    /// on an error reply or a broken connection. Signals in between are
+    /// skipped.
+    pub fn call(&mut self, call: &Call, args: &[Value]) -> Option<Vec<Value>> {
+        self.serial += 1;
+        self.stream
+            .write_all(&method_call(self.serial, call, args))
+            .ok()?;
+        loop {
+            let mut fixed = [0; 16];
+            self.stream.read_exact(&mut fixed).ok()?;
+            let header_len = align(16 + usize::try_from(u32_at(&fixed, 12)?).ok()?, 8);
+            let body_len = usize::try_from(u32_at(&fixed, 4)?).ok()?;
+            let mut message = fixed.to_vec();
+            message.resize(header_len + body_len, 0);
+            self.stream.read_exact(&mut message[16..]).ok()?;
+            let reply = parse_message(&message)?;
+            if reply.reply_serial == Some(self.serial) {
+                return (reply.kind == METHOD_RETURN).then_some(reply.body);
+            }
+        }
+    }
+}
+
+const fn align(offset: usize, alignment: usize) -> usize {
+    offset.next_multiple_of(alignment)
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// A method call message with `serial`. Alignment is relative to the start
+/// of the message, which the header fields and body both rely on.
+fn method_call(serial: u32, call: &Call, args: &[Value]) -> Vec<u8> {
+    let mut message = vec![b'l', METHOD_CALL, 0, 1, 0, 0, 0, 0];
+    message.extend_from_slice(&serial.to_le_bytes());
+    message.extend_from_slice(&[0; 4]);
+    let signature: String = args.iter().map(Value::signature).collect();
+    let mut fields = vec![
+        (FIELD_PATH, Value::Path(call.path.to_string())),
+        (FIELD_DESTINATION, Value::Str(call.destination.to_string())),
+        (FIELD_INTERFACE, Value::Str(call.interface.to_string())),
+        (FIELD_MEMBER, Value::Str(call.member.to_string())),
+    ];
+    if !signature.is_empty() {
+        fields.push((FIELD_SIGNATURE, Value::Str(signature)));
+    }
+    for (code, value) in fields {
+        pad(&mut message, 8);
+        message.push(code);
+        let signature = if code == FIELD_SIGNATURE {
+            "g"
+        } else {
+            value.signature()
+        };
+        write_signature(&mut message, signature);
+        match (code, value) {
+            (FIELD_SIGNATURE, Value::Str(signature)) => write_signature(&mut message, &signature),
+            (_, value) => write_value(&mut message, &value),
+        }
+    }
+    let fields_len = u32::try_from(message.len() - 16).unwrap_or(u32::MAX);
+    message[12..16].copy_from_slice(&fields_len.to_le_bytes());
+    pad(&mut message, 8);
+    let body_start = message.len();
+    for arg in args {
+        write_value(&mut message, arg);
+    }
+    let body_len = u32::try_from(message.len() - body_start).unwrap_or(u32::MAX);
+    message[4..8].copy_from_slice(&body_len.to_le_bytes());
+    message
+}
+
+fn pad(message: &mut Vec<u8>, alignment: usize) {
+    message.resize(align(message.len(), alignment), 0);
+}
+
+fn write_signature(message: &mut Vec<u8>, signature: &str) {
+    message.push(u8::try_from(signature.len()).unwrap_or(u8::MAX));
+    message.extend_from_slice(signature.as_bytes());
+    message.push(0);
+}
+
+fn write_value(message: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Str(s) | Value::Path(s) => {
+            pad(message, 4);
+            message.extend_from_slice(&u32::try_from(s.len()).unwrap_or(u32::MAX).to_le_bytes());
+            message.extend_from_slice(s.as_bytes());
+            message.push(0);
+        }
+        Value::U32(n) => {
+            pad(message, 4);
+            message.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::F64(x) => {
+            pad(message, 8);
+            message.extend_from_slice(&x.to_le_bytes());
+        }
+        Value::Variant(inner) => {
+            write_signature(message, inner.signature());
+            write_value(message, inner);
+        }
+    }
+}
+
+/// The parts of an incoming message that matter to `Connection::call`.
+struct Message {
+    kind: u8,
+    reply_serial: Option<u32>,
+    body: Vec<Value>,
+}
+
+fn parse_message(message: &[u8]) -> Option<Message> {
+    if message.first() != Some(&b'l') {
+        return None;
+    }
+    let kind = *message.get(1)?;
+    let fields_end = 16 + usize::try_from(u32_at(message, 12)?).ok()?;
+    let mut reader = Reader {
+        bytes: message.get(..fields_end)?,
+        pos: 16,
+    };
+    let (mut reply_serial, mut signature) = (None, String::new());
+    while reader.pos < fields_end {
+        reader.align(8);
+        let code = reader.byte()?;
+        match (code, reader.value("v")?) {
+            (FIELD_REPLY_SERIAL, Value::Variant(value)) => {
+                if let Value::U32(serial) = *value {
+                    reply_serial = Some(serial);
+                }
+            }
+            (FIELD_SIGNATURE, Value::Variant(value)) => {
+                if let Value::Str(body_signature) = *value {
+                    signature = body_signature;
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut body = Vec::new();
+    if kind == METHOD_RETURN || kind == ERROR {
+        let mut reader = Reader {
+            bytes: message,
+            pos: align(fields_end, 8),
+        };
+        for code in signature.chars() {
+            body.push(reader.value(code.encode_utf8(&mut [0; 4]))?);
+        }
+    }
+    Some(Message {
+        kind,
+        reply_serial,
+        body,
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    const fn align(&mut self, alignment: usize) {
+        self.pos = align(self.pos, alignment);
+    }
+
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.align(4);
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn string(&mut self, len: usize) -> Option<String> {
+        let s = String::from_utf8(self.take(len)?.to_vec()).ok();
+        self.take(1)?;
+        s
+    }
+
+    /// A value of the single complete type `signature`; `g` reads as a
+    /// string.
+    fn value(&mut self, signature: &str) -> Option<Value> {
+        match signature {
+            "s" | "o" => {
+                let len = usize::try_from(self.u32()?).ok()?;
+                let s = self.string(len)?;
+                Some(if signature == "o" {
+                    Value::Path(s)
+                } else {
+                    Value::Str(s)
+                })
+            }
+            "g" => {
+                let len = usize::from(self.byte()?);
+                self.string(len).map(Value::Str)
+            }
+            "u" => self.u32().map(Value::U32),
+            "d" => {
+                self.align(8);
+                Some(Value::F64(f64::from_le_bytes(
+                    self.take(8)?.try_into().ok()?,
+                )))
+            }
+            "v" => {
+                let len = usize::from(self.byte()?);
+                let inner = self.string(len)?;
+                Some(Value::Variant(Box::new(self.value(&inner)?)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reply to `serial` carrying `args`, laid out like the bus sends it.
+    fn reply(serial: u32, args: &[Value]) -> Vec<u8> {
+        let call = Call {
+            destination: "x",
+            path: "/",
+            interface: "x",
+            member: "x",
+        };
+        let mut message = method_call(7, &call, args);
+        message[1] = METHOD_RETURN;
+        // Swap the call's header fields for a reply serial and the signature
+        let body = message.split_off(align(16 + u32_at(&message, 12).unwrap() as usize, 8));
+        message.truncate(16);
+        let signature: String = args.iter().map(Value::signature).collect();
+        message.push(FIELD_REPLY_SERIAL);
+        write_signature(&mut message, "u");
+        write_value(&mut message, &Value::U32(serial));
+        pad(&mut message, 8);
+        message.push(FIELD_SIGNATURE);
+        write_signature(&mut message, "g");
+        write_signature(&mut message, &signature);
+        let fields_len = u32::try_from(message.len() - 16).unwrap();
+        message[12..16].copy_from_slice(&fields_len.to_le_bytes());
+        pad(&mut message, 8);
+        message.extend(body);
+        message
+    }
+
+    #[test]
+    fn method_calls_are_laid_out_with_aligned_fields() {
+        let call = Call {
+            destination: "org.freedesktop.DBus",
+            path: "/org/freedesktop/DBus",
+            interface: "org.freedesktop.DBus",
+            member: "Hello",
+        };
+        let message = method_call(1, &call, &[]);
+        assert_eq!(&message[..4], &[b'l', METHOD_CALL, 0, 1]);
+        assert_eq!(u32_at(&message, 4), Some(0));
+        assert_eq!(u32_at(&message, 8), Some(1));
+        assert_eq!(message.len() % 8, 0);
+        // The path field: code, signature "o", then the length-prefixed path
+        assert_eq!(&message[16..20], &[FIELD_PATH, 1, b'o', 0]);
+        assert_eq!(u32_at(&message, 20), Some(21));
+        assert_eq!(&message[24..45], b"/org/freedesktop/DBus");
+
+        let set = method_call(2, &call, &[Value::Variant(Box::new(Value::U32(4)))]);
+        assert_eq!(u32_at(&set, 4), Some(8));
+        assert_eq!(&set[set.len() - 8..], &[1, b'u', 0, 0, 4, 0, 0, 0]);
+    }
+
+    #[test]
+    fn replies_are_parsed_with_their_serial_and_arguments() {
+        let args = [
+            Value::Path("/org/freedesktop/GeoClue2/Client/1".to_string()),
+            Value::Variant(Box::new(Value::F64(52.52))),
+        ];
+        let message = parse_message(&reply(3, &args)).unwrap();
+        assert_eq!(
+            (message.kind, message.reply_serial),
+            (METHOD_RETURN, Some(3))
+        );
+        assert_eq!(message.body, args);
+    }
+
+    #[test]
+    fn calls_skip_other_messages_until_their_reply() {
+        struct Script {
+            replies: std::io::Cursor<Vec<u8>>,
+            sent: Vec<u8>,
+        }
+        impl Read for Script {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.replies.read(buf)
+            }
+        }
+        impl Write for Script {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.sent.write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut replies = b"OK 1234deadbeef\r\n".to_vec();
+        replies.extend(reply(9, &[Value::Str("unrelated".to_string())]));
+        replies.extend(reply(1, &[Value::Str(":1.42".to_string())]));
+        let stream = Script {
+            replies: std::io::Cursor::new(replies),
+            sent: Vec::new(),
+        };
+        let mut connection = Connection { stream, serial: 0 };
+
+        assert_eq!(connection.authenticate(1000), Some(()));
+        assert!(connection
+            .stream
+            .sent
+            .starts_with(b"\0AUTH EXTERNAL 31303030\r\nBEGIN\r\n"));
+        let call = Call {
+            destination: "x",
+            path: "/",
+            interface: "x",
+            member: "Hello",
+        };
+        assert_eq!(
+            connection.call(&call, &[]),
+            Some(vec![Value::Str(":1.42".to_string())])
+        );
+        assert_eq!(connection.call(&call, &[]), None);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::config::{Config, Mode};
+use crate::{hyprctl, scheduler, state, transition};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Outcome {
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Pass => "pass",
+            Self::Warn => "warn",
+            Self::Fail => "FAIL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, outcome: Outcome, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Runs every installation check against `config` (loaded from `config_path`,
+/// or the defaults when loading failed with `load_error`).
+pub fn run(config: &Config, config_path: Option<&str>, load_error: Option<&str>) -> Vec<Check> {
+    vec![
+        executable_check("hyprctl"),
+        executable_check("hyprsunset"),
+        hyprsunset_running_check(),
+        config_file_check(config_path, load_error),
+        schedule_check(config),
+        coordinates_check(config),
+        easing_check(config),
+        writable_check("state file", &config.daemon.state_file),
+        writable_check("status file", &config.daemon.status_file),
+    ]
+}
+
+/// Whether no hard check failed; warnings don't count.
+pub fn passed(checks: &[Check]) -> bool {
+    checks.iter().all(|check| check.outcome != Outcome::Fail)
+}
+
+pub fn render(checks: &[Check]) -> String {
+    checks
+        .iter()
+        .map(|check| {
+            format!(
+                "[{}] {}: {}",
+                check.outcome.label(),
+                check.name,
+                check.detail
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The checklist as a JSON object, for attaching to bug reports.
+pub fn render_json(checks: &[Check]) -> String {
+    let all_passed = passed(checks);
+    let checks: Vec<_> = checks
+        .iter()
+        .map(|check| {
+            serde_json::json!({
+                "name": check.name,
+                "outcome": check.outcome.label().to_lowercase(),
+                "detail": check.detail,
+            })
+        })
+        .collect();
+    serde_json::json!({ "passed": all_passed, "checks": checks }).to_string()
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+fn executable_check(program: &'static str) -> Check {
+    if on_path(program) {
+        Check::new(program, Outcome::Pass, "found on PATH")
+    } else {
+        Check::new(program, Outcome::Fail, "not found on PATH")
+    }
+}
+
+fn hyprsunset_running_check() -> Check {
+    if hyprctl::is_hyprsunset_running() {
+        Check::new("hyprsunset running", Outcome::Pass, "running")
+    } else {
+        Check::new(
+            "hyprsunset running",
+            Outcome::Warn,
+            "not running (the daemon starts it)",
+        )
+    }
+}
+
+fn config_file_check(config_path: Option<&str>, load_error: Option<&str>) -> Check {
+    if let Some(error) = load_error {
+        return Check::new("config file", Outcome::Fail, error);
+    }
+    match config_path {
+        Some(path) if Path::new(path).is_file() => Check::new("config file", Outcome::Pass, path),
+        Some(path) => Check::new(
+            "config file",
+            Outcome::Warn,
+            format!("{path} not found, using defaults"),
+        ),
+        None => Check::new("config file", Outcome::Warn, "none found, using defaults"),
+    }
+}
+
+fn schedule_check(config: &Config) -> Check {
+    match scheduler::Schedule::new(config.clone()) {
+        Ok(_) => Check::new("config valid", Outcome::Pass, "schedule parses"),
+        Err(e) => Check::new("config valid", Outcome::Fail, e),
+    }
+}
+
+fn coordinates_check(config: &Config) -> Check {
+    let (lat, lon) = (config.location.latitude, config.location.longitude);
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        Check::new(
+            "coordinates",
+            Outcome::Fail,
+            format!("{lat}, {lon} is out of range (latitude -90..90, longitude -180..180)"),
+        )
+    } else if config.mode == Mode::Auto && lat == 0.0 && lon == 0.0 {
+        Check::new(
+            "coordinates",
+            Outcome::Warn,
+            "latitude/longitude are 0,0; sunrise/sunset won't match your location",
+        )
+    } else if config.mode == Mode::Auto && config.location.from_timezone && lat == 0.0 {
+        Check::new(
+            "coordinates",
+            Outcome::Warn,
+            "from_timezone only sets the longitude; set latitude for your day length",
+        )
+    } else {
+        Check::new("coordinates", Outcome::Pass, format!("{lat}, {lon}"))
+    }
+}
+
+fn easing_check(config: &Config) -> Check {
+    let easing = &config.transition.easing;
+    if easing == "lut" {
+        return match transition::validate_lut(&config.transition.easing_lut) {
+            Ok(()) => Check::new("easing", Outcome::Pass, "lut"),
+            Err(e) => Check::new("easing", Outcome::Fail, e),
+        };
+    }
+    if transition::is_known_easing(easing) {
+        Check::new("easing", Outcome::Pass, easing.as_str())
+    } else {
+        Check::new(
+            "easing",
+            Outcome::Warn,
+            format!("unknown easing {easing:?}, falling back to linear"),
+        )
+    }
+}
+
+/// Checks `path`'s directory accepts new files. A directory that doesn't
+/// exist yet is checked through its nearest existing parent, which the daemon
+/// would create it under; doctor itself creates nothing.
+fn writable_check(name: &'static str, path: &str) -> Check {
+    let Some(path) = state::expand_path(path) else {
+        return Check::new(name, Outcome::Fail, format!("cannot expand {path}"));
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let Some(existing) = dir.ancestors().find(|ancestor| ancestor.is_dir()) else {
+        return Check::new(
+            name,
+            Outcome::Fail,
+            format!("{}: no existing parent", dir.display()),
+        );
+    };
+    let probe = existing.join(format!(".candela-doctor-{}", std::process::id()));
+    let result = std::fs::write(&probe, "");
+    let _ = std::fs::remove_file(&probe);
+    let detail = if existing == dir {
+        format!("{} is writable", dir.display())
+    } else {
+        format!("{} can be created in {}", dir.display(), existing.display())
+    };
+    match result {
+        Ok(()) => Check::new(name, Outcome::Pass, detail),
+        Err(e) => Check::new(name, Outcome::Fail, format!("{}: {e}", existing.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings_pass_but_failures_do_not() {
+        let pass = Check::new("a", Outcome::Pass, "ok");
+        let warn = Check::new("b", Outcome::Warn, "hmm");
+        let fail = Check::new("c", Outcome::Fail, "broken");
+
+        assert!(passed(&[pass.clone(), warn.clone()]));
+        assert!(!passed(&[pass, warn, fail]));
+        assert!(passed(&[]));
+    }
+
+    #[test]
+    fn render_lists_each_check() {
+        let checks = [
+            Check::new("hyprctl", Outcome::Pass, "found on PATH"),
+            Check::new("coordinates", Outcome::Warn, "0,0"),
+            Check::new("config valid", Outcome::Fail, "bad wakeup"),
+        ];
+
+        assert_eq!(
+            render(&checks),
+            "[pass] hyprctl: found on PATH\n\
+             [warn] coordinates: 0,0\n\
+             [FAIL] config valid: bad wakeup"
+        );
+    }
+
+    #[test]
+    fn zero_coordinates_warn_in_auto_mode() {
+        let mut config = Config::default();
+        assert_eq!(coordinates_check(&config).outcome, Outcome::Warn);
+        config.location.latitude = 48.5;
+        assert_eq!(coordinates_check(&config).outcome, Outcome::Pass);
+        config.location.longitude = 200.0;
+        assert_eq!(coordinates_check(&config).outcome, Outcome::Fail);
+
+        // from_timezone without a latitude gives the equator's day length
+        config.location = crate::location::resolve(
+            &crate::config::Location {
+                from_timezone: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
+        assert_eq!(coordinates_check(&config).outcome, Outcome::Warn);
+    }
+
+    #[test]
+    fn config_that_failed_to_load_fails() {
+        let check = config_file_check(Some("candela.toml"), Some("unknown field `temprature`"));
+        assert_eq!(check.outcome, Outcome::Fail);
+        assert_eq!(check.detail, "unknown field `temprature`");
+    }
+
+    #[test]
+    fn json_reports_each_check_and_the_verdict() {
+        let checks = [
+            Check::new("hyprctl", Outcome::Pass, "found on PATH"),
+            Check::new("config valid", Outcome::Fail, "bad wakeup"),
+        ];
+        let json: serde_json::Value = serde_json::from_str(&render_json(&checks)).unwrap();
+
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["checks"][0]["name"], "hyprctl");
+        assert_eq!(json["checks"][0]["outcome"], "pass");
+        assert_eq!(json["checks"][1]["outcome"], "fail");
+        assert_eq!(json["checks"][1]["detail"], "bad wakeup");
+    }
+
+    #[test]
+    fn unwritable_directory_fails() {
+        let check = writable_check("status file", "/proc/candela/status");
+        assert_eq!(check.outcome, Outcome::Fail);
+        let tmp = std::env::temp_dir().join("candela.status");
+        let check = writable_check("status file", &tmp.to_string_lossy());
+        assert_eq!(check.outcome, Outcome::Pass);
+    }
+
+    #[test]
+    fn missing_directory_is_checked_without_being_created() {
+        let dir = std::env::temp_dir().join(format!("candela-doctor-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("nested").join("candela.status");
+
+        let check = writable_check("status file", &path.to_string_lossy());
+        assert_eq!(check.outcome, Outcome::Pass);
+        assert!(
+            check.detail.contains("can be created in"),
+            "{}",
+            check.detail
+        );
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::config::Hooks;
+use crate::scheduler::Phase;
+use std::process::{Command, ExitStatus};
+
+/// The hook command configured for `phase` ("day" or "night"), if hooks are
+/// enabled and one is set.
+pub fn command_for<'a>(hooks: &'a Hooks, phase: &str) -> Option<&'a str> {
+    if !hooks.enabled {
+        return None;
+    }
+    let command = match phase {
+        "day" => &hooks.day,
+        "night" => &hooks.night,
+        _ => return None,
+    };
+    (!command.is_empty()).then_some(command.as_str())
+}
+
+/// The hook phase a schedule phase settles into, if it is a steady phase.
+pub const fn hook_phase(phase: Phase) -> Option<&'static str> {
+    match phase {
+        Phase::Day => Some("day"),
+        Phase::Night => Some("night"),
+        Phase::TransitioningToDay | Phase::TransitioningToNight => None,
+    }
+}
+
+/// Runs a hook command through `sh -c`, exposing the phase as `CANDELA_PHASE`,
+/// and waits for it to finish.
+pub fn fire(command: &str, phase: &str) -> std::io::Result<ExitStatus> {
+    log::debug!("Running {phase} hook: sh -c {command:?}");
+    Command::new("sh")
+        .args(["-c", command])
+        .env("CANDELA_PHASE", phase)
+        .status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hooks() -> Hooks {
+        Hooks {
+            enabled: true,
+            day: "echo day".to_string(),
+            night: String::new(),
+        }
+    }
+
+    #[test]
+    fn command_for_configured_phase() {
+        assert_eq!(command_for(&hooks(), "day"), Some("echo day"));
+        assert_eq!(command_for(&hooks(), "night"), None);
+    }
+
+    #[test]
+    fn disabled_hooks_never_fire() {
+        let mut hooks = hooks();
+        hooks.enabled = false;
+
+        assert_eq!(command_for(&hooks, "day"), None);
+    }
+
+    #[test]
+    fn fire_reports_exit_status() {
+        assert!(fire("exit 0", "day").unwrap().success());
+        assert_eq!(fire("exit 3", "night").unwrap().code(), Some(3));
+    }
+}
@@ -1,9 +1,198 @@
-use std::process::Command;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Condvar, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Sentinel temperature meaning "off": hyprsunset is reset to identity
+/// (the panel's native white point) instead of being given a Kelvin value.
+pub const OFF: u16 = 0;
+
+/// The temperature hyprsunset's identity corresponds to, used when
+/// interpolating to or from `OFF`.
+pub const IDENTITY_TEMPERATURE: u16 = 6500;
+
+fn temperature_args(kelvin: u16) -> Vec<String> {
+    if kelvin == OFF {
+        vec!["hyprsunset".to_string(), "identity".to_string()]
+    } else {
+        vec![
+            "hyprsunset".to_string(),
+            "temperature".to_string(),
+            kelvin.to_string(),
+        ]
+    }
+}
+
+fn gamma_args(percent: u8, floor: u8) -> Vec<String> {
+    vec![
+        "hyprsunset".to_string(),
+        "gamma".to_string(),
+        percent.max(floor).min(100).to_string(),
+    ]
+}
+
+/// Bounds how many closures (hyprctl invocations) run at once; callers past
+/// the limit block until a slot frees up.
+pub struct BoundedExecutor {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl BoundedExecutor {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            available: Mutex::new(limit.max(1)),
+            released: Condvar::new(),
+        }
+    }
+
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        {
+            let mut available = self
+                .available
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            while *available == 0 {
+                available = self
+                    .released
+                    .wait(available)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+            *available -= 1;
+        }
+
+        let result = f();
+
+        *self
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) += 1;
+        self.released.notify_one();
+        result
+    }
+}
+
+/// Default for `daemon.max_hyprctl_in_flight`.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+static EXECUTOR: OnceLock<BoundedExecutor> = OnceLock::new();
+
+/// Sets the process-wide hyprctl concurrency limit. Only the first call takes
+/// effect; later calls (or running without calling it) keep the current limit.
+pub fn set_max_in_flight(limit: usize) {
+    let _ = EXECUTOR.set(BoundedExecutor::new(limit));
+}
+
+fn executor() -> &'static BoundedExecutor {
+    EXECUTOR.get_or_init(|| BoundedExecutor::new(DEFAULT_MAX_IN_FLIGHT))
+}
+
+/// Default for `daemon.hyprctl_timeout_seconds`.
+pub const DEFAULT_TIMEOUT_SECONDS: u64 = 5;
+
+static TIMEOUT_SECONDS: AtomicU64 = AtomicU64::new(DEFAULT_TIMEOUT_SECONDS);
+
+/// Sets how long a hyprctl invocation may run before it is killed. Takes
+/// effect from the next invocation, so a reload can change it.
+pub fn set_timeout(timeout: Duration) {
+    TIMEOUT_SECONDS.store(timeout.as_secs(), Ordering::Relaxed);
+}
+
+/// Default for `brightness.floor`.
+pub const DEFAULT_GAMMA_FLOOR: u8 = 20;
+
+static GAMMA_FLOOR: AtomicU8 = AtomicU8::new(DEFAULT_GAMMA_FLOOR);
+
+/// Sets the lowest gamma percentage `set_gamma` sends. Takes effect from the
+/// next call, so a reload can change it.
+pub fn set_gamma_floor(percent: u8) {
+    GAMMA_FLOOR.store(percent, Ordering::Relaxed);
+}
+
+const OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Like `Command::output`, but kills the child and fails with
+/// `ErrorKind::TimedOut` if it hasn't exited within `timeout`.
+fn output_with_timeout(command: &mut Command, timeout: Duration) -> std::io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut wait_error = None;
+    let exited = poll_until(timeout, OUTPUT_POLL_INTERVAL, || match child.try_wait() {
+        Ok(status) => status.is_some(),
+        Err(e) => {
+            wait_error = Some(e);
+            true
+        }
+    });
+    if let Some(e) = wait_error {
+        return Err(e);
+    }
+    if !exited {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("timed out after {}ms", timeout.as_millis()),
+        ));
+    }
+    child.wait_with_output()
+}
 
 pub fn set_temperature(kelvin: u16) -> Result<(), Box<dyn std::error::Error>> {
-    let output = Command::new("hyprctl")
-        .args(["hyprsunset", "temperature", &kelvin.to_string()])
-        .output()?;
+    run(temperature_args(kelvin))
+}
+
+/// Where the daemon sends temperatures, gamma and backlight levels:
+/// hyprsunset and `brightnessctl` through `Hyprctl`, or a recorder in tests.
+pub trait TemperatureSink {
+    fn set_temperature(&self, kelvin: u16) -> Result<(), Box<dyn std::error::Error>>;
+    fn set_gamma(&self, percent: u8) -> Result<(), Box<dyn std::error::Error>>;
+    fn set_backlight(&self, percent: u8) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Sets temperatures and gamma with `hyprctl hyprsunset`, and the backlight
+/// with `brightnessctl`.
+pub struct Hyprctl;
+
+impl TemperatureSink for Hyprctl {
+    fn set_temperature(&self, kelvin: u16) -> Result<(), Box<dyn std::error::Error>> {
+        set_temperature(kelvin)
+    }
+
+    fn set_gamma(&self, percent: u8) -> Result<(), Box<dyn std::error::Error>> {
+        set_gamma(percent)
+    }
+
+    fn set_backlight(&self, percent: u8) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(crate::backlight::set_brightness(percent)?)
+    }
+}
+
+/// Sets hyprsunset's gamma, clamped between the gamma floor and 100%.
+pub fn set_gamma(percent: u8) -> Result<(), Box<dyn std::error::Error>> {
+    run(gamma_args(percent, GAMMA_FLOOR.load(Ordering::Relaxed)))
+}
+
+/// Runs `command` like hyprctl is run: waiting for a free slot under
+/// `daemon.max_hyprctl_in_flight`, and killed after the hyprctl timeout.
+pub fn run_bounded(command: &mut Command) -> std::io::Result<Output> {
+    let timeout = Duration::from_secs(TIMEOUT_SECONDS.load(Ordering::Relaxed));
+    executor().run(|| output_with_timeout(command, timeout))
+}
+
+fn run(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    log::debug!("Running: hyprctl {}", args.join(" "));
+    let output = run_bounded(Command::new("hyprctl").args(args))
+        .map_err(|e| format!("hyprctl failed: {e}"))?;
+    log::debug!(
+        "hyprctl exited with {}, stderr: {:?}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -13,18 +202,157 @@ pub fn set_temperature(kelvin: u16) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn is_hyprsunset_running() -> bool {
+pub fn is_hyprsunset_running() -> bool {
+    log::debug!("Running: pidof hyprsunset");
     Command::new("pidof")
         .arg("hyprsunset")
         .output()
-        .map(|o| o.status.success())
+        .map(|o| {
+            log::debug!("pidof exited with {}", o.status);
+            o.status.success()
+        })
         .unwrap_or(false)
 }
 
+/// How long to wait for a freshly spawned hyprsunset before giving up.
+const READY_TIMEOUT: Duration = Duration::from_secs(5);
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 pub fn ensure_hyprsunset_running() -> Result<(), Box<dyn std::error::Error>> {
     if !is_hyprsunset_running() {
         log::info!("Starting hyprsunset...");
+        log::debug!("Spawning: hyprsunset");
         Command::new("hyprsunset").spawn()?;
+        if !wait_until_ready(READY_TIMEOUT) {
+            log::warn!(
+                "hyprsunset not ready after {}s, continuing anyway",
+                READY_TIMEOUT.as_secs()
+            );
+        }
     }
     Ok(())
 }
+
+/// Blocks until hyprsunset is running or `timeout` elapses. Returns whether it
+/// became ready.
+pub fn wait_until_ready(timeout: Duration) -> bool {
+    poll_until(timeout, READY_POLL_INTERVAL, is_hyprsunset_running)
+}
+
+fn poll_until(timeout: Duration, interval: Duration, mut probe: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if probe() {
+            return true;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return false;
+        }
+        std::thread::sleep(remaining.min(interval));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn off_sentinel_issues_identity() {
+        assert_eq!(temperature_args(OFF), ["hyprsunset", "identity"]);
+    }
+
+    #[test]
+    fn kelvin_issues_temperature() {
+        assert_eq!(
+            temperature_args(3000),
+            ["hyprsunset", "temperature", "3000"]
+        );
+    }
+
+    #[test]
+    fn gamma_is_clamped_to_a_percentage() {
+        assert_eq!(gamma_args(80, 20), ["hyprsunset", "gamma", "80"]);
+        assert_eq!(gamma_args(150, 20), ["hyprsunset", "gamma", "100"]);
+    }
+
+    #[test]
+    fn gamma_never_drops_below_the_floor() {
+        assert_eq!(gamma_args(5, 20), ["hyprsunset", "gamma", "20"]);
+        assert_eq!(gamma_args(0, 20), ["hyprsunset", "gamma", "20"]);
+        assert_eq!(gamma_args(0, 0), ["hyprsunset", "gamma", "0"]);
+        assert_eq!(gamma_args(50, 150), ["hyprsunset", "gamma", "100"]);
+    }
+
+    #[test]
+    fn poll_until_returns_once_probe_succeeds() {
+        let mut calls = 0;
+        let ready = poll_until(Duration::from_secs(1), Duration::from_millis(1), || {
+            calls += 1;
+            calls == 3
+        });
+
+        assert!(ready);
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn poll_until_times_out() {
+        let ready = poll_until(Duration::from_millis(20), Duration::from_millis(5), || {
+            false
+        });
+
+        assert!(!ready);
+    }
+
+    #[test]
+    fn output_with_timeout_kills_slow_commands() {
+        let started = Instant::now();
+        let result = output_with_timeout(Command::new("sleep").arg("5"), Duration::from_millis(50));
+
+        let err = result.expect_err("sleep outlives the timeout");
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn output_with_timeout_returns_output_of_fast_commands() {
+        let output = output_with_timeout(
+            Command::new("sh").args(["-c", "echo ready"]),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "ready");
+    }
+
+    #[test]
+    fn bounded_executor_never_exceeds_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let executor = Arc::new(BoundedExecutor::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let (executor, running, peak) = (executor.clone(), running.clone(), peak.clone());
+                std::thread::spawn(move || {
+                    executor.run(|| {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(10));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    });
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+}
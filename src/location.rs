@@ -0,0 +1,438 @@
+use crate::config::{Location, Mode};
+use crate::dbus::{Call, Connection, Value};
+use chrono::{Datelike, TimeZone};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// `location.city` presets: lowercase name, latitude, longitude.
+const CITIES: [(&str, f64, f64); 42] = [
+    ("amsterdam", 52.37, 4.90),
+    ("athens", 37.98, 23.73),
+    ("auckland", -36.85, 174.76),
+    ("bangkok", 13.76, 100.50),
+    ("beijing", 39.90, 116.40),
+    ("berlin", 52.52, 13.40),
+    ("buenos aires", -34.60, -58.38),
+    ("cairo", 30.04, 31.24),
+    ("chicago", 41.88, -87.63),
+    ("delhi", 28.61, 77.21),
+    ("dubai", 25.20, 55.27),
+    ("helsinki", 60.17, 24.94),
+    ("hong kong", 22.32, 114.17),
+    ("istanbul", 41.01, 28.98),
+    ("jakarta", -6.21, 106.85),
+    ("johannesburg", -26.20, 28.05),
+    ("lagos", 6.52, 3.38),
+    ("lisbon", 38.72, -9.14),
+    ("london", 51.51, -0.13),
+    ("los angeles", 34.05, -118.24),
+    ("madrid", 40.42, -3.70),
+    ("mexico city", 19.43, -99.13),
+    ("moscow", 55.76, 37.62),
+    ("mumbai", 19.08, 72.88),
+    ("nairobi", -1.29, 36.82),
+    ("new york", 40.71, -74.01),
+    ("oslo", 59.91, 10.75),
+    ("paris", 48.86, 2.35),
+    ("reykjavik", 64.15, -21.94),
+    ("rome", 41.90, 12.50),
+    ("san francisco", 37.77, -122.42),
+    ("sao paulo", -23.55, -46.63),
+    ("seattle", 47.61, -122.33),
+    ("seoul", 37.57, 126.98),
+    ("singapore", 1.35, 103.82),
+    ("stockholm", 59.33, 18.07),
+    ("sydney", -33.87, 151.21),
+    ("tokyo", 35.68, 139.69),
+    ("toronto", 43.65, -79.38),
+    ("vancouver", 49.28, -123.12),
+    ("vienna", 48.21, 16.37),
+    ("warsaw", 52.23, 21.01),
+];
+
+/// geoclue's well-known bus name and manager object.
+const GEOCLUE: &str = "org.freedesktop.GeoClue2";
+const GEOCLUE_MANAGER: &str = "/org/freedesktop/GeoClue2/Manager";
+
+/// geoclue's `GCLUE_ACCURACY_LEVEL_CITY`: enough for sun times, and the
+/// level agents are most willing to grant.
+const ACCURACY_CITY: u32 = 4;
+
+const GEOCLUE_LOCATION: &str = "org.freedesktop.GeoClue2.Location";
+
+/// Replies with `<latitude>,<longitude>` for the caller's IP address.
+const IP_LOOKUP_URL: &str = "https://ipinfo.io/loc";
+
+/// Seconds either source may take before it's given up on.
+const LOOKUP_TIMEOUT_SECONDS: u64 = 5;
+
+type Lookup = fn() -> Option<(f64, f64)>;
+
+/// `location` with concrete coordinates: explicit coordinates win, then the
+/// `city` preset, then the `detected` fix. Failing those, `from_timezone`
+/// fills in a longitude derived from the standard UTC offset, keeping the
+/// configured latitude. An unknown city is an error even when unused, so
+/// typos don't go unnoticed.
+pub fn resolve(location: &Location, detected: Option<(f64, f64)>) -> Result<Location, String> {
+    let preset = location
+        .city
+        .as_deref()
+        .map(|name| {
+            city(name).ok_or_else(|| {
+                format!("Unknown location.city {name:?}; set latitude and longitude instead")
+            })
+        })
+        .transpose()?;
+    if location.is_unset() {
+        if let Some((latitude, longitude)) = preset.or(detected) {
+            return Ok(Location {
+                latitude,
+                longitude,
+                ..location.clone()
+            });
+        }
+    }
+    if (location.longitude, location.from_timezone) == (0.0, true) {
+        let longitude = timezone_longitude(standard_offset(local_offset_on));
+        return Ok(Location {
+            longitude,
+            ..location.clone()
+        });
+    }
+    Ok(location.clone())
+}
+
+/// Whether `location.detect` has anything to fill in: auto mode, with
+/// detection left on and neither coordinates nor a city given.
+pub fn wants_detection(location: &Location, mode: &Mode) -> bool {
+    *mode == Mode::Auto && location.detect && location.is_unset() && location.city.is_none()
+}
+
+/// Coordinates of a bundled city, matched case-insensitively with `_` or
+/// `-` accepted for spaces.
+fn city(name: &str) -> Option<(f64, f64)> {
+    let name = name.trim().to_lowercase().replace(['_', '-'], " ");
+    CITIES
+        .iter()
+        .find(|(city, _, _)| *city == name)
+        .map(|&(_, latitude, longitude)| (latitude, longitude))
+}
+
+/// Longitude whose solar time matches a UTC offset of `offset_seconds`
+/// (15 degrees per hour).
+fn timezone_longitude(offset_seconds: i32) -> f64 {
+    (f64::from(offset_seconds) / 3600.0 * 15.0).clamp(-180.0, 180.0)
+}
+
+/// The standard (non-DST) offset from the offsets on the 1st of January and
+/// July: daylight saving moves clocks forward, so it's the smaller one in
+/// either hemisphere.
+fn standard_offset(offset_on: impl Fn(u32) -> i32) -> i32 {
+    offset_on(1).min(offset_on(7))
+}
+
+/// The local UTC offset, in seconds, on the 1st of `month` this year.
+fn local_offset_on(month: u32) -> i32 {
+    let now = chrono::Local::now();
+    chrono::NaiveDate::from_ymd_opt(now.year(), month, 1)
+        .and_then(|date| date.and_hms_opt(12, 0, 0))
+        .map_or_else(
+            || now.offset().local_minus_utc(),
+            |utc| {
+                chrono::Local
+                    .from_utc_datetime(&utc)
+                    .offset()
+                    .local_minus_utc()
+            },
+        )
+}
+
+/// Discovers the current `(latitude, longitude)`: geoclue first, then IP
+/// geolocation, then the last location either of them found in `cache`.
+/// Looks it up afresh on every call, so a reload picks up a move, and logs
+/// which source was used.
+pub fn detect(cache: Option<&Path>) -> Option<(f64, f64)> {
+    detect_with(
+        &[("geoclue", geoclue), ("IP geolocation", ip_lookup)],
+        cache,
+    )
+}
+
+fn detect_with(sources: &[(&str, Lookup)], cache: Option<&Path>) -> Option<(f64, f64)> {
+    let found = sources
+        .iter()
+        .find_map(|(source, lookup)| lookup().map(|location| (source, location)));
+    if let Some((source, (latitude, longitude))) = found {
+        log::info!("Detected location {latitude}, {longitude} via {source}");
+        if let Some(path) = cache {
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            if let Err(e) = std::fs::write(path, format!("{latitude},{longitude}\n")) {
+                log::warn!("Failed to cache the detected location: {e}");
+            }
+        }
+        return Some((latitude, longitude));
+    }
+    let cached = cached(cache);
+    match cached {
+        Some((latitude, longitude)) => {
+            log::info!("Location detection failed; using the cached {latitude}, {longitude}");
+        }
+        None => log::warn!("Location detection failed; using the configured coordinates"),
+    }
+    cached
+}
+
+/// The location the daemon last detected, without looking it up again.
+pub fn cached(cache: Option<&Path>) -> Option<(f64, f64)> {
+    cache
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| parse_coordinates(&content))
+}
+
+/// Asks geoclue over D-Bus: a client with a desktop id and city accuracy,
+/// started and polled until it has a fix.
+fn geoclue() -> Option<(f64, f64)> {
+    let timeout = Duration::from_secs(LOOKUP_TIMEOUT_SECONDS);
+    let mut bus = Connection::system(timeout)?;
+    let manager = Call {
+        destination: GEOCLUE,
+        path: GEOCLUE_MANAGER,
+        interface: "org.freedesktop.GeoClue2.Manager",
+        member: "GetClient",
+    };
+    let Some(Value::Path(client)) = bus.call(&manager, &[])?.into_iter().next() else {
+        return None;
+    };
+    let properties = |member| Call {
+        destination: GEOCLUE,
+        path: &client,
+        interface: "org.freedesktop.DBus.Properties",
+        member,
+    };
+    let set = |bus: &mut Connection, name: &str, value| {
+        let args = [
+            Value::Str("org.freedesktop.GeoClue2.Client".to_string()),
+            Value::Str(name.to_string()),
+            Value::Variant(Box::new(value)),
+        ];
+        bus.call(&properties("Set"), &args)
+    };
+    set(&mut bus, "DesktopId", Value::Str("candela".to_string()))?;
+    set(
+        &mut bus,
+        "RequestedAccuracyLevel",
+        Value::U32(ACCURACY_CITY),
+    )?;
+    let start = Call {
+        interface: "org.freedesktop.GeoClue2.Client",
+        member: "Start",
+        ..manager
+    };
+    bus.call(
+        &Call {
+            path: &client,
+            ..start
+        },
+        &[],
+    )?;
+
+    let get = |bus: &mut Connection, path: &str, interface: &str, name: &str| {
+        let call = Call {
+            path,
+            ..properties("Get")
+        };
+        let args = [
+            Value::Str(interface.to_string()),
+            Value::Str(name.to_string()),
+        ];
+        if let Value::Variant(value) = bus.call(&call, &args)?.into_iter().next()? {
+            Some(*value)
+        } else {
+            None
+        }
+    };
+    let deadline = std::time::Instant::now() + timeout;
+    let location = loop {
+        // The location is "/" until geoclue has a fix
+        if let Value::Path(path) = get(
+            &mut bus,
+            &client,
+            "org.freedesktop.GeoClue2.Client",
+            "Location",
+        )? {
+            if path != "/" {
+                break path;
+            }
+        }
+        if std::time::Instant::now() >= deadline {
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    };
+    let mut coordinate = |name| {
+        if let Value::F64(value) = get(&mut bus, &location, GEOCLUE_LOCATION, name)? {
+            Some(value)
+        } else {
+            None
+        }
+    };
+    valid((coordinate("Latitude")?, coordinate("Longitude")?))
+}
+
+fn ip_lookup() -> Option<(f64, f64)> {
+    let output = Command::new("curl")
+        .args([
+            "-sf",
+            "--max-time",
+            &LOOKUP_TIMEOUT_SECONDS.to_string(),
+            IP_LOOKUP_URL,
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    parse_coordinates(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `<latitude>,<longitude>`, as the IP lookup replies and the cache stores.
+fn parse_coordinates(content: &str) -> Option<(f64, f64)> {
+    let (latitude, longitude) = content.trim().split_once(',')?;
+    valid((
+        latitude.trim().parse().ok()?,
+        longitude.trim().parse().ok()?,
+    ))
+}
+
+fn valid((latitude, longitude): (f64, f64)) -> Option<(f64, f64)> {
+    ((-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude))
+        .then_some((latitude, longitude))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates_are_parsed_and_range_checked() {
+        assert_eq!(
+            parse_coordinates("52.5200,13.4050\n"),
+            Some((52.52, 13.405))
+        );
+        assert_eq!(parse_coordinates("-33.87, 151.21"), Some((-33.87, 151.21)));
+        assert_eq!(parse_coordinates("95.0,13.4"), None);
+        assert_eq!(parse_coordinates("<html>"), None);
+    }
+
+    #[test]
+    fn city_presets_resolve_unless_coordinates_are_explicit() {
+        let preset = Location {
+            city: Some("New_York".to_string()),
+            ..Location::default()
+        };
+        let resolved = resolve(&preset, None).unwrap();
+        assert_eq!((resolved.latitude, resolved.longitude), (40.71, -74.01));
+
+        let explicit = Location {
+            latitude: 48.5,
+            longitude: 9.0,
+            ..preset
+        };
+        let resolved = resolve(&explicit, None).unwrap();
+        assert_eq!((resolved.latitude, resolved.longitude), (48.5, 9.0));
+
+        let unknown = Location {
+            city: Some("atlantis".to_string()),
+            ..Location::default()
+        };
+        let err = resolve(&unknown, None).unwrap_err();
+        assert!(err.contains("\"atlantis\""), "{err}");
+    }
+
+    #[test]
+    fn timezone_offset_maps_to_longitude() {
+        assert!((timezone_longitude(3600) - 15.0).abs() < f64::EPSILON);
+        assert!((timezone_longitude(-5 * 3600) + 75.0).abs() < f64::EPSILON);
+        assert!((timezone_longitude(14 * 3600) - 180.0).abs() < f64::EPSILON);
+        let tz = Location {
+            from_timezone: true,
+            ..Location::default()
+        };
+        let offset = standard_offset(local_offset_on);
+        let resolved = resolve(&tz, None).unwrap();
+        assert!((resolved.longitude - timezone_longitude(offset)).abs() < 1e-9);
+        let tz = Location {
+            latitude: 52.5,
+            ..tz
+        };
+        assert!((resolve(&tz, None).unwrap().latitude - 52.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn daylight_saving_doesnt_shift_the_timezone_longitude() {
+        // Berlin (CET, CEST in summer) and Sydney (AEST, AEDT in summer)
+        assert_eq!(
+            standard_offset(|month| if month == 7 { 7200 } else { 3600 }),
+            3600
+        );
+        assert_eq!(
+            standard_offset(|month| if month == 1 { 39600 } else { 36000 }),
+            36000
+        );
+    }
+
+    #[test]
+    fn detection_tries_each_source_and_falls_back_to_the_cache() {
+        let cache = std::env::temp_dir().join(format!("candela-location-{}", std::process::id()));
+        let _ = std::fs::remove_file(&cache);
+        let failing: Lookup = || None;
+        let berlin: Lookup = || Some((52.52, 13.40));
+
+        assert_eq!(detect_with(&[("a", failing)], Some(&cache)), None);
+        assert_eq!(
+            detect_with(&[("a", failing), ("b", berlin)], Some(&cache)),
+            Some((52.52, 13.40))
+        );
+        assert_eq!(
+            detect_with(&[("a", failing)], Some(&cache)),
+            Some((52.52, 13.40))
+        );
+        assert_eq!(cached(Some(&cache)), Some((52.52, 13.40)));
+        // Without a cache (ephemeral daemons) nothing is written or read
+        assert_eq!(detect_with(&[("a", failing)], None), None);
+        let _ = std::fs::remove_file(&cache);
+    }
+
+    #[test]
+    fn detection_only_fills_in_a_location_nothing_else_sets() {
+        let detect = Location::default();
+        assert!(wants_detection(&detect, &Mode::Auto));
+        assert!(!wants_detection(&detect, &Mode::Fixed));
+        assert!(!wants_detection(&detect, &Mode::Manual));
+        assert!(!wants_detection(
+            &Location {
+                detect: false,
+                ..detect.clone()
+            },
+            &Mode::Auto
+        ));
+        assert!(!wants_detection(
+            &Location {
+                latitude: 48.5,
+                ..detect.clone()
+            },
+            &Mode::Auto
+        ));
+        assert!(!wants_detection(
+            &Location {
+                city: Some("oslo".to_string()),
+                ..detect
+            },
+            &Mode::Auto
+        ));
+
+        let resolved = resolve(&detect, Some((52.52, 13.40))).unwrap();
+        assert_eq!((resolved.latitude, resolved.longitude), (52.52, 13.40));
+    }
+}
@@ -1,27 +1,46 @@
-#![allow(clippy::print_stdout, reason = "CLI binary produces user-facing output")]
+#![allow(
+    clippy::print_stdout,
+    reason = "CLI binary produces user-facing output"
+)]
 #![allow(clippy::print_stderr, reason = "CLI binary reports errors to stderr")]
 #![allow(clippy::exit, reason = "CLI binary uses process::exit for error codes")]
 
+use chrono::Datelike;
 use clap::{Parser, Subcommand};
 use std::fs;
+use std::io::Write;
 use std::process;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+mod backlight;
+mod color;
 mod config;
+mod dbus;
+mod doctor;
+mod hooks;
 mod hyprctl;
+mod location;
+mod power;
 mod scheduler;
+mod session;
+mod socket;
 mod state;
 mod transition;
+mod watch;
+mod zones;
 
 #[derive(Parser, Debug)]
 #[command(name = "candela")]
 #[command(author = "candela developers")]
 #[command(version = "0.1.0")]
 #[command(about = "Smooth color temperature transitions for hyprsunset", long_about = None)]
-#[allow(clippy::struct_excessive_bools, reason = "CLI flags are inherently boolean")]
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "CLI flags are inherently boolean"
+)]
 struct Args {
     #[command(subcommand)]
     command: Option<Commands>,
@@ -29,32 +48,341 @@ struct Args {
     #[arg(short, long, global = true)]
     config: Option<String>,
 
+    /// Load `config.toml` from this directory (`--config` takes precedence)
+    #[arg(long, global = true)]
+    config_dir: Option<String>,
+
     #[arg(short, long, global = true)]
     verbose: bool,
 
     #[arg(long, global = true)]
     json: bool,
 
+    /// Bare values without units or labels (status is tab-separated)
+    #[arg(long, global = true, conflicts_with = "json")]
+    plain: bool,
+
     #[arg(short, long, global = true)]
     quiet: bool,
 
     #[arg(long, global = true)]
     dry_run: bool,
+
+    /// Log how long each daemon tick step takes (at debug level)
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Log every daemon tick's inputs and outcome as JSON (at trace level)
+    #[arg(long, global = true)]
+    trace: bool,
+
+    /// Run a single daemon tick: set the scheduled temperature, write status and exit
+    #[arg(long, global = true)]
+    once: bool,
+
+    /// Override `transition.easing` for this run
+    #[arg(long, global = true)]
+    easing: Option<String>,
+
+    /// Override `daemon.tick_interval_seconds` for this run
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    tick: Option<u64>,
+
+    /// Override `location.latitude` for this run
+    #[arg(long, global = true, allow_negative_numbers = true, value_parser = parse_latitude)]
+    lat: Option<f64>,
+
+    /// Override `location.longitude` for this run
+    #[arg(long, global = true, allow_negative_numbers = true, value_parser = parse_longitude)]
+    lon: Option<f64>,
+}
+
+fn parse_coordinate(value: &str, limit: f64) -> Result<f64, String> {
+    let parsed: f64 = value.parse().map_err(|e| format!("{e}"))?;
+    if (-limit..=limit).contains(&parsed) {
+        Ok(parsed)
+    } else {
+        Err(format!("must be between -{limit} and {limit}"))
+    }
+}
+
+fn parse_progress(value: &str) -> Result<f64, String> {
+    let parsed: f64 = value.parse().map_err(|e| format!("{e}"))?;
+    if (0.0..=1.0).contains(&parsed) {
+        Ok(parsed)
+    } else {
+        Err("must be between 0.0 and 1.0".to_string())
+    }
+}
+
+fn parse_deadline(value: &str) -> Result<chrono::NaiveTime, String> {
+    chrono::NaiveTime::parse_from_str(value, "%H:%M").map_err(|e| format!("expected HH:MM: {e}"))
+}
+
+fn parse_latitude(value: &str) -> Result<f64, String> {
+    parse_coordinate(value, 90.0)
+}
+
+fn parse_longitude(value: &str) -> Result<f64, String> {
+    parse_coordinate(value, 180.0)
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
-    Daemon,
+    Daemon {
+        /// Ignore config files and keep all state in memory (defaults + env + CLI flags)
+        #[arg(long)]
+        ephemeral: bool,
+        /// Start fresh at the scheduled target instead of resuming a saved
+        /// transition, and don't save one on exit
+        #[arg(long, conflicts_with = "ephemeral")]
+        no_state: bool,
+    },
     Now,
-    Status,
-    Set { temperature: u16 },
+    /// Print the current phase, computed from the schedule (no daemon needed)
+    Phase,
+    /// Block until the next transition starts, then exit (e.g. `candela wait && notify-send`)
+    Wait {
+        /// Only wake for a transition ending in this phase (`day` or `night`)
+        #[arg(long, value_parser = parse_wait_phase)]
+        phase: Option<scheduler::Phase>,
+    },
+    /// Print sunrise, sunset, solar noon and day length (use --lat/--lon for other places)
+    Sun {
+        /// Date to compute for (YYYY-MM-DD); defaults to today
+        #[arg(long)]
+        date: Option<chrono::NaiveDate>,
+    },
+    /// Print the daemon's status as Prometheus metrics (e.g. for a textfile collector)
+    Metrics,
+    /// Print the status each time the temperature or phase changes, until Ctrl-C
+    Watch {
+        /// Seconds between status file checks
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: Option<u64>,
+    },
+    Status {
+        /// Warn (and exit 1) if the config file changed since the daemon loaded it
+        #[arg(long)]
+        check_config: bool,
+        /// Also report `melanopic_factor`, a 0-1 proxy for melatonin suppression
+        #[arg(long)]
+        melanopic: bool,
+    },
+    Set {
+        temperature: u16,
+        /// Have the daemon ramp so `temperature` is reached at this time (HH:MM, next occurrence)
+        #[arg(long, value_parser = parse_deadline)]
+        by: Option<chrono::NaiveTime>,
+        /// Ease from the current temperature over this many seconds (Ctrl-C stops where it is)
+        #[arg(long, conflicts_with = "by", value_parser = clap::value_parser!(u32).range(1..))]
+        duration: Option<u32>,
+    },
+    /// Apply the next temperature from `[cycle] steps`, wrapping at the end
+    Cycle,
+    /// Switch to the night temperature, or to the day one if already closer to night
+    Toggle,
+    /// Set the temperature estimated from an image's average color
+    Match {
+        image: std::path::PathBuf,
+    },
     Pause,
     Resume,
-    Config,
+    /// Undo everything: pause the daemon and set the day temperature, full
+    /// gamma and full backlight directly, even if the daemon is wedged
+    Reset,
+    /// Move the running daemon to new coordinates without restarting its transition
+    SetLocation {
+        #[arg(allow_negative_numbers = true, value_parser = parse_latitude)]
+        latitude: f64,
+        #[arg(allow_negative_numbers = true, value_parser = parse_longitude)]
+        longitude: f64,
+    },
+    Config {
+        /// Print `CANDELA_*=value` lines instead (e.g. for a systemd `EnvironmentFile`)
+        #[arg(long, conflicts_with = "json")]
+        env: bool,
+    },
+    /// Pin the current transition at a progress point, or release it when omitted
+    Progress {
+        #[arg(value_parser = parse_progress)]
+        value: Option<f64>,
+    },
+    /// Run the configured hook for a phase now and report its exit status
+    TestHooks {
+        #[arg(value_parser = ["day", "night"])]
+        phase: String,
+    },
+    /// Check the installation and config, exiting non-zero if a hard check fails
+    Doctor,
+    /// Print the running daemon's transition fields and the saved state file, for bug reports
+    DumpState,
+    /// Preview the configured day-to-night transition
+    Preview {
+        /// Animate the transition as a colored bar (requires a truecolor terminal)
+        #[arg(long)]
+        animate: bool,
+        /// Print today's temperatures side by side with those under another config file
+        #[arg(long, conflicts_with = "animate")]
+        compare: Option<String>,
+        /// Simulate a whole day of the schedule, printing time, phase and temperature
+        #[arg(long, conflicts_with = "animate")]
+        day: bool,
+        /// Day to simulate or compare (YYYY-MM-DD); implies --day; defaults to today
+        #[arg(long, conflicts_with = "animate")]
+        date: Option<chrono::NaiveDate>,
+        /// Minutes between simulated time points; implies --day
+        #[arg(
+            long,
+            conflicts_with = "animate",
+            value_parser = clap::value_parser!(u32).range(1..=1440),
+        )]
+        step_minutes: Option<u32>,
+    },
+}
+
+/// The status file as `key=value` lines, whichever format the daemon wrote
+/// it in; empty when missing or unreadable.
+fn read_status(path: &str) -> String {
+    fs::read_to_string(path)
+        .map(|content| status_text(&content))
+        .unwrap_or_default()
+}
+
+/// `key=value` lines for status `content`, converting a JSON status object.
+fn status_text(content: &str) -> String {
+    if !content.trim_start().starts_with('{') {
+        return content.to_string();
+    }
+    let Ok(serde_json::Value::Object(fields)) = serde_json::from_str(content) else {
+        return String::new();
+    };
+    fields
+        .iter()
+        .map(|(key, value)| {
+            value
+                .as_str()
+                .map_or_else(|| format!("{key}={value}\n"), |s| format!("{key}={s}\n"))
+        })
+        .collect()
+}
+
+/// What the status file reports, as written by the daemon, `--once` and a
+/// manual `set`.
+#[derive(Debug, Clone, PartialEq)]
+struct Status {
+    temp: u16,
+    phase: &'static str,
+    target: u16,
+    progress: f64,
+    /// `config_generation` and `config_hash`.
+    config: Option<(u64, u64)>,
+    transition: Option<transition::Snapshot>,
+    /// `eta_seconds` and `target_time`.
+    eta: Option<(u64, u64)>,
+    zones: Vec<(String, zones::Reading)>,
+    updated_at: u64,
+}
+
+impl Status {
+    /// Status for `transition`, showing `temp`, at `updated_at`.
+    fn of_transition(
+        transition: &transition::Transition,
+        temp: u16,
+        phase: scheduler::Phase,
+        updated_at: u64,
+    ) -> Self {
+        Self {
+            temp,
+            phase: phase.as_str(),
+            target: transition.target_temperature(),
+            progress: transition.progress(),
+            config: None,
+            transition: Some(transition.snapshot()),
+            eta: Some(status_eta(transition.remaining(), updated_at)),
+            zones: Vec::new(),
+            updated_at,
+        }
+    }
+
+    /// Status for a `temperature` set by hand, outside any schedule.
+    const fn manual(temperature: u16, updated_at: u64) -> Self {
+        Self {
+            temp: temperature,
+            phase: "manual",
+            target: temperature,
+            progress: 1.0,
+            config: None,
+            transition: None,
+            eta: None,
+            zones: Vec::new(),
+            updated_at,
+        }
+    }
+
+    /// Every field in file order, keyed as `key=value` readers expect.
+    fn fields(&self) -> Vec<(String, serde_json::Value)> {
+        let mut fields = vec![
+            ("temp".to_string(), self.temp.into()),
+            ("phase".to_string(), self.phase.into()),
+            ("target".to_string(), self.target.into()),
+            ("progress".to_string(), self.progress.into()),
+        ];
+        if let Some((generation, hash)) = self.config {
+            fields.push(("config_generation".to_string(), generation.into()));
+            fields.push(("config_hash".to_string(), format!("{hash:016x}").into()));
+        }
+        if let Some(snapshot) = &self.transition {
+            let snapshot = snapshot.status_fields();
+            fields.extend(snapshot.map(|(key, value)| (key.to_string(), value)));
+        }
+        if let Some((eta, target_time)) = self.eta {
+            fields.push(("eta_seconds".to_string(), eta.into()));
+            fields.push(("target_time".to_string(), target_time.into()));
+        }
+        for (name, reading) in &self.zones {
+            fields.extend(reading.status_fields(name));
+        }
+        fields.push(("updated_at".to_string(), self.updated_at.into()));
+        fields
+    }
+
+    /// The status file content: `key=value` lines, or one JSON object with
+    /// the same keys and typed values. Fractions are kept to two places.
+    fn render(&self, format: config::StatusFormat) -> String {
+        let fields = self.fields().into_iter().map(|(key, value)| {
+            let fraction = value.as_f64().filter(|_| value.is_f64());
+            (
+                key,
+                fraction.map_or(value, |f| ((f * 100.0).round() / 100.0).into()),
+            )
+        });
+        match format {
+            config::StatusFormat::Text => {
+                fields
+                    .map(|(key, value)| {
+                        let text = if value.is_f64() {
+                            format!("{:.2}", value.as_f64().unwrap_or_default())
+                        } else {
+                            value
+                                .as_str()
+                                .map_or_else(|| value.to_string(), str::to_string)
+                        };
+                        format!("{key}={text}")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    + "\n"
+            }
+            config::StatusFormat::Json => {
+                format!("{}\n", serde_json::Value::Object(fields.collect()))
+            }
+        }
+    }
 }
 
 fn read_status_file(path: &str) -> (u16, String, u16, f64) {
-    let content = fs::read_to_string(path).unwrap_or_default();
+    let content = read_status(path);
     let mut temp = 0;
     let mut phase = "unknown".to_string();
     let mut target = 0;
@@ -81,317 +409,4193 @@ fn read_status_file(path: &str) -> (u16, String, u16, f64) {
     (temp, phase, target, progress)
 }
 
+/// The last temperature recorded in the status file, if there is one.
+fn read_status_temperature(path: &str) -> Option<u16> {
+    read_status(path)
+        .lines()
+        .find_map(|line| line.strip_prefix("temp=")?.parse().ok())
+}
+
+/// The time left in the transition, as of `now`: `eta_seconds` (rounded up)
+/// and `target_time`, the Unix time the target is reached.
+fn status_eta(remaining: Duration, now: u64) -> (u64, u64) {
+    let eta = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    (eta, now + eta)
+}
+
+/// `eta_seconds` and `target_time` from the status file, when the daemon
+/// wrote them.
+fn read_status_eta(path: &str) -> Option<(u64, u64)> {
+    let field = |key| read_status_field(path, key)?.parse().ok();
+    Some((field("eta_seconds")?, field("target_time")?))
+}
+
+fn read_status_field(path: &str, key: &str) -> Option<String> {
+    read_status(path).lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        (name == key).then(|| value.to_string())
+    })
+}
+
+/// Whether the config file no longer matches the hash the daemon reported,
+/// or `None` when no hash was reported.
+fn config_drifted(loaded_hash: Option<&str>, file_hash: u64) -> Option<bool> {
+    let loaded = u64::from_str_radix(loaded_hash?, 16).ok()?;
+    Some(loaded != file_hash)
+}
+
+/// Whether a manual `set` would change anything, mirroring `optimize_updates`.
+fn set_changes_temperature(current: Option<u16>, requested: u16) -> bool {
+    current != Some(requested)
+}
+
+#[allow(
+    clippy::too_many_lines,
+    reason = "subcommand dispatch is a single flat match"
+)]
 fn main() {
     env_logger::init();
 
     let args = Args::parse();
 
-    let config_path = args
-        .config
-        .clone()
-        .or_else(|| config::find_config().map(|p| p.to_string_lossy().into_owned()));
+    let persistence = match args.command {
+        Some(Commands::Daemon {
+            ephemeral: true, ..
+        }) => Persistence::Ephemeral,
+        Some(Commands::Daemon { no_state: true, .. }) => Persistence::NoState,
+        _ => Persistence::Full,
+    };
+    let ephemeral = persistence == Persistence::Ephemeral;
+    let resolved = if ephemeral {
+        None
+    } else {
+        config::resolve_path(args.config.as_deref(), args.config_dir.as_deref())
+    };
+    let explicit_config = resolved.as_ref().is_some_and(|(_, explicit)| *explicit);
+    let config_path = resolved.map(|(path, _)| path.to_string_lossy().into_owned());
+    match &config_path {
+        Some(path) => log::info!("Using config {path}"),
+        None => log::info!("No config file found, using defaults"),
+    }
 
-    let config = config::load(config_path.as_deref());
+    // Only the daemon looks the location up; the rest reuse what it cached
+    let runs_daemon = matches!(args.command, Some(Commands::Daemon { .. }) | None);
+    let load = || {
+        let mut config = config::load(config_path.as_deref(), explicit_config)?;
+        apply_cli(&mut config, &args);
+        resolve_location(&mut config, persistence, runs_daemon)?;
+        Ok::<_, String>(config)
+    };
+    let mut config = load().unwrap_or_else(|e| {
+        if matches!(args.command, Some(Commands::Doctor)) {
+            let defaults = config::Config::default();
+            report_doctor(
+                &doctor::run(&defaults, config_path.as_deref(), Some(&e)),
+                &args,
+            );
+        }
+        eprintln!("{e}");
+        process::exit(1);
+    });
+    validate(&mut config);
+    hyprctl::set_gamma_floor(config.brightness.floor);
 
     match args.command {
-        Some(Commands::Daemon) | None => {
-            if let Err(e) = run_daemon(&config, args.dry_run, args.quiet) {
+        Some(Commands::Daemon { .. }) | None if args.once => {
+            if let Err(e) = apply_once(&config, config_path.as_deref(), &args) {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+        }
+        Some(Commands::Daemon { .. }) | None => {
+            let reload = || {
+                let mut config = load()?;
+                validate(&mut config);
+                Ok(config)
+            };
+            if let Err(e) = run_daemon(
+                config,
+                config_path.as_deref(),
+                reload,
+                DaemonOptions {
+                    dry_run: args.dry_run,
+                    quiet: args.quiet,
+                    show_timings: args.timings,
+                    trace: args.trace,
+                    persistence,
+                },
+                &hyprctl::Hyprctl,
+            ) {
                 eprintln!("Daemon error: {e}");
                 process::exit(1);
             }
         }
         Some(Commands::Now) => {
             let (temp, _, _, _) = read_status_file(&config.daemon.status_file);
-            if args.json {
-                println!(r#"{{"temp":{temp}}}"#);
-            } else {
-                println!("{temp}K");
+            let (_, stale) = status_freshness(&config.daemon);
+            let format = output_format(&args);
+            println!("{}", render_now(temp, stale, format));
+            exit_if_stale(stale, format);
+        }
+        Some(Commands::Sun { date }) => {
+            let date = date.unwrap_or_else(|| chrono::Local::now().date_naive());
+            let times = scheduler::sun_times(
+                config.location.latitude,
+                config.location.longitude,
+                config.auto.sun_elevation_degrees,
+                date,
+            );
+            match times {
+                Ok((sunrise, sunset)) => {
+                    let local = |t: chrono::DateTime<chrono::Utc>| {
+                        t.with_timezone(&chrono::Local).fixed_offset()
+                    };
+                    println!(
+                        "{}",
+                        render_sun(local(sunrise), local(sunset), output_format(&args))
+                    );
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
             }
         }
-        Some(Commands::Status) => {
+        Some(Commands::Watch { interval }) => {
+            let interval = Duration::from_secs(interval.unwrap_or(WATCH_INTERVAL_SECONDS));
+            watch_status(&config.daemon.status_file, interval, output_format(&args));
+        }
+        Some(Commands::Metrics) => {
             let (temp, phase, target, progress) = read_status_file(&config.daemon.status_file);
-
-            if args.json {
-                println!(
-                    r#"{{"temp":{temp},"phase":"{phase}","target":{target},"progress":{progress:.2}}}"#,
-                );
-            } else {
-                println!("temp={temp}");
-                println!("phase={phase}");
-                println!("target={target}");
-                println!("progress={progress:.2}");
+            print!("{}", render_metrics(temp, &phase, target, progress));
+        }
+        Some(Commands::Phase) => {
+            let schedule = scheduler::Schedule::new(config.clone()).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                process::exit(1);
+            });
+            let phase = schedule.current_phase_at(chrono::Local::now());
+            println!("{}", render_phase(phase, output_format(&args)));
+        }
+        Some(Commands::Wait { phase }) => {
+            let wake = scheduler::Schedule::new(config.clone()).and_then(|schedule| {
+                schedule
+                    .next_transition_into(chrono::Local::now(), phase)
+                    .ok_or_else(|| "No upcoming transition in the schedule".to_string())
+            });
+            match wake {
+                Ok(wake) => {
+                    if !args.quiet {
+                        println!("Waiting until {}", wake.format("%Y-%m-%d %H:%M:%S"));
+                    }
+                    if !wait_until(wake) {
+                        process::exit(130);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
             }
         }
-        Some(Commands::Set { temperature }) => {
+        Some(Commands::Status {
+            check_config,
+            melanopic,
+        }) => {
+            let (temp, phase, target, progress) = read_status_file(&config.daemon.status_file);
+            let (updated_at, stale) = status_freshness(&config.daemon);
+            let extras = StatusExtras {
+                melanopic: melanopic.then(|| color::melanopic_factor(temp)),
+                eta: read_status_eta(&config.daemon.status_file),
+                updated_at,
+                stale,
+            };
+            let format = output_format(&args);
+            println!(
+                "{}",
+                render_status(temp, &phase, target, progress, &extras, format)
+            );
+            exit_if_stale(stale, format);
+            if check_config {
+                let loaded = read_status_field(&config.daemon.status_file, "config_hash");
+                match config_drifted(loaded.as_deref(), config::file_hash(config_path.as_deref())) {
+                    Some(true) => {
+                        let generation =
+                            read_status_field(&config.daemon.status_file, "config_generation")
+                                .unwrap_or_else(|| "?".to_string());
+                        eprintln!(
+                            "Config file changed since the daemon loaded it (generation \
+                             {generation}); reload with `pkill -HUP -x candela`"
+                        );
+                        process::exit(1);
+                    }
+                    Some(false) => {}
+                    None => eprintln!("The daemon has not reported a config hash"),
+                }
+            }
+        }
+        Some(Commands::Set {
+            temperature,
+            by: Some(deadline),
+            ..
+        }) => {
+            let deadline = deadline.format("%H:%M");
+            send_control(
+                &config.daemon.status_file,
+                &format!("set {temperature} by {deadline}"),
+            );
             if !args.quiet {
-                println!("Setting temperature to {temperature}K");
+                println!("Transitioning to {temperature}K by {deadline}");
             }
-            if !args.dry_run {
-                if let Err(e) = hyprctl::set_temperature(temperature) {
-                    eprintln!("Failed to set temperature: {e}");
+        }
+        Some(Commands::Set {
+            temperature,
+            by: None,
+            duration: Some(seconds),
+        }) => {
+            ramped_set(
+                &config,
+                &args,
+                temperature,
+                Duration::from_secs(u64::from(seconds)),
+            );
+        }
+        Some(Commands::Set {
+            temperature,
+            by: None,
+            duration: None,
+        }) => {
+            manual_set(&config, &args, temperature);
+        }
+        Some(Commands::Match { ref image }) => {
+            let kelvin = ::image::open(image)
+                .map_err(|e| e.to_string())
+                .and_then(|decoded| {
+                    color::image_kelvin(&decoded.to_rgb8())
+                        .ok_or_else(|| "image has no color to match".to_string())
+                });
+            match kelvin {
+                Ok(kelvin) => manual_set(&config, &args, kelvin),
+                Err(e) => {
+                    eprintln!("Failed to match {}: {e}", image.display());
                     process::exit(1);
                 }
-                let state_file = state::expand_path(&config.daemon.state_file);
-                if let Some(ref p) = state_file {
-                    let _ = fs::remove_file(p);
+            }
+        }
+        Some(Commands::Cycle) => {
+            let steps = &config.cycle.steps;
+            if steps.is_empty() {
+                eprintln!("No cycle steps configured; set `[cycle] steps` in the config");
+                process::exit(1);
+            }
+            let last = state::load_cycle_index(&config.cycle.state_file);
+            let index = state::next_cycle_index(last, steps.len());
+            if !args.dry_run {
+                if let Err(e) = state::save_cycle_index(&config.cycle.state_file, index) {
+                    log::warn!("Failed to save cycle index: {e}");
                 }
-                let status = format!(
-                    "temp={temperature}\nphase=manual\ntarget={temperature}\nprogress=1.00\n",
-                );
-                let _ = fs::write(&config.daemon.status_file, status);
+            }
+            manual_set(&config, &args, steps[index]);
+        }
+        Some(Commands::Toggle) => {
+            let current = read_status_temperature(&config.daemon.status_file);
+            let temperature = config.temperature.on(chrono::Local::now().weekday());
+            manual_set(&config, &args, toggle_target(current, &temperature));
+        }
+        Some(Commands::SetLocation {
+            latitude,
+            longitude,
+        }) => {
+            send_control(
+                &config.daemon.status_file,
+                &format!("location {latitude} {longitude}"),
+            );
+            if !args.quiet {
+                println!("Moving to {latitude}, {longitude}");
             }
         }
         Some(Commands::Pause) => {
-            let control_file = control_file_from_status(&config.daemon.status_file);
-            let _ = fs::write(&control_file, "pause\n");
+            send_control(&config.daemon.status_file, "pause");
             if !args.quiet {
                 println!("Paused");
             }
         }
+        Some(Commands::Reset) if args.dry_run => {
+            if !args.quiet {
+                for step in reset_steps(&config, chrono::Local::now().weekday()) {
+                    println!("Would {step}");
+                }
+            }
+        }
+        Some(Commands::Reset) => {
+            let mut failed = false;
+            for step in reset_steps(&config, chrono::Local::now().weekday()) {
+                match step {
+                    ResetStep::PauseDaemon => send_control(&config.daemon.status_file, "pause"),
+                    ResetStep::Temperature(kelvin) => {
+                        if let Err(e) = hyprctl::set_temperature(kelvin) {
+                            eprintln!("Error setting temperature: {e}");
+                            failed = true;
+                        }
+                    }
+                    ResetStep::Gamma(percent) => {
+                        if let Err(e) = hyprctl::set_gamma(percent) {
+                            eprintln!("Error setting gamma: {e}");
+                            failed = true;
+                        }
+                    }
+                    ResetStep::Backlight(percent) => {
+                        // Not everyone has brightnessctl; only complain if it's in use
+                        if let Err(e) = backlight::set_brightness(percent) {
+                            if config.backlight.enabled {
+                                eprintln!("Error setting backlight: {e}");
+                                failed = true;
+                            }
+                        }
+                    }
+                }
+            }
+            if failed {
+                process::exit(1);
+            }
+            if !args.quiet {
+                println!("Reset; the daemon is paused until `candela resume`");
+            }
+        }
+        Some(Commands::DumpState) => {
+            let content = read_status(&config.daemon.status_file);
+            let snapshot = transition::Snapshot::from_status(&content);
+            let saved = state::State::load(&config.daemon.state_file);
+            println!(
+                "{}",
+                render_dump(snapshot.as_ref(), saved.as_ref(), args.json)
+            );
+        }
         Some(Commands::Resume) => {
-            let control_file = control_file_from_status(&config.daemon.status_file);
-            let _ = fs::write(&control_file, "resume\n");
+            send_control(&config.daemon.status_file, "resume");
             if !args.quiet {
                 println!("Resumed");
             }
         }
-        Some(Commands::Config) => {
-            if args.json {
-                match serde_json::to_string(&config) {
-                    Ok(json) => println!("{json}"),
-                    Err(e) => {
-                        eprintln!("Failed to serialize config: {e}");
+        Some(Commands::Progress { value }) => {
+            let command = value.map_or_else(
+                || "progress release".to_string(),
+                |p| format!("progress {p}"),
+            );
+            send_control(&config.daemon.status_file, &command);
+            if !args.quiet {
+                match value {
+                    Some(p) => println!("Progress pinned at {p:.2}"),
+                    None => println!("Progress released"),
+                }
+            }
+        }
+        Some(Commands::TestHooks { phase }) => {
+            let Some(command) = hooks::command_for(&config.hooks, &phase) else {
+                println!("No {phase} hook configured (or hooks disabled)");
+                return;
+            };
+            match hooks::fire(command, &phase) {
+                Ok(status) => {
+                    println!("Ran {phase} hook `{command}`: {status}");
+                    if !status.success() {
                         process::exit(1);
                     }
                 }
+                Err(e) => {
+                    eprintln!("Failed to run {phase} hook `{command}`: {e}");
+                    process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Doctor) => {
+            report_doctor(&doctor::run(&config, config_path.as_deref(), None), &args);
+        }
+        Some(Commands::Config { env: true }) => {
+            println!("{}", config::env_lines(&config).join("\n"));
+        }
+        Some(Commands::Config { env: false }) => match render_config(&config, args.json) {
+            Ok(output) => println!("{output}"),
+            Err(e) => {
+                eprintln!("Failed to serialize config: {e}");
+                process::exit(1);
+            }
+        },
+        Some(Commands::Preview {
+            compare: Some(ref other),
+            date,
+            step_minutes,
+            ..
+        }) => {
+            let date = date.unwrap_or_else(|| chrono::Local::now().date_naive());
+            let step = step_minutes.unwrap_or(COMPARE_STEP_MINUTES);
+            let other = config::load(Some(other), true).and_then(|mut other| {
+                resolve_location(&mut other, persistence, false)?;
+                let active = scheduler::Schedule::new(config.clone())?;
+                let other = scheduler::Schedule::new(other)?;
+                Ok(compare_days(&active, &other, date, step))
+            });
+            match other {
+                Ok(rows) => println!("{}", render_comparison(&rows, args.json)),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Preview {
+            compare: None,
+            day,
+            date,
+            step_minutes,
+            ..
+        }) if day || date.is_some() || step_minutes.is_some() => {
+            let date = date.unwrap_or_else(|| chrono::Local::now().date_naive());
+            let step = step_minutes.unwrap_or(DAY_STEP_MINUTES);
+            match scheduler::Schedule::new(config.clone()) {
+                Ok(schedule) => {
+                    println!(
+                        "{}",
+                        render_day(&preview_day(&schedule, date, step), args.json)
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Invalid schedule configuration: {e}");
+                    process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Preview {
+            animate,
+            compare: None,
+            ..
+        }) => {
+            let colorterm = std::env::var("COLORTERM").ok();
+            if animate && color::supports_truecolor(colorterm.as_deref()) {
+                animate_preview(&config);
             } else {
-                match toml::to_string_pretty(&config) {
-                    Ok(toml) => println!("{toml}"),
-                    Err(e) => {
-                        eprintln!("Failed to serialize config: {e}");
-                        process::exit(1);
-                    }
+                for (progress, temp) in preview_steps(&config) {
+                    println!("{progress:.2} {temp}K");
                 }
             }
         }
     }
 }
 
-fn control_file_from_status(status_file: &str) -> std::path::PathBuf {
-    std::path::PathBuf::from(status_file).with_extension("control")
-}
+const PREVIEW_STEPS: u32 = 20;
+const PREVIEW_FRAME: Duration = Duration::from_millis(150);
+const PREVIEW_BAR_WIDTH: usize = 40;
 
-const fn should_set_temperature(optimize_updates: bool, last_sent: Option<u16>, current: u16) -> bool {
-    if !optimize_updates {
-        return true;
+/// `candela dump-state` output: the daemon's published transition fields and
+/// the saved state file, either of which may be missing.
+fn render_dump(
+    snapshot: Option<&transition::Snapshot>,
+    saved: Option<&state::State>,
+    json: bool,
+) -> String {
+    if json {
+        return serde_json::json!({ "transition": snapshot, "state": saved }).to_string();
     }
+    let transition = snapshot.map_or_else(
+        || vec!["  (no running daemon reported one)".to_string()],
+        |s| {
+            vec![
+                format!("  start_temp = {}", s.start_temp),
+                format!("  target = {}", s.target),
+                format!("  start_timestamp = {}", s.start_timestamp),
+                format!("  progress = {:.2}", s.progress),
+                format!("  in_transition = {}", s.in_transition),
+            ]
+        },
+    );
+    let state = saved.map_or_else(
+        || vec!["  (none)".to_string()],
+        |s| {
+            toml::to_string(s)
+                .unwrap_or_default()
+                .lines()
+                .map(|line| format!("  {line}"))
+                .collect()
+        },
+    );
+    std::iter::once("transition:".to_string())
+        .chain(transition)
+        .chain(std::iter::once("state file:".to_string()))
+        .chain(state)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-    match last_sent {
-        Some(prev) => prev != current,
-        None => true,
-    }
+/// One action of `candela reset`.
+#[derive(Debug, PartialEq, Eq)]
+enum ResetStep {
+    /// Stop a running daemon from reapplying its schedule over the reset.
+    PauseDaemon,
+    Temperature(u16),
+    Gamma(u8),
+    Backlight(u8),
 }
 
-#[allow(clippy::too_many_lines, reason = "daemon loop is inherently sequential")]
-fn run_daemon(
-    config: &config::Config,
-    dry_run: bool,
-    quiet: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    if !quiet {
-        log::info!("Starting candela daemon");
+impl std::fmt::Display for ResetStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PauseDaemon => write!(f, "pause the daemon"),
+            Self::Temperature(kelvin) => write!(f, "set the temperature to {kelvin}K"),
+            Self::Gamma(percent) => write!(f, "set gamma to {percent}%"),
+            Self::Backlight(percent) => write!(f, "set the backlight to {percent}%"),
+        }
     }
+}
 
-    hyprctl::ensure_hyprsunset_running()?;
+/// What `candela reset` does, in order: pause the daemon first so it can't
+/// race the reset, then go straight to hyprctl and brightnessctl. Gamma goes
+/// back to 100% too, in case a dimmed night left the screen dark.
+fn reset_steps(config: &config::Config, weekday: chrono::Weekday) -> [ResetStep; 4] {
+    [
+        ResetStep::PauseDaemon,
+        ResetStep::Temperature(config.temperature.on(weekday).day),
+        ResetStep::Gamma(100),
+        ResetStep::Backlight(100),
+    ]
+}
 
-    if !quiet {
-        log::info!("Mode: {:?}", config.mode);
+/// Applies `temperature` as a manual override, dropping any saved transition.
+/// A running daemon is told to hold it until the schedule moves on.
+fn manual_set(config: &config::Config, args: &Args, temperature: u16) {
+    let current = read_status_temperature(&config.daemon.status_file);
+    let changed = set_changes_temperature(current, temperature);
+    if args.json {
+        println!(r#"{{"changed":{changed}}}"#);
+    } else if !args.quiet {
+        if changed {
+            println!("Setting temperature to {temperature}K");
+        } else {
+            println!("Already at {temperature}K");
+        }
     }
+    if args.dry_run {
+        return;
+    }
+    if changed {
+        if let Err(e) = hyprctl::set_temperature(temperature) {
+            eprintln!("Failed to set temperature: {e}");
+            process::exit(1);
+        }
+    }
+    // Already being at the temperature still makes it a manual override
+    record_manual_temperature(config, temperature);
+    send_control(&config.daemon.status_file, &format!("set {temperature}"));
+}
 
-    let shutdown = Arc::new(AtomicBool::new(false));
-    let shutdown_clone = shutdown.clone();
-    let paused = Arc::new(AtomicBool::new(false));
-
-    let result = ctrlc::set_handler(move || {
-        shutdown_clone.store(true, Ordering::SeqCst);
-    });
-
-    let control_file = control_file_from_status(&config.daemon.status_file);
-    let status_file = std::path::PathBuf::from(&config.daemon.status_file);
-    let state_file = config.daemon.state_file.clone();
-
-    let scheduler = scheduler::Schedule::new(config.clone())
-        .map_err(|e| format!("Invalid schedule configuration: {e}"))?;
-
-    let initial_temp = if config.mode == config::Mode::Auto || config.mode == config::Mode::Fixed {
-        let target_temp = scheduler.target_temperature();
-
-        state::State::load(&state_file).map_or(target_temp, |saved_state| {
-            let max_age = u64::from(config.transition.duration_minutes) * 60 * 2;
-            if saved_state.age_seconds() < max_age {
-                log::info!("Resuming transition from saved state");
-                state::calculate_temperature_from_state(
-                    &saved_state,
-                    u64::from(config.transition.duration_minutes) * 60,
-                    &config.transition.easing,
-                )
-            } else {
-                log::info!("Saved state too old, calculating fresh");
-                target_temp
-            }
-        })
+/// Prints the doctor checklist (`--json` for bug reports) and exits, nonzero
+/// if any check failed.
+fn report_doctor(checks: &[doctor::Check], args: &Args) -> ! {
+    if args.json {
+        println!("{}", doctor::render_json(checks));
     } else {
-        config.temperature.day
+        println!("{}", doctor::render(checks));
+    }
+    process::exit(i32::from(!doctor::passed(checks)));
+}
+
+/// `candela toggle`: the day or night temperature, whichever `current` is
+/// further from. Without a status file this switches to night.
+fn toggle_target(current: Option<u16>, temperature: &config::Temperature) -> u16 {
+    let Some(current) = current else {
+        return temperature.night;
     };
+    let distance = |kelvin| {
+        transition::interpolation_temperature(current)
+            .abs_diff(transition::interpolation_temperature(kelvin))
+    };
+    if distance(temperature.night) < distance(temperature.day) {
+        temperature.day
+    } else {
+        temperature.night
+    }
+}
 
-    let mut transition = transition::Transition::new_with_temp(config.clone(), initial_temp);
+/// How often `candela set --duration` steps the temperature.
+const SET_RAMP_STEP: Duration = Duration::from_millis(200);
 
-    let tick_interval = Duration::from_secs(config.daemon.tick_interval_seconds);
+/// The temperature `elapsed` into a `duration`-long ramp from `start` to
+/// `target`, eased like a scheduled transition.
+fn ramp_step(
+    config: &config::Transition,
+    start: u16,
+    target: u16,
+    elapsed: Duration,
+    duration: Duration,
+) -> u16 {
+    let progress = (elapsed.as_secs_f64() / duration.as_secs_f64()).min(1.0);
+    transition::temperature_at(start, target, progress, config)
+}
 
-    let mut tick_count = 0;
-    let status_update_interval = if config.daemon.status_update_interval == 0 {
-        1
-    } else {
-        config.daemon.status_update_interval
+/// `candela set --duration`: eases from the status file's temperature to
+/// `temperature` with the configured easing. A running daemon ramps it as a
+/// timed override, so the two don't fight over the temperature. Otherwise
+/// the ramp runs here: Ctrl-C stops at the last step applied, which is then
+/// recorded like a plain `set`. Without a known starting temperature this is
+/// a plain `set`.
+fn ramped_set(config: &config::Config, args: &Args, temperature: u16, duration: Duration) {
+    let current = read_status_temperature(&config.daemon.status_file);
+    let Some(start) =
+        current.filter(|&current| set_changes_temperature(Some(current), temperature))
+    else {
+        manual_set(config, args, temperature);
+        return;
     };
+    if args.json {
+        println!(r#"{{"changed":true}}"#);
+    } else if !args.quiet {
+        println!(
+            "Easing from {start}K to {temperature}K over {}s",
+            duration.as_secs()
+        );
+    }
+    if args.dry_run {
+        return;
+    }
 
-    let mut last_set_temperature: Option<u16> = None;
-
-    loop {
-        if let Ok(content) = fs::read_to_string(&control_file) {
-            for line in content.lines() {
-                match line.trim() {
-                    "pause" => {
-                        paused.store(true, Ordering::SeqCst);
-                    }
-                    "resume" => {
-                        paused.store(false, Ordering::SeqCst);
-                    }
-                    _ => {}
-                }
+    let command = format!("set {temperature} over {}", duration.as_secs());
+    match socket::send(
+        &socket::path_from_status(&config.daemon.status_file),
+        &command,
+    ) {
+        Ok(reply) => {
+            if let Some(error) = reply.strip_prefix("error: ") {
+                eprintln!("Daemon rejected {command:?}: {}", error.trim_end());
+                process::exit(1);
             }
-            let _ = fs::write(&control_file, "");
+            return;
         }
+        Err(e) => log::debug!("No daemon to ramp for us ({e}); ramping here"),
+    }
 
-        if shutdown.load(Ordering::SeqCst) {
-            if !dry_run {
-                let now = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-                let start = transition.transition_start_timestamp();
-                let elapsed = now.saturating_sub(start);
-
-                let state = state::State {
-                    transition_start_temp: transition.transition_start_temp(),
-                    transition_start_timestamp: start,
-                    elapsed_seconds: elapsed,
-                    target_temp: transition.target_temperature(),
-                };
-                let _ = state.save(&state_file);
+    let interrupted = interrupt_flag();
+    let started = std::time::Instant::now();
+    let mut applied = start;
+    while !interrupted.load(Ordering::SeqCst) {
+        let elapsed = started.elapsed();
+        let step = ramp_step(&config.transition, start, temperature, elapsed, duration);
+        if step != applied {
+            if let Err(e) = hyprctl::set_temperature(step) {
+                eprintln!("Failed to set temperature: {e}");
+                process::exit(1);
             }
-            break;
+            applied = step;
         }
-
-        if paused.load(Ordering::SeqCst) {
-            thread::sleep(Duration::from_millis(100));
-            continue;
+        if elapsed >= duration {
+            break;
         }
+        thread::sleep(SET_RAMP_STEP);
+    }
 
-        let now = chrono::Local::now();
-        let phase = scheduler.current_phase_at(now);
-        let target_temp = match phase {
-            scheduler::Phase::Day | scheduler::Phase::TransitioningToDay => config.temperature.day,
-            scheduler::Phase::Night | scheduler::Phase::TransitioningToNight => {
-                config.temperature.night
-            }
-        };
+    if applied != temperature && !args.quiet && !args.json {
+        println!("Interrupted at {applied}K");
+    }
+    record_manual_temperature(config, applied);
+    send_control(&config.daemon.status_file, &format!("set {applied}"));
+}
 
-        if let Some(window) = scheduler.transition_window_at(now) {
-            let elapsed = now.signed_duration_since(window.start);
-            let elapsed = elapsed.to_std().unwrap_or_default();
-            transition.align_with_schedule(window.start_temp, window.target_temp, elapsed);
-        } else {
-            transition.update(target_temp);
-        }
+/// Drops any saved transition and reports `temperature` in the status file
+/// as a manual setting.
+fn record_manual_temperature(config: &config::Config, temperature: u16) {
+    if let Some(path) = state::expand_path(&config.daemon.state_file) {
+        let _ = fs::remove_file(path);
+    }
+    let status = Status::manual(temperature, transition::current_unix_timestamp())
+        .render(config.daemon.status_format);
+    let _ = write_status_file(std::path::Path::new(&config.daemon.status_file), &status);
+}
 
-        let temp = transition.current_temperature();
-        let target = transition.target_temperature();
-        let progress = transition.progress();
+/// A transition placed where the schedule has it at `now`: partway through
+/// the current window, or settled at the phase's target.
+fn scheduled_transition(
+    config: &config::Config,
+    schedule: &scheduler::Schedule,
+    now: chrono::DateTime<chrono::Local>,
+) -> transition::Transition {
+    let target = schedule.target_temperature_at(now);
+    let mut transition = transition::Transition::new_with_temp(config.clone(), target);
+    let now = TickTime {
+        wall: now,
+        monotonic: std::time::Instant::now(),
+    };
+    Control::Scheduled.drive(&mut transition, schedule, now, target);
+    transition
+}
 
-        if !quiet {
-            log::info!(
-                "Phase: {phase:?}, Temp: {temp}, Target: {target}, Progress: {progress:.2}",
-            );
-        }
+/// The temperature to show for `transition`: quantized to
+/// `transition.temperature_step` on the way, exact once it arrives.
+fn displayed_temperature(transition: &transition::Transition, config: &config::Config) -> u16 {
+    match transition.current_temperature() {
+        current if current == transition.target_temperature() => current,
+        current => transition::quantize(
+            current,
+            config.transition.temperature_step,
+            config.transition.temperature_step_rounding,
+        ),
+    }
+}
 
-        if !dry_run {
-            if should_set_temperature(config.daemon.optimize_updates, last_set_temperature, temp) {
-                if let Err(e) = hyprctl::set_temperature(temp) {
-                    log::error!("Error setting temperature: {e}");
-                } else {
-                    last_set_temperature = Some(temp);
-                    log::info!("Set temperature to {temp}");
-                }
-            }
+/// `--once`: one daemon tick without the loop, for cron or hypridle. Sets the
+/// scheduled temperature and writes the status file, unless `--dry-run`.
+fn apply_once(
+    config: &config::Config,
+    config_path: Option<&str>,
+    args: &Args,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let schedule = scheduler::Schedule::new(config.clone())?;
+    let now = chrono::Local::now();
+    let phase = schedule.current_phase_at(now);
+    let transition = scheduled_transition(config, &schedule, now);
+    let temp = displayed_temperature(&transition, config);
 
-            tick_count += 1;
-            if tick_count >= status_update_interval {
-                tick_count = 0;
-                let status = format!(
-                    "temp={temp}\nphase={phase}\ntarget={target}\nprogress={progress:.2}\n",
-                    phase = phase.as_str(),
-                );
-                let _ = fs::write(&status_file, status);
-            }
+    if !args.quiet {
+        println!("{}: setting temperature to {temp}K", phase.as_str());
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+    hyprctl::set_temperature(temp)?;
+    let updated_at = transition::current_unix_timestamp();
+    let status = Status {
+        config: Some((1, config::file_hash(config_path))),
+        ..Status::of_transition(&transition, temp, phase, updated_at)
+    };
+    let status = status.render(config.daemon.status_format);
+    write_status_file(std::path::Path::new(&config.daemon.status_file), &status)?;
+    Ok(())
+}
+
+/// Evenly spaced `(progress, temperature)` samples of the day-to-night transition.
+fn preview_steps(config: &config::Config) -> Vec<(f64, u16)> {
+    (0..=PREVIEW_STEPS)
+        .map(|step| {
+            let progress = f64::from(step) / f64::from(PREVIEW_STEPS);
+            let (day, night) = (config.temperature.day, config.temperature.resolved_night());
+            let temp = transition::temperature_at(day, night, progress, &config.transition);
+            (progress, temp)
+        })
+        .collect()
+}
+
+/// Default minutes between the time points `preview --compare` samples.
+const COMPARE_STEP_MINUTES: u32 = 30;
+/// Default minutes between the time points `preview --day` samples.
+const DAY_STEP_MINUTES: u32 = 15;
+
+/// `(time, phase, temperature)` samples of `schedule` across `date`, every
+/// `step_minutes`. Purely computed; nothing is sent to the display.
+fn preview_day(
+    schedule: &scheduler::Schedule,
+    date: chrono::NaiveDate,
+    step_minutes: u32,
+) -> Vec<(chrono::NaiveTime, scheduler::Phase, u16)> {
+    use chrono::TimeZone;
+
+    (0..24 * 60)
+        .step_by(step_minutes as usize)
+        .filter_map(|minute| {
+            let time = chrono::NaiveTime::from_num_seconds_from_midnight_opt(minute * 60, 0)?;
+            // Times skipped by a DST change don't exist locally
+            let now = chrono::Local
+                .from_local_datetime(&date.and_time(time))
+                .earliest()?;
+            Some((
+                time,
+                schedule.current_phase_at(now),
+                schedule.temperature_at(now),
+            ))
+        })
+        .collect()
+}
+
+/// `(time, a, b)` rows pairing `preview_day` of two schedules.
+fn compare_days(
+    a: &scheduler::Schedule,
+    b: &scheduler::Schedule,
+    date: chrono::NaiveDate,
+    step_minutes: u32,
+) -> Vec<(chrono::NaiveTime, u16, u16)> {
+    preview_day(a, date, step_minutes)
+        .into_iter()
+        .zip(preview_day(b, date, step_minutes))
+        .map(|((time, _, a), (_, _, b))| (time, a, b))
+        .collect()
+}
+
+fn render_day(rows: &[(chrono::NaiveTime, scheduler::Phase, u16)], json: bool) -> String {
+    if json {
+        let rows: Vec<String> = rows
+            .iter()
+            .map(|(time, phase, temp)| {
+                format!(
+                    r#"{{"time":"{}","phase":"{}","temperature":{temp}}}"#,
+                    time.format("%H:%M"),
+                    phase.as_str()
+                )
+            })
+            .collect();
+        return format!("[{}]", rows.join(","));
+    }
+    rows.iter()
+        .map(|(time, phase, temp)| {
+            format!(
+                "{}  {:<22}  {temp:>5}K",
+                time.format("%H:%M"),
+                phase.as_str()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_comparison(rows: &[(chrono::NaiveTime, u16, u16)], json: bool) -> String {
+    if json {
+        let rows: Vec<String> = rows
+            .iter()
+            .map(|(time, a, b)| format!(r#"{{"time":"{}","a":{a},"b":{b}}}"#, time.format("%H:%M")))
+            .collect();
+        return format!("[{}]", rows.join(","));
+    }
+    let header = format!("{:<5}  {:>6}  {:>6}", "time", "a", "b");
+    let lines = rows.iter().map(|(time, a, b)| {
+        let marker = if a == b { "" } else { "  *" };
+        format!("{}  {a:>5}K  {b:>5}K{marker}", time.format("%H:%M"))
+    });
+    std::iter::once(header)
+        .chain(lines)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn animate_preview(config: &config::Config) {
+    let mut stdout = std::io::stdout();
+    for (progress, temp) in preview_steps(config) {
+        let bar = " ".repeat(PREVIEW_BAR_WIDTH);
+        let background = color::ansi_background(color::kelvin_to_rgb(temp));
+        print!(
+            "\r{background}{bar}{} {progress:.2} {temp:>5}K",
+            color::ANSI_RESET
+        );
+        let _ = stdout.flush();
+        thread::sleep(PREVIEW_FRAME);
+    }
+    println!();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Human,
+    Json,
+    /// Bare values, no units or labels, for `awk`-style consumers.
+    Plain,
+}
+
+const fn output_format(args: &Args) -> OutputFormat {
+    if args.json {
+        OutputFormat::Json
+    } else if args.plain {
+        OutputFormat::Plain
+    } else {
+        OutputFormat::Human
+    }
+}
+
+/// The status file's `updated_at` stamp, and whether it's older than
+/// `stale_after` allows, i.e. the daemon has likely died.
+fn status_freshness(daemon: &config::Daemon) -> (Option<u64>, bool) {
+    let updated_at =
+        read_status_field(&daemon.status_file, "updated_at").and_then(|at| at.parse().ok());
+    let now = transition::current_unix_timestamp();
+    (updated_at, is_stale(updated_at, now, stale_after(daemon)))
+}
+
+/// Seconds without a status write before the status counts as stale: three
+/// of whichever the daemon does less often, ticking or writing status.
+fn stale_after(daemon: &config::Daemon) -> u64 {
+    let interval = daemon
+        .tick_interval_seconds
+        .max(status_write_interval(daemon).as_secs());
+    interval.saturating_mul(3)
+}
+
+/// Whether status stamped `updated_at` is more than `limit` seconds old at
+/// `now`. Unstamped status (no file, or an older daemon) is never stale.
+fn is_stale(updated_at: Option<u64>, now: u64, limit: u64) -> bool {
+    updated_at.is_some_and(|at| now.saturating_sub(at) > limit)
+}
+
+/// Stale status fails JSON output so scripts can react; plain output, which
+/// has no room for a marker, gets a note on stderr instead.
+fn exit_if_stale(stale: bool, format: OutputFormat) {
+    if !stale {
+        return;
+    }
+    match format {
+        OutputFormat::Json => process::exit(1),
+        OutputFormat::Plain => eprintln!("Status is stale; is the daemon running?"),
+        OutputFormat::Human => {}
+    }
+}
+
+/// `now` output, marked `stale` where the format has room for it.
+fn render_now(temp: u16, stale: bool, format: OutputFormat) -> String {
+    match (format, stale) {
+        (OutputFormat::Human, false) => format!("{temp}K"),
+        (OutputFormat::Human, true) => format!("{temp}K\nstale=true"),
+        (OutputFormat::Json, false) => format!(r#"{{"temp":{temp}}}"#),
+        (OutputFormat::Json, true) => format!(r#"{{"temp":{temp},"stale":true}}"#),
+        (OutputFormat::Plain, _) => temp.to_string(),
+    }
+}
+
+/// `candela wait --phase`: the phase the awaited transition should end in.
+fn parse_wait_phase(phase: &str) -> Result<scheduler::Phase, String> {
+    match phase {
+        "day" => Ok(scheduler::Phase::Day),
+        "night" => Ok(scheduler::Phase::Night),
+        other => Err(format!(
+            "Unknown phase {other:?}; expected `day` or `night`"
+        )),
+    }
+}
+
+/// Sleeps until the wall clock reaches `wake`, re-checking every second so
+/// suspend and clock changes don't oversleep. Returns `false` if interrupted
+/// by Ctrl-C first.
+fn wait_until(wake: chrono::DateTime<chrono::Local>) -> bool {
+    let interrupted = interrupt_flag();
+    while !interrupted.load(Ordering::SeqCst) {
+        let Ok(remaining) = (wake - chrono::Local::now()).to_std() else {
+            return true;
+        };
+        thread::sleep(remaining.min(Duration::from_secs(1)));
+    }
+    false
+}
+
+/// Default for `candela watch --interval`, in seconds.
+const WATCH_INTERVAL_SECONDS: u64 = 1;
+
+/// `candela watch`: polls the status file every `interval` and prints it
+/// whenever the temperature or phase changed, until Ctrl-C.
+fn watch_status(status_file: &str, interval: Duration, format: OutputFormat) {
+    let interrupted = interrupt_flag();
+    let mut last = None;
+    while !interrupted.load(Ordering::SeqCst) {
+        let (temp, phase, target, progress) = read_status_file(status_file);
+        if replace_if_changed(&mut last, (temp, phase.clone())) {
+            let extras = StatusExtras::default();
+            println!(
+                "{}",
+                render_status(temp, &phase, target, progress, &extras, format)
+            );
+            let _ = std::io::stdout().flush();
+        }
+        let next = std::time::Instant::now() + interval;
+        while !interrupted.load(Ordering::SeqCst) && std::time::Instant::now() < next {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+/// Stores `current` in `last`, returning whether it differed.
+fn replace_if_changed<T: PartialEq>(last: &mut Option<T>, current: T) -> bool {
+    if last.as_ref() == Some(&current) {
+        return false;
+    }
+    *last = Some(current);
+    true
+}
+
+/// Set once Ctrl-C is pressed, for commands that stop early instead of dying.
+fn interrupt_flag() -> Arc<AtomicBool> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = interrupted.clone();
+    if let Err(e) = ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst)) {
+        log::warn!("Error setting signal handler: {e}");
+    }
+    interrupted
+}
+
+/// Phase output, using the same names the daemon writes to the status file.
+fn render_phase(phase: scheduler::Phase, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Human | OutputFormat::Plain => phase.as_str().to_string(),
+        OutputFormat::Json => format!(r#"{{"phase":"{}"}}"#, phase.as_str()),
+    }
+}
+
+/// `candela sun` output, with times in the offset they were given in.
+fn render_sun(
+    sunrise: chrono::DateTime<chrono::FixedOffset>,
+    sunset: chrono::DateTime<chrono::FixedOffset>,
+    format: OutputFormat,
+) -> String {
+    let day_length = sunset - sunrise;
+    let noon = sunrise + day_length / 2;
+    let minutes = day_length.num_minutes();
+    match format {
+        OutputFormat::Human => format!(
+            "sunrise {}\nsunset {}\nsolar noon {}\nday length {}h{:02}m",
+            sunrise.format("%H:%M"),
+            sunset.format("%H:%M"),
+            noon.format("%H:%M"),
+            minutes / 60,
+            minutes % 60
+        ),
+        OutputFormat::Plain => format!(
+            "{} {} {} {minutes}",
+            sunrise.format("%H:%M"),
+            sunset.format("%H:%M"),
+            noon.format("%H:%M")
+        ),
+        OutputFormat::Json => format!(
+            r#"{{"sunrise":"{}","sunset":"{}","solar_noon":"{}","day_length_minutes":{minutes}}}"#,
+            sunrise.to_rfc3339(),
+            sunset.to_rfc3339(),
+            noon.to_rfc3339()
+        ),
+    }
+}
+
+/// Phase label values of `candela_phase`: the schedule's phases plus `manual`.
+const METRIC_PHASES: [&str; 5] = [
+    scheduler::Phase::Day.as_str(),
+    scheduler::Phase::TransitioningToNight.as_str(),
+    scheduler::Phase::Night.as_str(),
+    scheduler::Phase::TransitioningToDay.as_str(),
+    "manual",
+];
+
+/// Status in the Prometheus text exposition format. `candela_phase` is 1 for
+/// the current phase and 0 for the others.
+fn render_metrics(temp: u16, phase: &str, target: u16, progress: f64) -> String {
+    let gauge = |name: &str, help: &str, samples: &[(String, String)]| {
+        let mut lines = vec![
+            format!("# HELP {name} {help}"),
+            format!("# TYPE {name} gauge"),
+        ];
+        lines.extend(
+            samples
+                .iter()
+                .map(|(labels, value)| format!("{name}{labels} {value}")),
+        );
+        lines.join("\n")
+    };
+    let phases: Vec<(String, String)> = METRIC_PHASES
+        .iter()
+        .map(|name| {
+            (
+                format!(r#"{{phase="{name}"}}"#),
+                u8::from(*name == phase).to_string(),
+            )
+        })
+        .collect();
+
+    [
+        gauge(
+            "candela_temperature_kelvin",
+            "Current color temperature (0 = off).",
+            &[(String::new(), temp.to_string())],
+        ),
+        gauge(
+            "candela_target_temperature_kelvin",
+            "Temperature the current transition is heading to.",
+            &[(String::new(), target.to_string())],
+        ),
+        gauge(
+            "candela_transition_progress",
+            "Progress through the current transition, from 0 to 1.",
+            &[(String::new(), format!("{progress:.2}"))],
+        ),
+        gauge(
+            "candela_phase",
+            "Current phase (1 for the active phase).",
+            &phases,
+        ),
+    ]
+    .join("\n")
+        + "\n"
+}
+
+/// Fields `status` shows after the basics, each left out when unknown.
+#[derive(Debug, Clone, Copy, Default)]
+struct StatusExtras {
+    melanopic: Option<f64>,
+    /// `eta_seconds` and `target_time`, as the daemon wrote them.
+    eta: Option<(u64, u64)>,
+    updated_at: Option<u64>,
+    stale: bool,
+}
+
+impl StatusExtras {
+    /// `(key, value)` pairs in output order. Every value is a number or a
+    /// flag, so it reads the same in `key=value` and JSON output.
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        if let Some(factor) = self.melanopic {
+            fields.push(("melanopic_factor", format!("{factor:.2}")));
+        }
+        if let Some((eta, target_time)) = self.eta {
+            fields.push(("eta_seconds", eta.to_string()));
+            fields.push(("target_time", target_time.to_string()));
+        }
+        if let Some(updated_at) = self.updated_at {
+            fields.push(("updated_at", updated_at.to_string()));
+        }
+        if self.stale {
+            fields.push(("stale", "true".to_string()));
+        }
+        fields
+    }
+}
+
+/// Status output; the plain layout is `temp<TAB>phase<TAB>target<TAB>progress`,
+/// plus the melanopic factor when asked for. Plain output has no room for
+/// the other extras.
+fn render_status(
+    temp: u16,
+    phase: &str,
+    target: u16,
+    progress: f64,
+    extras: &StatusExtras,
+    format: OutputFormat,
+) -> String {
+    let extra = extras.fields();
+    match format {
+        OutputFormat::Human => {
+            let lines = extra.iter().map(|(key, value)| format!("\n{key}={value}"));
+            format!(
+                "temp={temp}\nphase={phase}\ntarget={target}\nprogress={progress:.2}{}",
+                lines.collect::<Vec<_>>().concat()
+            )
+        }
+        OutputFormat::Json => {
+            let fields = format!(r#""temp":{temp},"phase":"{phase}","target":{target}"#);
+            let extra = extra
+                .iter()
+                .map(|(key, value)| format!(r#","{key}":{value}"#));
+            let extra = extra.collect::<Vec<_>>().concat();
+            format!(r#"{{{fields},"progress":{progress:.2}{extra}}}"#)
+        }
+        OutputFormat::Plain => {
+            let factor = extras.melanopic.map(|f| format!("\t{f:.2}"));
+            format!(
+                "{temp}\t{phase}\t{target}\t{progress:.2}{}",
+                factor.unwrap_or_default()
+            )
+        }
+    }
+}
+
+/// Fills in `config.location` from its city, `detect` or `from_timezone`
+/// settings. With `lookup` a location to detect is looked up (slow, and may
+/// go over the network); otherwise only the daemon's cached result is used.
+fn resolve_location(
+    config: &mut config::Config,
+    persistence: Persistence,
+    lookup: bool,
+) -> Result<(), String> {
+    let detected = location::wants_detection(&config.location, &config.mode)
+        .then(|| {
+            let cache = location_cache(&config.daemon, persistence);
+            if lookup {
+                location::detect(cache.as_deref())
+            } else {
+                location::cached(cache.as_deref())
+            }
+        })
+        .flatten();
+    config.location = location::resolve(&config.location, detected)?;
+    Ok(())
+}
+
+/// Where a detected location is cached: `location` next to the state file,
+/// unless the daemon keeps no state.
+fn location_cache(daemon: &config::Daemon, persistence: Persistence) -> Option<std::path::PathBuf> {
+    if persistence != Persistence::Full {
+        return None;
+    }
+    let state = state::expand_path(&daemon.state_file)?;
+    Some(state.parent()?.join("location"))
+}
+
+/// Fixes up out-of-range config values, printing a warning for each one.
+/// These go to stderr directly: a bad value should be seen even without
+/// `RUST_LOG`.
+fn validate(config: &mut config::Config) {
+    for warning in config.validate() {
+        eprintln!("Warning: {warning}");
+    }
+}
+
+/// Applies command-line overrides on top of the file and environment layers.
+fn apply_cli(config: &mut config::Config, args: &Args) {
+    if let Some(ref easing) = args.easing {
+        config.transition.easing.clone_from(easing);
+    }
+    if let Some(tick) = args.tick {
+        config.daemon.tick_interval_seconds = tick;
+    }
+    if let Some(lat) = args.lat {
+        config.location.latitude = lat;
+    }
+    if let Some(lon) = args.lon {
+        config.location.longitude = lon;
+    }
+}
+
+fn render_config(
+    config: &config::Config,
+    json: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if json {
+        Ok(serde_json::to_string(config)?)
+    } else {
+        Ok(toml::to_string_pretty(config)?)
+    }
+}
+
+fn control_file_from_status(status_file: &str) -> std::path::PathBuf {
+    std::path::PathBuf::from(status_file).with_extension("control")
+}
+
+/// Delivers a control line to the daemon: over its socket when it's
+/// listening, otherwise through the control file it polls each tick.
+fn send_control(status_file: &str, line: &str) {
+    match socket::send(&socket::path_from_status(status_file), line) {
+        Ok(reply) => {
+            if let Some(error) = reply.strip_prefix("error: ") {
+                eprintln!("Daemon rejected {line:?}: {}", error.trim_end());
+            }
+        }
+        Err(_) => {
+            let _ = fs::write(control_file_from_status(status_file), format!("{line}\n"));
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ControlCommand {
+    Pause,
+    Resume,
+    /// `progress <0.0-1.0>` pins the transition, `progress release` unpins it.
+    ForceProgress(Option<f64>),
+    /// `set <kelvin>` overrides the schedule with a temperature.
+    Set(u16),
+    /// `set <kelvin> by <HH:MM>` ramps to a temperature by a deadline.
+    SetBy(u16, chrono::NaiveTime),
+    /// `set <kelvin> over <seconds>` ramps to a temperature over a duration.
+    SetOver(u16, Duration),
+    /// `location <lat> <lon>` moves the schedule to new coordinates.
+    SetLocation(f64, f64),
+}
+
+fn parse_control(line: &str) -> Option<ControlCommand> {
+    match line.trim() {
+        "pause" => Some(ControlCommand::Pause),
+        "resume" => Some(ControlCommand::Resume),
+        other if other.starts_with("location ") => {
+            let (latitude, longitude) = other["location ".len()..].trim().split_once(' ')?;
+            Some(ControlCommand::SetLocation(
+                parse_latitude(latitude.trim()).ok()?,
+                parse_longitude(longitude.trim()).ok()?,
+            ))
+        }
+        other if other.starts_with("set ") => {
+            let rest = &other["set ".len()..];
+            if let Some((temperature, seconds)) = rest.split_once(" over ") {
+                let seconds: u64 = seconds.trim().parse().ok().filter(|&s| s > 0)?;
+                return Some(ControlCommand::SetOver(
+                    temperature.trim().parse().ok()?,
+                    Duration::from_secs(seconds),
+                ));
+            }
+            let Some((temperature, deadline)) = rest.split_once(" by ") else {
+                return rest.trim().parse().ok().map(ControlCommand::Set);
+            };
+            Some(ControlCommand::SetBy(
+                temperature.trim().parse().ok()?,
+                parse_deadline(deadline.trim()).ok()?,
+            ))
+        }
+        other => match other.strip_prefix("progress ")?.trim() {
+            "release" => Some(ControlCommand::ForceProgress(None)),
+            value => parse_progress(value)
+                .ok()
+                .map(|p| ControlCommand::ForceProgress(Some(p))),
+        },
+    }
+}
+
+/// Moves the daemon to `latitude`/`longitude`, rebuilding the schedule in
+/// place. The transition (and with it the current temperature) is left alone,
+/// so any change in phase is eased into on the next tick.
+fn relocate(
+    config: &mut config::Config,
+    scheduler: &mut scheduler::Schedule,
+    latitude: f64,
+    longitude: f64,
+) -> Result<(), String> {
+    let mut moved = config.clone();
+    moved.location.latitude = latitude;
+    moved.location.longitude = longitude;
+    *scheduler = scheduler::Schedule::new(moved.clone())?;
+    *config = moved;
+    Ok(())
+}
+
+/// What the daemon persists between runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Persistence {
+    /// Status, transition state and history files.
+    Full,
+    /// Everything but the transition state (`daemon --no-state`).
+    NoState,
+    /// Nothing at all (`daemon --ephemeral`).
+    Ephemeral,
+}
+
+/// The `daemon` command's switches.
+#[derive(Debug, Clone, Copy)]
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "each flag mirrors a separate CLI switch"
+)]
+struct DaemonOptions {
+    dry_run: bool,
+    quiet: bool,
+    show_timings: bool,
+    trace: bool,
+    persistence: Persistence,
+}
+
+/// Files the daemon persists to between runs. An ephemeral daemon has none, so
+/// it neither resumes from nor leaves behind any state.
+struct DaemonFiles {
+    status: Option<std::path::PathBuf>,
+    state: Option<String>,
+    history: Option<String>,
+}
+
+impl DaemonFiles {
+    fn new(daemon: &config::Daemon, persistence: Persistence) -> Self {
+        if persistence == Persistence::Ephemeral {
+            return Self {
+                status: None,
+                state: None,
+                history: None,
+            };
+        }
+        Self {
+            status: Some(std::path::PathBuf::from(&daemon.status_file)),
+            state: (persistence == Persistence::Full).then(|| daemon.state_file.clone()),
+            history: daemon.history_file.clone(),
+        }
+    }
+
+    fn load_state(&self) -> Option<state::State> {
+        state::State::load(self.state.as_deref()?)
+    }
+
+    fn save_state(&self, state: &state::State) -> std::io::Result<()> {
+        self.state
+            .as_deref()
+            .map_or(Ok(()), |path| state.save(path))
+    }
+
+    fn append_history(&self, line: &str, max_lines: usize) -> std::io::Result<()> {
+        self.history
+            .as_deref()
+            .map_or(Ok(()), |path| state::append_history(path, line, max_lines))
+    }
+
+    fn write_status(&self, content: &str) -> std::io::Result<()> {
+        self.status
+            .as_deref()
+            .map_or(Ok(()), |path| write_status_file(path, content))
+    }
+}
+
+const INITIAL_SET_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Attempts at each set after the first, so a hyprsunset briefly gone during
+/// a Hyprland reload doesn't cost a whole tick.
+const SET_RETRY_ATTEMPTS: u32 = 3;
+
+/// Pause after failed set `attempt` (1-based): 100ms, doubling each time.
+fn set_retry_delay(attempt: u32) -> Duration {
+    Duration::from_millis(100) * 2u32.pow(attempt.saturating_sub(1).min(8))
+}
+
+/// Sends `temp` through `sink`, retrying per `attempts` and `delay`, and
+/// tracks it in `last_sent`. A failure forgets `last_sent`, since whatever
+/// hyprsunset shows is then unknown, so the next tick resends.
+fn send_temperature(
+    sink: &impl hyprctl::TemperatureSink,
+    last_sent: &mut Option<u16>,
+    temp: u16,
+    attempts: u32,
+    delay: fn(u32) -> Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let result = retry(attempts, delay, || sink.set_temperature(temp));
+    *last_sent = result.is_ok().then_some(temp);
+    result
+}
+
+/// Runs `f` up to `attempts` times (at least once), sleeping `delay(attempt)`
+/// after each failed attempt, and returns the first success or the last error.
+fn retry<T, E: std::fmt::Display>(
+    attempts: u32,
+    delay: impl Fn(u32) -> Duration,
+    mut f: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(e) if attempt < attempts => {
+                log::warn!("Attempt {attempt}/{attempts} failed: {e}; retrying");
+                thread::sleep(delay(attempt));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Writes the status file atomically: the content goes to a temp file in the
+/// same directory, which is then renamed over it, so pollers never see a
+/// partial write.
+fn write_status_file(path: &std::path::Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = status_temp_path(path, process::id());
+    let written = fs::write(&temp, content).and_then(|()| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// The temp file process `pid` writes `path` through. Keyed by pid so the
+/// daemon and a CLI `set` writing at once don't rename each other's halves.
+fn status_temp_path(path: &std::path::Path, pid: u32) -> std::path::PathBuf {
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(format!(".{pid}.tmp"));
+    path.with_file_name(temp_name)
+}
+
+/// The temperature `daemon.restore_on_exit` sets on shutdown, if enabled:
+/// `restore_temperature`, or `weekday`'s day temperature.
+fn exit_temperature(config: &config::Config, weekday: chrono::Weekday) -> Option<u16> {
+    config.daemon.restore_on_exit.then(|| {
+        config
+            .daemon
+            .restore_temperature
+            .unwrap_or_else(|| config.temperature.on(weekday).day)
+    })
+}
+
+/// Like `should_set_temperature`, tracked separately so a gamma change
+/// doesn't force a temperature call or vice versa.
+const fn should_set_gamma(optimize_updates: bool, last_sent: Option<u8>, current: u8) -> bool {
+    match last_sent {
+        Some(prev) if optimize_updates => prev != current,
+        _ => true,
+    }
+}
+
+/// Whether to send `current` after `last_sent`. With `optimize_updates` it
+/// has to have moved by at least `min_delta` kelvin, except that arriving at
+/// `target` always goes out so a transition lands exactly.
+const fn should_set_temperature(
+    optimize_updates: bool,
+    min_delta: u16,
+    last_sent: Option<u16>,
+    current: u16,
+    target: u16,
+) -> bool {
+    if !optimize_updates {
+        return true;
+    }
+
+    match last_sent {
+        Some(prev) if prev == current => false,
+        Some(prev) => current == target || prev.abs_diff(current) >= min_delta,
+        None => true,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LockAction {
+    /// Session is locked: skip the tick entirely.
+    Skip,
+    /// Session was just unlocked: jump to the correct value, then tick.
+    Unlocked,
+    /// Normal tick.
+    Tick,
+}
+
+const fn lock_action(was_locked: bool, locked: bool) -> LockAction {
+    match (was_locked, locked) {
+        (_, true) => LockAction::Skip,
+        (true, false) => LockAction::Unlocked,
+        (false, false) => LockAction::Tick,
+    }
+}
+
+/// Length in seconds of a transition heading in `direction`.
+fn transition_duration_seconds(config: &config::Config, direction: state::Direction) -> u64 {
+    let minutes = match direction {
+        state::Direction::ToDay => config.transition.duration_minutes.to_day(),
+        state::Direction::ToNight => config.transition.duration_minutes.to_night(),
+    };
+    u64::from(minutes) * 60
+}
+
+/// Whether a paused daemon should skip the whole tick (no status updates).
+fn pause_skips_tick(paused: bool, mode: config::PauseMode) -> bool {
+    paused && mode == config::PauseMode::Full
+}
+
+static STATUS_INTERVAL_DEPRECATION: std::sync::Once = std::sync::Once::new();
+
+/// Minimum time between status file writes. The deprecated tick-based
+/// `status_update_interval` is converted using the tick interval, with a
+/// warning the first time.
+fn status_write_interval(daemon: &config::Daemon) -> Duration {
+    let seconds = daemon.status_update_interval_seconds.unwrap_or_else(|| {
+        if daemon.status_update_interval > 1 {
+            STATUS_INTERVAL_DEPRECATION.call_once(|| {
+                log::warn!(
+                    "daemon.status_update_interval is deprecated, \
+                     use status_update_interval_seconds instead"
+                );
+            });
+        }
+        daemon
+            .status_update_interval
+            .saturating_mul(daemon.tick_interval_seconds)
+    });
+    Duration::from_secs(seconds)
+}
+
+fn status_write_due(
+    last_write: Option<std::time::Instant>,
+    now: std::time::Instant,
+    interval: Duration,
+) -> bool {
+    last_write.is_none_or(|last| now.saturating_duration_since(last) >= interval)
+}
+
+/// Time from `now` until the next occurrence of `deadline` (tomorrow if it
+/// has already passed today).
+fn duration_until(deadline: chrono::NaiveTime, now: chrono::NaiveDateTime) -> Duration {
+    let today = now.date().and_time(deadline);
+    let next = if today > now {
+        today
+    } else {
+        today + chrono::Duration::days(1)
+    };
+    (next - now).to_std().unwrap_or_default()
+}
+
+/// Logged once when the daemon settles into a hold, so a long quiet sleep
+/// doesn't look like a hang.
+fn hold_message(temp: u16, next: chrono::DateTime<chrono::Local>, sleep: Duration) -> String {
+    let minutes = sleep.as_secs() / 60;
+    format!(
+        "Holding {temp}K, next transition at {}, sleeping {}h{:02}m",
+        next.format("%H:%M"),
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// `sleep`, extended so that waking after it from `now` lands exactly on the
+/// start of a minute.
+fn align_to_minute(now: chrono::DateTime<chrono::Local>, sleep: Duration) -> Duration {
+    let Ok(offset) = chrono::Duration::from_std(sleep) else {
+        return sleep;
+    };
+    let past = (now + offset).timestamp_millis().rem_euclid(60_000);
+    if past == 0 {
+        sleep
+    } else {
+        sleep + Duration::from_millis((60_000 - past).unsigned_abs())
+    }
+}
+
+/// A manual `set` (instantly) or `set --by`/`--duration` (ramped) override. It holds its
+/// target after the ramp until the schedule's own target changes.
+struct Override {
+    start_temp: u16,
+    target: u16,
+    started: std::time::Instant,
+    duration: Duration,
+    schedule_target: u16,
+}
+
+/// Who drives the temperature. A manual override takes precedence the moment
+/// it arrives, ramping from the current temperature and suspending any
+/// scheduled transition. It expires once the schedule's target moves on. If
+/// that happens inside a transition window, the temperature rejoins the
+/// window where the schedule has it; otherwise it eases from wherever the
+/// override left it to the new target.
+enum Control {
+    Scheduled,
+    Overridden(Override),
+}
+
+impl Control {
+    /// Ends an override whose schedule target no longer matches
+    /// `schedule_target`. Returns whether it expired.
+    const fn expire(&mut self, schedule_target: u16) -> bool {
+        let expired = matches!(
+            self,
+            Self::Overridden(manual) if manual.schedule_target != schedule_target
+        );
+        if expired {
+            *self = Self::Scheduled;
+        }
+        expired
+    }
+
+    /// Advances `transition` to `now`: towards the override if there is one,
+    /// otherwise along the schedule's transition window or towards its target.
+    fn drive(
+        &self,
+        transition: &mut transition::Transition,
+        scheduler: &scheduler::Schedule,
+        now: TickTime,
+        target_temp: u16,
+    ) {
+        if let Self::Overridden(manual) = self {
+            transition.align_with_schedule(
+                manual.start_temp,
+                manual.target,
+                now.monotonic.saturating_duration_since(manual.started),
+                manual.duration,
+                now.monotonic,
+            );
+        } else if let Some(window) = scheduler.transition_window_at(now.wall) {
+            let elapsed = now.wall.signed_duration_since(window.start);
+            let elapsed = elapsed.to_std().unwrap_or_default();
+            transition.align_with_schedule(
+                window.start_temp,
+                window.target_temp,
+                elapsed,
+                window.duration.to_std().unwrap_or_default(),
+                now.monotonic,
+            );
+        } else {
+            transition.update(target_temp, now.monotonic);
+        }
+    }
+}
+
+/// The time a daemon tick runs at: the wall clock drives the schedule, the
+/// monotonic clock ramps and intervals. Tests step both by hand.
+#[derive(Debug, Clone, Copy)]
+struct TickTime {
+    wall: chrono::DateTime<chrono::Local>,
+    monotonic: std::time::Instant,
+}
+
+impl TickTime {
+    fn now() -> Self {
+        Self {
+            wall: chrono::Local::now(),
+            monotonic: std::time::Instant::now(),
+        }
+    }
+}
+
+/// How long each step of a daemon tick took, for `--timings`. Steps that
+/// didn't run this tick are left out.
+#[derive(Debug, Default)]
+struct TickTimings {
+    solar: Option<Duration>,
+    transition: Option<Duration>,
+    set: Option<Duration>,
+    status: Option<Duration>,
+}
+
+impl TickTimings {
+    fn render(&self) -> String {
+        [
+            ("solar", self.solar),
+            ("transition", self.transition),
+            ("set", self.set),
+            ("status", self.status),
+        ]
+        .iter()
+        .filter_map(|(name, took)| {
+            took.map(|took| format!("{name}={:.1}ms", took.as_secs_f64() * 1000.0))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+/// Everything one daemon tick computed, logged as JSON with `--trace`.
+#[derive(Debug, serde::Serialize)]
+struct TickTrace {
+    now: String,
+    phase: &'static str,
+    window: Option<TraceWindow>,
+    start_temp: u16,
+    target_temp: u16,
+    current_temp: u16,
+    progress: f64,
+    sleep_seconds: f64,
+    set_issued: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct TraceWindow {
+    start: String,
+    duration_seconds: i64,
+    start_temp: u16,
+    target_temp: u16,
+}
+
+impl TickTrace {
+    fn new(
+        now: chrono::DateTime<chrono::Local>,
+        phase: scheduler::Phase,
+        window: Option<&scheduler::TransitionWindow>,
+        transition: &transition::Transition,
+        current_temp: u16,
+        sleep: Duration,
+        set_issued: bool,
+    ) -> Self {
+        let timestamp = |at: chrono::DateTime<chrono::Local>| {
+            at.to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+        };
+        Self {
+            now: timestamp(now),
+            phase: phase.as_str(),
+            window: window.map(|window| TraceWindow {
+                start: timestamp(window.start),
+                duration_seconds: window.duration.num_seconds(),
+                start_temp: window.start_temp,
+                target_temp: window.target_temp,
+            }),
+            start_temp: transition.transition_start_temp(),
+            target_temp: transition.target_temperature(),
+            current_temp,
+            progress: transition.progress(),
+            sleep_seconds: sleep.as_secs_f64(),
+            set_issued,
+        }
+    }
+}
+
+/// Runs `f`, recording how long it took in `slot` when `enabled`. Disabled
+/// timing doesn't read the clock at all.
+fn timed<T>(enabled: bool, slot: &mut Option<Duration>, f: impl FnOnce() -> T) -> T {
+    if !enabled {
+        return f();
+    }
+    let start = std::time::Instant::now();
+    let result = f();
+    *slot = Some(start.elapsed());
+    result
+}
+
+/// Time left of the startup grace period at `now`, if it hasn't ended.
+fn startup_grace_remaining(
+    started: std::time::Instant,
+    now: std::time::Instant,
+    grace: Duration,
+) -> Option<Duration> {
+    let remaining = grace.saturating_sub(now.saturating_duration_since(started));
+    (!remaining.is_zero()).then_some(remaining)
+}
+
+/// Set from the SIGHUP handler; the daemon loop reloads the config when it sees it.
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_reload(_signal: libc::c_int) {
+    RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Set on SIGINT or SIGTERM; the daemon loop saves state and exits when it
+/// sees it.
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_shutdown(_signal: libc::c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Whether switching from `old` to `new` affects the temperature curve, so
+/// the in-flight transition has to restart. Daemon, hook and cycle settings
+/// (status path, tick/status intervals, ...) are picked up without one.
+fn reload_restarts_transition(old: &config::Config, new: &config::Config) -> bool {
+    old.mode != new.mode
+        || old.location != new.location
+        || old.auto != new.auto
+        || old.schedule != new.schedule
+        || old.transition != new.transition
+        || old.temperature != new.temperature
+        || old.keyframes != new.keyframes
+}
+
+/// Applies a reloaded config to `transition`, restarting it from its current
+/// temperature only when the change affects the curve.
+fn reload_transition(
+    transition: &mut transition::Transition,
+    old: &config::Config,
+    new: &config::Config,
+) {
+    if reload_restarts_transition(old, new) {
+        log::info!("Config reloaded, restarting transition");
+        *transition =
+            transition::Transition::new_with_temp(new.clone(), transition.current_temperature());
+    } else {
+        log::info!("Config reloaded, keeping the current transition");
+    }
+}
+
+/// What the daemon carries from one tick to the next.
+struct Daemon {
+    config: config::Config,
+    options: DaemonOptions,
+    scheduler: scheduler::Schedule,
+    zones: Vec<zones::Zone>,
+    transition: transition::Transition,
+    files: DaemonFiles,
+    control: Control,
+    started: std::time::Instant,
+    tick_interval: Duration,
+    status_update_interval: Duration,
+    last_status_write: Option<std::time::Instant>,
+    last_status: Option<Status>,
+    status_write_failed: bool,
+    config_generation: u64,
+    config_hash: u64,
+    last_set_temperature: Option<u16>,
+    last_set_gamma: Option<u8>,
+    first_set: bool,
+    set_failed: bool,
+    /// Why the daemon has to stop, set when the first temperature set fails
+    /// under `on_backend_failure = "exit"`.
+    backend_failure: Option<String>,
+    last_hook_phase: Option<&'static str>,
+    announced_hold: Option<chrono::DateTime<chrono::Local>>,
+    backlight: backlight::BacklightBackend,
+}
+
+impl Daemon {
+    /// Sets up the schedule and resumes a saved transition that is recent
+    /// enough, otherwise starts at the temperature scheduled for `time`.
+    fn new(
+        config: config::Config,
+        config_path: Option<&str>,
+        options: DaemonOptions,
+        time: TickTime,
+    ) -> Result<Self, String> {
+        let scheduler = scheduler::Schedule::new(config.clone())
+            .map_err(|e| format!("Invalid schedule configuration: {e}"))?;
+        let zones = zones::from_config(&config)
+            .map_err(|e| format!("Invalid schedule configuration: {e}"))?;
+        let files = DaemonFiles::new(&config.daemon, options.persistence);
+
+        let target_temp = scheduler.target_temperature_at(time.wall);
+        let initial_temp = files.load_state().map_or(target_temp, |saved_state| {
+            let direction = saved_state.direction(&config.temperature.on(time.wall.weekday()));
+            let duration = transition_duration_seconds(&config, direction);
+            let max_age = duration * 2;
+            if saved_state.age_seconds() < max_age {
+                log::info!("Resuming {direction:?} transition from saved state");
+                state::calculate_temperature_from_state(&saved_state, duration, &config.transition)
+            } else {
+                log::info!("Saved state too old, calculating fresh");
+                target_temp
+            }
+        });
+
+        Ok(Self {
+            transition: transition::Transition::new_with_temp(config.clone(), initial_temp),
+            started: time.monotonic,
+            tick_interval: Duration::from_secs(config.daemon.tick_interval_seconds),
+            status_update_interval: status_write_interval(&config.daemon),
+            last_status_write: None,
+            last_status: None,
+            status_write_failed: false,
+            config_generation: 1,
+            config_hash: config::file_hash(config_path),
+            last_set_temperature: None,
+            last_set_gamma: None,
+            first_set: true,
+            set_failed: false,
+            backend_failure: None,
+            last_hook_phase: None,
+            announced_hold: None,
+            backlight: backlight::BacklightBackend::default(),
+            control: Control::Scheduled,
+            config,
+            options,
+            scheduler,
+            zones,
+            files,
+        })
+    }
+
+    /// Switches to a reloaded `config`, restarting the transition only when
+    /// the curve changed. On error the current config stays in place.
+    fn reload(&mut self, config: config::Config, config_path: Option<&str>) -> Result<(), String> {
+        let scheduler = scheduler::Schedule::new(config.clone())
+            .map_err(|e| format!("invalid schedule: {e}"))?;
+        let zones = zones::from_config(&config)?;
+        hyprctl::set_timeout(Duration::from_secs(config.daemon.hyprctl_timeout_seconds));
+        hyprctl::set_gamma_floor(config.brightness.floor);
+        self.config_hash = config::file_hash(config_path);
+        reload_transition(&mut self.transition, &self.config, &config);
+        self.scheduler = scheduler;
+        self.zones = zones;
+        self.files = DaemonFiles::new(&config.daemon, self.options.persistence);
+        self.tick_interval = Duration::from_secs(config.daemon.tick_interval_seconds);
+        self.status_update_interval = status_write_interval(&config.daemon);
+        self.status_write_failed = false;
+        self.config_generation += 1;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Hands the temperature to a manual override ramping to `target` over
+    /// `duration`, which lasts until the schedule's target moves on from
+    /// where it is at `time`.
+    fn override_with(&mut self, target: u16, duration: Duration, time: TickTime) {
+        self.control = Control::Overridden(Override {
+            start_temp: self.transition.current_temperature(),
+            target,
+            started: time.monotonic,
+            duration,
+            schedule_target: self.scheduler.target_temperature_at(time.wall),
+        });
+    }
+
+    /// Saves the transition state and restores the exit temperature, unless
+    /// this is a dry run.
+    fn shut_down(&self, sink: &impl hyprctl::TemperatureSink) {
+        if self.options.dry_run {
+            return;
+        }
+        let now = transition::current_unix_timestamp();
+        let start = self.transition.transition_start_timestamp();
+        let state = state::State {
+            transition_start_temp: self.transition.transition_start_temp(),
+            transition_start_timestamp: start,
+            elapsed_seconds: now.saturating_sub(start),
+            target_temp: self.transition.target_temperature(),
+            direction: Some(state::Direction::towards(
+                self.transition.target_temperature(),
+                &self.config.temperature.on(chrono::Local::now().weekday()),
+            )),
+        };
+        let _ = self.files.save_state(&state);
+
+        if let Some(kelvin) = exit_temperature(&self.config, chrono::Local::now().weekday()) {
+            match sink.set_temperature(kelvin) {
+                Ok(()) => log::info!("Restored {kelvin}K on exit"),
+                Err(e) => log::error!("Error restoring temperature on exit: {e}"),
+            }
+        }
+    }
+
+    /// Writes `status` and keeps it for `refresh_status`. A failing write is
+    /// only logged once until it succeeds again.
+    fn write_status(&mut self, status: Status) {
+        match self
+            .files
+            .write_status(&status.render(self.config.daemon.status_format))
+        {
+            Ok(()) => self.status_write_failed = false,
+            Err(e) => {
+                if !self.status_write_failed {
+                    let path = &self.config.daemon.status_file;
+                    log::warn!("Failed to write status file {path}: {e}");
+                }
+                self.status_write_failed = true;
+            }
+        }
+        self.last_status = Some(status);
+    }
+
+    /// Rewrites the last status with a fresh `updated_at` once a status write
+    /// is due, so a daemon sleeping through a day or night hold doesn't read
+    /// as stale.
+    fn refresh_status(&mut self, now: std::time::Instant) {
+        if !status_write_due(self.last_status_write, now, self.status_update_interval) {
+            return;
+        }
+        let Some(mut status) = self.last_status.take() else {
+            return;
+        };
+        self.last_status_write = Some(now);
+        status.updated_at = transition::current_unix_timestamp();
+        if let Some((eta, target_time)) = status.eta.as_mut() {
+            *eta = target_time.saturating_sub(status.updated_at);
+        }
+        self.write_status(status);
+    }
+
+    /// Whether a pause skips this pass of the loop. A fully paused daemon
+    /// still rewrites its last status, so it doesn't read as stale.
+    fn skip_paused(&mut self, paused: bool, now: std::time::Instant) -> bool {
+        if !pause_skips_tick(paused, self.config.daemon.pause_mode) {
+            return false;
+        }
+        self.refresh_status(now);
+        true
+    }
+
+    /// One pass of the daemon loop at `now`: moves the transition along,
+    /// sends the result through `sink` and writes the status file. Returns
+    /// how long to sleep before the next pass.
+    #[allow(clippy::too_many_lines, reason = "a tick is inherently sequential")]
+    fn tick(
+        &mut self,
+        sink: &impl hyprctl::TemperatureSink,
+        time: TickTime,
+        paused: bool,
+        unlocked: bool,
+    ) -> Duration {
+        let DaemonOptions {
+            dry_run,
+            quiet,
+            show_timings,
+            trace,
+            ..
+        } = self.options;
+        let now = time.wall;
+        let mut timings = TickTimings::default();
+        let (phase, target_temp) = timed(show_timings, &mut timings.solar, || {
+            (
+                self.scheduler.current_phase_at(now),
+                self.scheduler.target_temperature_at(now),
+            )
+        });
+
+        if unlocked {
+            log::info!("Session unlocked, applying {target_temp}K");
+            self.transition.snap_to(target_temp);
+        }
+
+        if self.control.expire(target_temp) {
+            log::info!("Schedule moved on, ending the manual override");
+        }
+
+        let grace_remaining = startup_grace_remaining(
+            self.started,
+            time.monotonic,
+            Duration::from_secs(self.config.daemon.startup_grace_seconds),
+        );
+        timed(show_timings, &mut timings.transition, || {
+            if grace_remaining.is_some() {
+                log::debug!(
+                    "Startup grace, holding {}K",
+                    self.transition.current_temperature()
+                );
+            } else {
+                self.control
+                    .drive(&mut self.transition, &self.scheduler, time, target_temp);
+            }
+        });
+
+        let target = self.transition.target_temperature();
+        if let Some(hook_phase) = hooks::hook_phase(phase) {
+            if self.last_hook_phase.is_some_and(|last| last != hook_phase) && !dry_run {
+                if let Some(command) = hooks::command_for(&self.config.hooks, hook_phase) {
+                    let command = command.to_string();
+                    thread::spawn(move || match hooks::fire(&command, hook_phase) {
+                        Ok(status) => log::info!("{hook_phase} hook exited with {status}"),
+                        Err(e) => log::error!("Failed to run {hook_phase} hook: {e}"),
+                    });
+                }
+            }
+            self.last_hook_phase = Some(hook_phase);
+        }
+
+        let temp = displayed_temperature(&self.transition, &self.config);
+        let progress = self.transition.progress();
+
+        if !quiet {
+            log::info!("Phase: {phase:?}, Temp: {temp}, Target: {target}, Progress: {progress:.2}");
+        }
+
+        let zone_readings: Vec<zones::Reading> =
+            self.zones.iter().map(|zone| zone.reading(now)).collect();
+
+        let mut set_issued = false;
+        if !dry_run {
+            // Every pause mode stops hyprctl calls; they differ only in ticking
+            let skip_sets = paused;
+            if !skip_sets
+                && should_set_temperature(
+                    self.config.daemon.optimize_updates,
+                    self.config.daemon.min_update_delta,
+                    self.last_set_temperature,
+                    temp,
+                    target,
+                )
+            {
+                set_issued = true;
+                let (attempts, delay): (u32, fn(u32) -> Duration) = if self.first_set {
+                    (self.config.daemon.initial_set_attempts, |_| {
+                        INITIAL_SET_RETRY_DELAY
+                    })
+                } else {
+                    (SET_RETRY_ATTEMPTS, set_retry_delay)
+                };
+                let result = timed(show_timings, &mut timings.set, || {
+                    send_temperature(sink, &mut self.last_set_temperature, temp, attempts, delay)
+                });
+                let first_set = std::mem::replace(&mut self.first_set, false);
+                self.set_failed = result.is_err();
+                if let Err(e) = result {
+                    log::error!("Error setting temperature: {e}");
+                    let policy = self.config.daemon.on_backend_failure;
+                    if first_set && policy == config::BackendFailure::Exit {
+                        self.backend_failure = Some(format!(
+                            "Giving up after the first temperature set failed: {e}"
+                        ));
+                    }
+                } else {
+                    log::info!("Set temperature to {temp}");
+                    let line = format!(
+                        "{},{temp},{}",
+                        now.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+                        phase.as_str()
+                    );
+                    let max_lines = self.config.daemon.history_max_lines;
+                    if let Err(e) = self.files.append_history(&line, max_lines) {
+                        log::warn!("Failed to append to history file: {e}");
+                    }
+                }
+            }
+
+            if !skip_sets {
+                for (zone, reading) in self.zones.iter_mut().zip(&zone_readings) {
+                    zone.apply(reading.temp, self.config.daemon.optimize_updates);
+                }
+            }
+
+            let temperature = self.config.temperature.on(now.weekday());
+            if let Some(gamma) = self.config.gamma.as_ref() {
+                let level =
+                    transition::follow_temperature(gamma.day, gamma.night, temp, &temperature);
+                if !skip_sets
+                    && should_set_gamma(
+                        self.config.daemon.optimize_updates,
+                        self.last_set_gamma,
+                        level,
+                    )
+                {
+                    match sink.set_gamma(level) {
+                        Ok(()) => self.last_set_gamma = Some(level),
+                        Err(e) => log::error!("Error setting gamma: {e}"),
+                    }
+                }
+            }
+
+            if self.config.backlight.enabled && !skip_sets {
+                let percent = backlight::percent_at(&self.config.backlight, temp, &temperature);
+                self.backlight
+                    .apply(percent, |percent| sink.set_backlight(percent));
+            }
+
+            if status_write_due(
+                self.last_status_write,
+                time.monotonic,
+                self.status_update_interval,
+            ) {
+                self.last_status_write = Some(time.monotonic);
+                let updated_at = transition::current_unix_timestamp();
+                let zones = self.zones.iter().map(|zone| zone.name.clone());
+                let status = Status {
+                    config: Some((self.config_generation, self.config_hash)),
+                    zones: zones.zip(zone_readings).collect(),
+                    ..Status::of_transition(&self.transition, temp, phase, updated_at)
+                };
+                timed(show_timings, &mut timings.status, || {
+                    self.write_status(status);
+                });
+            }
+        }
+
+        if show_timings {
+            log::debug!("{}", timings.render());
+        }
+
+        let multiplier = self.config.power.battery_tick_multiplier;
+        let tick = power::battery_tick(self.tick_interval, multiplier);
+        // The daemon only sleeps through a hold if every zone is holding too
+        let next_change = self
+            .zones
+            .iter()
+            .map(|zone| zone.next_phase_change(now))
+            .fold(self.scheduler.next_phase_change(now), std::cmp::min);
+        let until_change = (next_change - now).to_std().unwrap_or(tick);
+        let holding =
+            !phase.is_transitioning() && !self.zones.iter().any(|zone| zone.is_transitioning(now));
+        let (next_transition, sleep_duration) = if holding {
+            (Some(next_change), until_change)
+        } else {
+            (None, tick.min(until_change))
+        };
+        let sleep_duration = grace_remaining.map_or(sleep_duration, |r| sleep_duration.min(r));
+        let sleep_duration = if matches!(self.control, Control::Overridden(_)) || self.set_failed {
+            sleep_duration.min(tick)
+        } else {
+            sleep_duration
+        };
+        let sleep_duration = if self.config.daemon.align_to_minute {
+            align_to_minute(now, sleep_duration)
+        } else {
+            sleep_duration
+        };
+
+        match next_transition.filter(|_| sleep_duration > tick) {
+            Some(next) if self.announced_hold != Some(next) => {
+                if !quiet {
+                    log::info!("{}", hold_message(temp, next, sleep_duration));
+                }
+                self.announced_hold = Some(next);
+            }
+            Some(_) => {}
+            None => self.announced_hold = None,
+        }
+
+        if trace {
+            let window = self.scheduler.transition_window_at(now);
+            let record = TickTrace::new(
+                now,
+                phase,
+                window.as_ref(),
+                &self.transition,
+                temp,
+                sleep_duration,
+                set_issued,
+            );
+            match serde_json::to_string(&record) {
+                Ok(json) => log::trace!("tick {json}"),
+                Err(e) => log::warn!("Failed to serialize tick trace: {e}"),
+            }
+        }
+
+        sleep_duration
+    }
+}
+
+/// Listens for commands on the socket next to `status_file`. Fails while
+/// another daemon answers there; any other error only leaves the daemon
+/// without a socket.
+fn listen(
+    status_file: &str,
+    commands: &std::sync::mpsc::Sender<String>,
+) -> Result<Option<socket::Server>, String> {
+    let path = socket::path_from_status(status_file);
+    match socket::bind(&path) {
+        Ok(listener) => {
+            let status_file = std::path::PathBuf::from(status_file);
+            let accepts = |line: &str| parse_control(line).is_some();
+            Ok(Some(socket::serve(
+                listener,
+                status_file,
+                accepts,
+                commands.clone(),
+            )))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => Err(format!(
+            "Another daemon is already listening on {}",
+            path.display()
+        )),
+        Err(e) => {
+            log::warn!("Not listening on {}: {e}", path.display());
+            Ok(None)
+        }
+    }
+}
+
+/// Sleeps for `sleep` between ticks, keeping the status fresh. Wakes early
+/// for a shutdown or reload, a command in the control file, or a socket
+/// command, which is returned.
+fn wait_for_next_tick(
+    daemon: &mut Daemon,
+    sleep: Duration,
+    socket_rx: &std::sync::mpsc::Receiver<String>,
+    control_file: &std::path::Path,
+) -> Option<String> {
+    let deadline = std::time::Instant::now() + sleep;
+    // The monotonic clock stops during suspend; the wall clock doesn't
+    let wake_at = chrono::Duration::from_std(sleep)
+        .ok()
+        .and_then(|sleep| chrono::Local::now().checked_add_signed(sleep));
+    loop {
+        if SHUTDOWN_REQUESTED.load(Ordering::SeqCst) || RELOAD_REQUESTED.load(Ordering::SeqCst) {
+            return None;
+        }
+        if wake_at.is_some_and(|wake_at| chrono::Local::now() >= wake_at) {
+            return None;
+        }
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+        daemon.refresh_status(std::time::Instant::now());
+        // Commands written to the control file (no socket) wake us too
+        if fs::metadata(control_file).is_ok_and(|meta| meta.len() > 0) {
+            return None;
+        }
+        match socket_rx.recv_timeout(remaining.min(daemon.tick_interval)) {
+            Ok(line) => return Some(line),
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                thread::sleep(remaining.min(daemon.tick_interval));
+            }
+        }
+    }
+}
+
+#[allow(
+    clippy::too_many_lines,
+    reason = "daemon loop is inherently sequential"
+)]
+fn run_daemon(
+    config: config::Config,
+    config_path: Option<&str>,
+    reload: impl Fn() -> Result<config::Config, String>,
+    options: DaemonOptions,
+    sink: &impl hyprctl::TemperatureSink,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !options.quiet {
+        log::info!("Starting candela daemon");
+    }
+
+    hyprctl::set_max_in_flight(config.daemon.max_hyprctl_in_flight);
+    hyprctl::set_timeout(Duration::from_secs(config.daemon.hyprctl_timeout_seconds));
+    hyprctl::set_gamma_floor(config.brightness.floor);
+    hyprctl::ensure_hyprsunset_running()?;
+
+    if !options.quiet {
+        log::info!("Mode: {:?}", config.mode);
+    }
+
+    let paused = Arc::new(AtomicBool::new(false));
+
+    let result = ctrlc::set_handler(|| SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst));
+    // SAFETY: the handlers only store to an atomic, which is async-signal-safe.
+    unsafe {
+        let handler: extern "C" fn(libc::c_int) = request_reload;
+        libc::signal(libc::SIGHUP, handler as libc::sighandler_t);
+        // ctrlc only covers SIGINT; `systemctl stop` sends SIGTERM
+        let handler: extern "C" fn(libc::c_int) = request_shutdown;
+        libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+    }
+    if let Some(path) = config_path.filter(|_| config.daemon.watch_config) {
+        watch::spawn(path.into(), || {
+            RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+        });
+    }
+
+    let mut control_file = control_file_from_status(&config.daemon.status_file);
+    // Commands queued while no daemon was running (e.g. a `set`) are stale
+    let _ = fs::remove_file(&control_file);
+    // Socket commands wake the daemon immediately; without a socket only the
+    // control file is polled. An ephemeral daemon leaves no socket behind.
+    let (socket_tx, socket_rx) = std::sync::mpsc::channel();
+    let mut server = if options.persistence == Persistence::Ephemeral {
+        None
+    } else {
+        listen(&config.daemon.status_file, &socket_tx)?
+    };
+    let mut queued_commands: Vec<String> = Vec::new();
+
+    let mut daemon = Daemon::new(config, config_path, options, TickTime::now())?;
+    let mut was_locked = false;
+
+    loop {
+        let mut lines = std::mem::take(&mut queued_commands);
+        lines.extend(socket_rx.try_iter());
+        if let Ok(content) = fs::read_to_string(&control_file) {
+            lines.extend(content.lines().map(str::to_string));
+            let _ = fs::write(&control_file, "");
+        }
+        for line in &lines {
+            match parse_control(line) {
+                Some(ControlCommand::Pause) => {
+                    paused.store(true, Ordering::SeqCst);
+                }
+                Some(ControlCommand::Resume) => {
+                    paused.store(false, Ordering::SeqCst);
+                }
+                Some(ControlCommand::ForceProgress(progress)) => {
+                    daemon.transition.force_progress(progress);
+                }
+                Some(ControlCommand::SetBy(target, deadline)) => {
+                    let duration = duration_until(deadline, chrono::Local::now().naive_local());
+                    log::info!("Transitioning to {target}K over {}s", duration.as_secs());
+                    daemon.override_with(target, duration, TickTime::now());
+                }
+                Some(ControlCommand::SetOver(target, duration)) => {
+                    log::info!("Transitioning to {target}K over {}s", duration.as_secs());
+                    daemon.override_with(target, duration, TickTime::now());
+                }
+                Some(ControlCommand::Set(target)) => {
+                    log::info!("Overriding the schedule with {target}K");
+                    daemon.override_with(target, Duration::ZERO, TickTime::now());
+                }
+                Some(ControlCommand::SetLocation(latitude, longitude)) => {
+                    match relocate(
+                        &mut daemon.config,
+                        &mut daemon.scheduler,
+                        latitude,
+                        longitude,
+                    ) {
+                        Ok(()) => log::info!("Moved to {latitude}, {longitude}"),
+                        Err(e) => log::error!("Ignoring new location: {e}"),
+                    }
+                }
+                None => {}
+            }
+        }
+
+        if RELOAD_REQUESTED.swap(false, Ordering::SeqCst) {
+            match reload().and_then(|new_config| daemon.reload(new_config, config_path)) {
+                Ok(()) => {
+                    let status_file = &daemon.config.daemon.status_file;
+                    let moved = control_file_from_status(status_file);
+                    if moved != control_file && options.persistence != Persistence::Ephemeral {
+                        // Dropping the old server removes its socket
+                        drop(server.take());
+                        server = listen(status_file, &socket_tx).unwrap_or_else(|e| {
+                            log::error!("{e}");
+                            None
+                        });
+                    }
+                    control_file = moved;
+                }
+                Err(e) => log::error!("Ignoring reloaded config: {e}"),
+            }
+        }
+
+        if SHUTDOWN_REQUESTED.load(Ordering::SeqCst) {
+            daemon.shut_down(sink);
+            break;
+        }
+
+        let is_paused = paused.load(Ordering::SeqCst);
+        if daemon.skip_paused(is_paused, std::time::Instant::now()) {
+            thread::sleep(Duration::from_millis(100));
+            continue;
+        }
+
+        let locked = session::is_locked(
+            daemon.config.daemon.lock_detection,
+            &daemon.config.daemon.lock_command,
+        );
+        let action = lock_action(was_locked, locked);
+        was_locked = locked;
+        let sleep = if action == LockAction::Skip {
+            daemon.tick_interval
+        } else {
+            daemon.tick(
+                sink,
+                TickTime::now(),
+                is_paused,
+                action == LockAction::Unlocked,
+            )
+        };
+        if let Some(e) = daemon.backend_failure.take() {
+            return Err(e.into());
+        }
+        if let Some(line) = wait_for_next_tick(&mut daemon, sleep, &socket_rx, &control_file) {
+            queued_commands.push(line);
+        }
+    }
+
+    drop(server);
+
+    if let Err(e) = result {
+        log::error!("Error setting signal handler: {e}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{align_to_minute, duration_until, hold_message, timed, TickTimings};
+    use super::{
+        apply_cli, compare_days, parse_control, parse_wait_phase, pause_skips_tick, preview_day,
+        preview_steps, read_status_field, read_status_file, relocate, render_comparison,
+        render_config, render_day, retry, send_control, set_changes_temperature, should_set_gamma,
+        should_set_temperature, write_status_file, Args, Control, DaemonFiles, Override,
+        Persistence, TickTrace,
+    };
+    use super::{config_drifted, startup_grace_remaining, status_write_due, status_write_interval};
+    use super::{
+        exit_temperature, is_stale, lock_action, render_dump, render_metrics, render_now,
+        render_phase, render_status, render_sun, reset_steps, stale_after, ControlCommand,
+        LockAction, OutputFormat, ResetStep,
+    };
+    use super::{location_cache, resolve_location, status_freshness};
+    use super::{read_status_eta, status_eta, Status, StatusExtras};
+    use super::{reload_restarts_transition, reload_transition};
+    use super::{replace_if_changed, send_temperature};
+    use super::{scheduled_transition, toggle_target};
+    use super::{set_retry_delay, SET_RETRY_ATTEMPTS};
+    use super::{Daemon, DaemonOptions, TickTime};
+    use crate::config::PauseMode;
+    use crate::hyprctl::TemperatureSink;
+    use clap::Parser;
+    use std::time::Duration;
+
+    #[test]
+    fn gamma_updates_are_optimized_independently() {
+        assert!(should_set_gamma(true, None, 80));
+        assert!(!should_set_gamma(true, Some(80), 80));
+        assert!(should_set_gamma(true, Some(80), 75));
+        assert!(should_set_gamma(false, Some(80), 80));
+    }
+
+    #[test]
+    fn optimize_skips_same_temperature() {
+        assert!(!should_set_temperature(true, 1, Some(2000), 2000, 1500));
+    }
+
+    #[test]
+    fn optimize_sets_when_temperature_changes() {
+        assert!(should_set_temperature(true, 1, Some(2000), 2100, 1500));
+    }
+
+    #[test]
+    fn always_sets_when_optimization_disabled() {
+        assert!(should_set_temperature(false, 1, Some(2000), 2000, 1500));
+        assert!(should_set_temperature(false, 500, Some(2000), 1999, 1500));
+    }
+
+    #[test]
+    fn min_update_delta_skips_small_steps_but_not_the_target() {
+        assert!(!should_set_temperature(true, 50, Some(2000), 1951, 1500));
+        assert!(should_set_temperature(true, 50, Some(2000), 1950, 1500));
+        // The final step is sent however small, and only once
+        assert!(should_set_temperature(true, 50, Some(1510), 1500, 1500));
+        assert!(!should_set_temperature(true, 50, Some(1500), 1500, 1500));
+        assert!(should_set_temperature(true, 50, None, 1990, 1500));
+    }
+
+    #[test]
+    fn min_update_delta_thins_a_transition_and_still_lands_on_target() {
+        let mut sent = Vec::new();
+        let mut last_sent = None;
+        for temp in (1500..=6500).rev() {
+            if should_set_temperature(true, 100, last_sent, temp, 1500) {
+                last_sent = Some(temp);
+                sent.push(temp);
+            }
+        }
+        assert_eq!(sent.len(), 51);
+        assert_eq!((sent.first(), sent.last()), (Some(&6500), Some(&1500)));
+    }
+
+    #[test]
+    fn status_write_creates_missing_parent_directory() {
+        let dir = std::env::temp_dir().join(format!("candela-status-{}", std::process::id()));
+        let path = dir.join("nested").join("candela.status");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        write_status_file(&path, "temp=4000\n").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "temp=4000\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn status_write_replaces_the_file_without_leaving_a_temp_file() {
+        let dir = std::env::temp_dir().join(format!("candela-atomic-{}", std::process::id()));
+        let path = dir.join("candela.status");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        write_status_file(&path, "temp=4000\nphase=day\n").unwrap();
+        write_status_file(&path, "temp=3900\n").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "temp=3900\n");
+        let entries: Vec<_> = std::fs::read_dir(&dir).unwrap().flatten().collect();
+        assert_eq!(entries.len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn status_temp_files_are_per_process() {
+        let path = std::path::Path::new("/run/user/1000/candela.status");
+        assert_eq!(
+            super::status_temp_path(path, 4242),
+            std::path::Path::new("/run/user/1000/.candela.status.4242.tmp")
+        );
+        assert_ne!(
+            super::status_temp_path(path, 1),
+            super::status_temp_path(path, 2)
+        );
+    }
+
+    #[test]
+    fn json_status_reads_like_text() {
+        let status = Status {
+            phase: "night",
+            target: 1500,
+            progress: 0.751,
+            config: Some((1, 0x00ab_12cd_34ef_5678)),
+            transition: Some(crate::transition::Snapshot {
+                start_temp: 6500,
+                target: 1500,
+                start_timestamp: 1_717_266_600,
+                progress: 0.751,
+                in_transition: true,
+            }),
+            ..Status::manual(5432, 1_717_270_200)
+        };
+        let text = status.render(crate::config::StatusFormat::Text);
+        let json = status.render(crate::config::StatusFormat::Json);
+        let fields: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(fields["temp"], 5432);
+        assert_eq!(fields["phase"], "night");
+        assert_eq!(fields["progress"], 0.75);
+        assert_eq!(fields["config_hash"], "00ab12cd34ef5678");
+        assert_eq!(fields["in_transition"], true);
+        assert_eq!(fields["updated_at"], 1_717_270_200);
+        assert!(text.starts_with(
+            "temp=5432\nphase=night\ntarget=1500\nprogress=0.75\n\
+             config_generation=1\nconfig_hash=00ab12cd34ef5678\ntransition_start_temp=6500\n"
+        ));
+        assert!(text.ends_with("in_transition=true\nupdated_at=1717270200\n"));
+        assert_eq!(
+            Status::manual(3000, 7).render(crate::config::StatusFormat::Text),
+            "temp=3000\nphase=manual\ntarget=3000\nprogress=1.00\nupdated_at=7\n"
+        );
+
+        let path = std::env::temp_dir().join(format!("candela-json-{}", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        std::fs::write(&path, &json).unwrap();
+        assert_eq!(
+            read_status_file(path_str),
+            (5432, "night".to_string(), 1500, 0.75)
+        );
+        assert_eq!(
+            read_status_field(path_str, "config_hash").as_deref(),
+            Some("00ab12cd34ef5678")
+        );
+        assert_eq!(
+            read_status_field(path_str, "updated_at").as_deref(),
+            Some("1717270200")
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn missing_or_empty_status_reads_as_unknown() {
+        let path = std::env::temp_dir().join(format!("candela-empty-{}", std::process::id()));
+        let unknown = (0, "unknown".to_string(), 0, 0.0);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(read_status_file(path.to_str().unwrap()), unknown);
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(read_status_file(path.to_str().unwrap()), unknown);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn ephemeral_daemon_creates_no_files() {
+        let dir = std::env::temp_dir().join(format!("candela-ephemeral-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut daemon = crate::config::Config::default().daemon;
+        daemon.status_file = dir.join("candela.status").to_string_lossy().into_owned();
+        daemon.state_file = dir.join("state.toml").to_string_lossy().into_owned();
+        daemon.history_file = Some(dir.join("history.csv").to_string_lossy().into_owned());
+
+        let files = DaemonFiles::new(&daemon, Persistence::Ephemeral);
+        files.write_status("temp=4000\n").unwrap();
+        files
+            .append_history("2024-01-01T00:00:00+00:00,4000,day", 10)
+            .unwrap();
+        let state = crate::state::State {
+            transition_start_temp: 6500,
+            transition_start_timestamp: 0,
+            elapsed_seconds: 0,
+            target_temp: 4000,
+            direction: None,
+        };
+        files.save_state(&state).unwrap();
+
+        assert!(files.load_state().is_none());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn no_state_daemon_neither_reads_nor_writes_state() {
+        let dir = std::env::temp_dir().join(format!("candela-no-state-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut daemon = crate::config::Config::default().daemon;
+        daemon.status_file = dir.join("candela.status").to_string_lossy().into_owned();
+        daemon.state_file = dir.join("state.toml").to_string_lossy().into_owned();
+        let saved = crate::state::State {
+            transition_start_temp: 6500,
+            transition_start_timestamp: 0,
+            elapsed_seconds: 0,
+            target_temp: 4000,
+            direction: None,
+        };
+        DaemonFiles::new(&daemon, Persistence::Full)
+            .save_state(&saved)
+            .unwrap();
+        let before = std::fs::read_to_string(&daemon.state_file).unwrap();
+
+        let files = DaemonFiles::new(&daemon, Persistence::NoState);
+        assert!(files.load_state().is_none());
+        let fresh = crate::state::State {
+            target_temp: 1500,
+            ..saved
+        };
+        files.save_state(&fresh).unwrap();
+        files.write_status("temp=4000\n").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&daemon.state_file).unwrap(), before);
+        assert!(std::path::Path::new(&daemon.status_file).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn retry_stops_at_first_success_or_after_all_attempts() {
+        let mut calls = 0;
+        let result = retry(
+            5,
+            |_| Duration::ZERO,
+            || {
+                calls += 1;
+                if calls < 3 {
+                    Err("not ready")
+                } else {
+                    Ok(calls)
+                }
+            },
+        );
+        assert_eq!(result, Ok(3));
+
+        let mut calls = 0;
+        let result: Result<(), _> = retry(
+            4,
+            |_| Duration::ZERO,
+            || {
+                calls += 1;
+                Err("not ready")
+            },
+        );
+        assert_eq!((result, calls), (Err("not ready"), 4));
+
+        let mut calls = 0;
+        let _ = retry(
+            0,
+            |_| Duration::ZERO,
+            || -> Result<(), &str> {
+                calls += 1;
+                Err("not ready")
+            },
+        );
+        assert_eq!(calls, 1);
+    }
+
+    /// Records each temperature that gets through, after failing the first
+    /// `failures` attempts.
+    #[derive(Default)]
+    struct RecordingSink {
+        sent: std::cell::RefCell<Vec<u16>>,
+        attempts: std::cell::Cell<u32>,
+        failures: std::cell::Cell<u32>,
+        gamma: std::cell::RefCell<Vec<u8>>,
+        backlight: std::cell::RefCell<Vec<u8>>,
+    }
+
+    impl TemperatureSink for RecordingSink {
+        fn set_temperature(&self, kelvin: u16) -> Result<(), Box<dyn std::error::Error>> {
+            self.attempts.set(self.attempts.get() + 1);
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err("hyprsunset is reloading".into());
+            }
+            self.sent.borrow_mut().push(kelvin);
+            Ok(())
+        }
+
+        fn set_gamma(&self, percent: u8) -> Result<(), Box<dyn std::error::Error>> {
+            self.gamma.borrow_mut().push(percent);
+            Ok(())
+        }
+
+        fn set_backlight(&self, percent: u8) -> Result<(), Box<dyn std::error::Error>> {
+            self.backlight.borrow_mut().push(percent);
+            Ok(())
+        }
+    }
+
+    fn test_daemon(config: crate::config::Config, time: TickTime) -> Daemon {
+        let options = DaemonOptions {
+            dry_run: false,
+            quiet: true,
+            show_timings: false,
+            trace: false,
+            persistence: Persistence::Ephemeral,
+        };
+        Daemon::new(config, None, options, time).unwrap()
+    }
+
+    #[test]
+    fn a_paused_tick_sends_nothing() {
+        use chrono::TimeZone;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.daemon.pause_mode = PauseMode::SetsOnly;
+        let noon = chrono::Local
+            .with_ymd_and_hms(2024, 6, 3, 12, 0, 0)
+            .unwrap();
+        let time = TickTime {
+            wall: noon,
+            monotonic: std::time::Instant::now(),
+        };
+        let mut daemon = test_daemon(config, time);
+        let sink = RecordingSink::default();
+
+        daemon.tick(&sink, time, true, false);
+        assert!(sink.sent.borrow().is_empty());
+        daemon.tick(&sink, time, false, false);
+        assert_eq!(*sink.sent.borrow(), [6500]);
+    }
+
+    #[test]
+    fn a_fixed_day_sends_each_step_once() {
+        use chrono::TimeZone;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.schedule.wakeup = "07:00".to_string();
+        config.schedule.bedtime = "22:00".to_string();
+        config.transition.duration_minutes = 60.into();
+        let (day, night) = (config.temperature.day, config.temperature.resolved_night());
+
+        let sink = RecordingSink::default();
+        let midnight = chrono::Local.with_ymd_and_hms(2024, 6, 3, 0, 0, 0).unwrap();
+        let start = std::time::Instant::now();
+        let mut daemon = test_daemon(
+            config,
+            TickTime {
+                wall: midnight,
+                monotonic: start,
+            },
+        );
+        for minute in 0..24 * 60 {
+            let elapsed = chrono::Duration::minutes(minute);
+            let time = TickTime {
+                wall: midnight + elapsed,
+                monotonic: start + elapsed.to_std().unwrap(),
+            };
+            daemon.tick(&sink, time, false, false);
+        }
+
+        let sent = sink.sent.into_inner();
+        assert_eq!((sent.first(), sent.last()), (Some(&night), Some(&night)));
+        let peak = sent.iter().position(|&temp| temp == day).unwrap();
+        assert!(
+            sent[..=peak].windows(2).all(|pair| pair[0] < pair[1]),
+            "{sent:?}"
+        );
+        assert!(
+            sent[peak..].windows(2).all(|pair| pair[0] > pair[1]),
+            "{sent:?}"
+        );
+        // One send per distinct step; a held phase sends nothing
+        assert!(sent.len() <= 2 * 60 + 1, "{sent:?}");
+    }
+
+    #[test]
+    fn gamma_and_backlight_go_through_the_sink() {
+        use chrono::TimeZone;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.gamma = Some(crate::config::Gamma {
+            day: 100,
+            night: 60,
+        });
+        config.backlight = crate::config::Backlight {
+            enabled: true,
+            day: 90,
+            night: 30,
+        };
+
+        let sink = RecordingSink::default();
+        let noon = chrono::Local
+            .with_ymd_and_hms(2024, 6, 3, 12, 0, 0)
+            .unwrap();
+        let start = std::time::Instant::now();
+        let mut daemon = test_daemon(
+            config,
+            TickTime {
+                wall: noon,
+                monotonic: start,
+            },
+        );
+        for minutes in [0, 1] {
+            let elapsed = chrono::Duration::minutes(minutes);
+            let time = TickTime {
+                wall: noon + elapsed,
+                monotonic: start,
+            };
+            daemon.tick(&sink, time, false, false);
+        }
+
+        // Sent once each, at the day level
+        assert_eq!(sink.gamma.into_inner(), [100]);
+        assert_eq!(sink.backlight.into_inner(), [90]);
+    }
+
+    #[test]
+    fn a_failed_set_is_retried_by_the_next_tick() {
+        use chrono::TimeZone;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.daemon.initial_set_attempts = 1;
+        let sink = RecordingSink::default();
+        sink.failures.set(SET_RETRY_ATTEMPTS + 1);
+        let noon = chrono::Local
+            .with_ymd_and_hms(2024, 6, 3, 12, 0, 0)
+            .unwrap();
+        let start = std::time::Instant::now();
+        let time = TickTime {
+            wall: noon,
+            monotonic: start,
+        };
+        let mut daemon = test_daemon(config, time);
+
+        // The first tick and the one after it both fail
+        for _ in 0..2 {
+            let sleep = daemon.tick(&sink, time, false, false);
+            assert_eq!(
+                (daemon.last_set_temperature, daemon.set_failed),
+                (None, true)
+            );
+            assert!(sleep <= daemon.tick_interval, "{sleep:?}");
+        }
+
+        daemon.tick(&sink, time, false, false);
+        assert_eq!(
+            (daemon.last_set_temperature, daemon.set_failed),
+            (Some(6500), false)
+        );
+        assert_eq!(sink.sent.into_inner(), [6500]);
+        assert_eq!(sink.attempts.get(), 1 + SET_RETRY_ATTEMPTS + 1);
+    }
+
+    #[test]
+    fn overrides_remember_the_schedule_target_at_the_tick_time() {
+        use chrono::TimeZone;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        let night = config.temperature.resolved_night();
+        let midnight = chrono::Local.with_ymd_and_hms(2024, 6, 3, 0, 0, 0).unwrap();
+        let time = TickTime {
+            wall: midnight,
+            monotonic: std::time::Instant::now(),
+        };
+        let mut daemon = test_daemon(config, time);
+
+        daemon.override_with(3000, Duration::ZERO, time);
+        assert!(matches!(
+            daemon.control,
+            Control::Overridden(Override { target: 3000, schedule_target, .. })
+                if schedule_target == night
+        ));
+    }
+
+    #[test]
+    fn a_failed_first_set_exits_only_when_configured_to() {
+        use chrono::TimeZone;
+
+        let noon = chrono::Local
+            .with_ymd_and_hms(2024, 6, 3, 12, 0, 0)
+            .unwrap();
+        let time = TickTime {
+            wall: noon,
+            monotonic: std::time::Instant::now(),
+        };
+        for (policy, exits) in [
+            (crate::config::BackendFailure::Continue, false),
+            (crate::config::BackendFailure::Exit, true),
+        ] {
+            let mut config = crate::config::Config::default();
+            config.mode = crate::config::Mode::Fixed;
+            config.daemon.initial_set_attempts = 2;
+            config.daemon.on_backend_failure = policy;
+            let sink = RecordingSink::default();
+            sink.failures.set(2);
+            let mut daemon = test_daemon(config, time);
+
+            daemon.tick(&sink, time, false, false);
+            // Every initial attempt is made before the policy applies
+            assert_eq!(sink.attempts.get(), 2);
+            assert_eq!(daemon.backend_failure.is_some(), exits, "{policy:?}");
         }
+    }
+
+    #[test]
+    fn status_is_refreshed_while_sleeping_through_a_hold() {
+        use chrono::TimeZone;
+
+        let dir = std::env::temp_dir().join(format!("candela-refresh-{}", std::process::id()));
+        let path = dir.join("status");
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.daemon.status_file = path.to_str().unwrap().to_string();
+        config.daemon.status_update_interval_seconds = Some(60);
+        let options = DaemonOptions {
+            dry_run: false,
+            quiet: true,
+            show_timings: false,
+            trace: false,
+            persistence: Persistence::NoState,
+        };
+        let noon = chrono::Local
+            .with_ymd_and_hms(2024, 6, 3, 12, 0, 0)
+            .unwrap();
+        let start = std::time::Instant::now();
+        let time = TickTime {
+            wall: noon,
+            monotonic: start,
+        };
+        let mut daemon = Daemon::new(config, None, options, time).unwrap();
+
+        // Noon is a day hold, so the tick sleeps for hours
+        let sleep = daemon.tick(&RecordingSink::default(), time, false, false);
+        assert!(sleep > Duration::from_secs(3600), "{sleep:?}");
+        std::fs::write(&path, "").unwrap();
+
+        daemon.refresh_status(start + Duration::from_secs(30));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        daemon.refresh_status(start + Duration::from_secs(60));
+        let status = std::fs::read_to_string(&path).unwrap();
+        assert!(status.starts_with("temp=6500\nphase=day\n"), "{status}");
+        assert!(status.contains("updated_at="), "{status}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn status_stays_fresh_while_fully_paused() {
+        use chrono::TimeZone;
+
+        let dir = std::env::temp_dir().join(format!("candela-paused-{}", std::process::id()));
+        let path = dir.join("status");
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.daemon.pause_mode = PauseMode::Full;
+        config.daemon.status_file = path.to_str().unwrap().to_string();
+        config.daemon.status_update_interval_seconds = Some(60);
+        let options = DaemonOptions {
+            dry_run: false,
+            quiet: true,
+            show_timings: false,
+            trace: false,
+            persistence: Persistence::NoState,
+        };
+        let noon = chrono::Local
+            .with_ymd_and_hms(2024, 6, 3, 12, 0, 0)
+            .unwrap();
+        let start = std::time::Instant::now();
+        let time = TickTime {
+            wall: noon,
+            monotonic: start,
+        };
+        let mut daemon = Daemon::new(config, None, options, time).unwrap();
+        daemon.tick(&RecordingSink::default(), time, false, false);
+
+        // A status written long ago reads as stale
+        std::fs::write(&path, "temp=6500\nupdated_at=1\n").unwrap();
+        assert!(status_freshness(&daemon.config.daemon).1);
+
+        assert!(!daemon.skip_paused(false, start + Duration::from_secs(60)));
+        assert!(daemon.skip_paused(true, start + Duration::from_secs(60)));
+        assert!(!status_freshness(&daemon.config.daemon).1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 
-        let sleep_duration = match phase {
-            scheduler::Phase::Day | scheduler::Phase::Night => scheduler
-                .next_transition_start(now)
-                .and_then(|next| (next - now).to_std().ok())
-                .map_or(tick_interval, |d| d.min(Duration::from_secs(3600))),
-            scheduler::Phase::TransitioningToNight | scheduler::Phase::TransitioningToDay => {
-                tick_interval
+    #[test]
+    fn zone_transitions_cut_a_hold_short() {
+        use chrono::TimeZone;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.transition.duration_minutes = 60.into();
+        let mut ambient = config.transition.clone();
+        ambient.duration_minutes = 120.into();
+        config.schedules = vec![crate::config::NamedSchedule {
+            name: "ambient".to_string(),
+            transition: Some(ambient),
+            ..crate::config::NamedSchedule::default()
+        }];
+        let at = |hour, minute| {
+            let wall = chrono::Local
+                .with_ymd_and_hms(2024, 6, 3, hour, minute, 0)
+                .unwrap();
+            TickTime {
+                wall,
+                monotonic: std::time::Instant::now(),
             }
         };
+        let sink = RecordingSink::default();
+        let mut daemon = test_daemon(config, at(12, 0));
+
+        // The ambient ramp starts at 20:00, an hour before the main one
+        let sleep = daemon.tick(&sink, at(12, 0), false, false);
+        assert_eq!(sleep, Duration::from_secs(8 * 3600));
+        // The main schedule still holds, but the zone is mid-ramp
+        let sleep = daemon.tick(&sink, at(20, 30), false, false);
+        assert!(sleep <= daemon.tick_interval, "{sleep:?}");
+    }
+
+    #[test]
+    fn failed_send_is_resent_next_tick() {
+        let sink = RecordingSink::default();
+        sink.failures.set(3);
+        let mut last_sent = Some(4000);
+
+        let result = send_temperature(&sink, &mut last_sent, 3000, 3, |_| Duration::ZERO);
+        assert!(result.is_err());
+        assert_eq!((sink.attempts.get(), last_sent), (3, None));
+
+        // Even an unchanged temperature goes out again
+        assert!(should_set_temperature(true, 1, last_sent, 3000, 3000));
+        send_temperature(&sink, &mut last_sent, 3000, 3, |_| Duration::ZERO).unwrap();
+        assert_eq!(
+            (sink.sent.into_inner(), last_sent),
+            (vec![3000], Some(3000))
+        );
+    }
+
+    #[test]
+    fn retry_backs_off_between_attempts() {
+        let waits = std::cell::RefCell::new(Vec::new());
+        let mut calls = 0;
+        let result: Result<(), _> = retry(
+            SET_RETRY_ATTEMPTS,
+            |attempt| {
+                waits.borrow_mut().push(set_retry_delay(attempt));
+                Duration::ZERO
+            },
+            || {
+                calls += 1;
+                Err("hyprsunset is reloading")
+            },
+        );
+
+        assert_eq!((result, calls), (Err("hyprsunset is reloading"), 3));
+        assert_eq!(
+            waits.into_inner(),
+            [Duration::from_millis(100), Duration::from_millis(200)]
+        );
+        assert_eq!(set_retry_delay(4), Duration::from_millis(800));
+    }
+
+    #[test]
+    fn config_output_reflects_cli_overrides() {
+        let args =
+            Args::try_parse_from(["candela", "--easing", "sine", "--tick", "9", "config"]).unwrap();
+        let mut config = crate::config::Config::default();
+
+        apply_cli(&mut config, &args);
+
+        let toml = render_config(&config, false).unwrap();
+        assert!(toml.contains("easing = \"sine\""));
+        assert!(toml.contains("tick_interval_seconds = 9"));
+        let json = render_config(&config, true).unwrap();
+        assert!(json.contains(r#""easing":"sine""#));
+    }
+
+    #[test]
+    fn preview_steps_span_day_to_night() {
+        let config = crate::config::Config::default();
+
+        let steps = preview_steps(&config);
+
+        assert_eq!(steps.first(), Some(&(0.0, 6500)));
+        assert_eq!(steps.last(), Some(&(1.0, 1500)));
+    }
+
+    #[test]
+    fn compare_shows_both_configs_side_by_side() {
+        let mut a = crate::config::Config::default();
+        a.mode = crate::config::Mode::Fixed;
+        a.transition.easing = "linear".to_string();
+        let mut b = a.clone();
+        b.transition.duration_minutes = 120.into();
+        let a = crate::scheduler::Schedule::new(a).unwrap();
+        let b = crate::scheduler::Schedule::new(b).unwrap();
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+
+        let rows = compare_days(&a, &b, date, super::COMPARE_STEP_MINUTES);
+        let at = |hour| {
+            let time = chrono::NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+            *rows.iter().find(|row| row.0 == time).unwrap()
+        };
+        assert_eq!(rows.len(), 48);
+        assert_eq!(at(12), (at(12).0, 6500, 6500));
+        assert_eq!(at(21), (at(21).0, 6500, 4000));
+        assert_eq!(at(23), (at(23).0, 1500, 1500));
+
+        let table = render_comparison(&rows, false);
+        assert!(table.starts_with("time        a       b\n00:00   1500K   1500K\n"));
+        assert!(table.contains("\n21:00   6500K   4000K  *\n"));
+        let json = render_comparison(&rows[42..43], true);
+        assert_eq!(json, r#"[{"time":"21:00","a":6500,"b":4000}]"#);
+    }
+
+    #[test]
+    fn wait_phase_accepts_day_and_night() {
+        assert_eq!(
+            parse_wait_phase("night"),
+            Ok(crate::scheduler::Phase::Night)
+        );
+        assert_eq!(parse_wait_phase("day"), Ok(crate::scheduler::Phase::Day));
+        assert!(parse_wait_phase("dusk").is_err());
+
+        let args = Args::try_parse_from(["candela", "wait", "--phase", "night"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(super::Commands::Wait {
+                phase: Some(crate::scheduler::Phase::Night)
+            })
+        ));
+        assert!(Args::try_parse_from(["candela", "wait", "--phase", "dusk"]).is_err());
+    }
+
+    #[test]
+    fn day_preview_walks_the_schedule() {
+        use crate::scheduler::Phase;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.transition.easing = "linear".to_string();
+        let schedule = crate::scheduler::Schedule::new(config).unwrap();
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+
+        let rows = preview_day(&schedule, date, 60);
+        assert_eq!(rows.len(), 24);
+        let at = |hour: usize| rows[hour].1;
+        assert_eq!(at(3), Phase::Night);
+        assert_eq!(at(12), Phase::Day);
+        assert_eq!(rows[12].2, 6500);
+        assert_eq!(rows[23].2, 1500);
+        assert_eq!(preview_day(&schedule, date, 7).len(), 206);
+
+        let table = render_day(&rows[12..13], false);
+        assert_eq!(table, "12:00  day                      6500K");
+        let json = render_day(&rows[12..13], true);
+        assert_eq!(
+            json,
+            r#"[{"time":"12:00","phase":"day","temperature":6500}]"#
+        );
+    }
+
+    #[test]
+    fn set_to_current_temperature_is_unchanged() {
+        assert!(!set_changes_temperature(Some(3000), 3000));
+    }
+
+    #[test]
+    fn set_to_new_or_unknown_temperature_is_changed() {
+        assert!(set_changes_temperature(Some(3000), 3100));
+        assert!(set_changes_temperature(None, 3000));
+    }
+
+    #[test]
+    fn cli_location_overrides_schedule() {
+        let args =
+            Args::try_parse_from(["candela", "--lat", "60.0", "--lon", "-20.5", "now"]).unwrap();
+        let mut config = crate::config::Config::default();
+
+        apply_cli(&mut config, &args);
+
+        assert!((config.location.latitude - 60.0).abs() < f64::EPSILON);
+        assert!((config.location.longitude + 20.5).abs() < f64::EPSILON);
+        let noon =
+            chrono::TimeZone::with_ymd_and_hms(&chrono::Local, 2024, 6, 1, 12, 0, 0).unwrap();
+        let overridden = crate::scheduler::Schedule::new(config).unwrap();
+        let default = crate::scheduler::Schedule::new(crate::config::Config::default()).unwrap();
+        assert_ne!(
+            overridden.next_transition_start(noon),
+            default.next_transition_start(noon)
+        );
+    }
+
+    #[test]
+    fn cli_location_rejects_out_of_range() {
+        assert!(Args::try_parse_from(["candela", "--lat", "91", "now"]).is_err());
+        assert!(Args::try_parse_from(["candela", "--lon", "-180.5", "now"]).is_err());
+    }
+
+    #[test]
+    fn toggle_flips_to_the_further_phase() {
+        let temperature = crate::config::Temperature {
+            day: 6500,
+            night: 2500,
+            ..crate::config::Temperature::default()
+        };
+        assert_eq!(toggle_target(None, &temperature), 2500);
+        assert_eq!(toggle_target(Some(6500), &temperature), 2500);
+        assert_eq!(toggle_target(Some(5000), &temperature), 2500);
+        assert_eq!(toggle_target(Some(2500), &temperature), 6500);
+        assert_eq!(toggle_target(Some(3000), &temperature), 6500);
+        // OFF reads as neutral white, which is closer to day
+        assert_eq!(toggle_target(Some(0), &temperature), 2500);
+    }
+
+    #[test]
+    fn set_duration_is_positive_and_excludes_by() {
+        let args = Args::try_parse_from(["candela", "set", "3000", "--duration", "10"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(super::Commands::Set {
+                temperature: 3000,
+                by: None,
+                duration: Some(10)
+            })
+        ));
+        assert!(Args::try_parse_from(["candela", "set", "3000", "--duration", "0"]).is_err());
+        let both = [
+            "candela",
+            "set",
+            "3000",
+            "--duration",
+            "10",
+            "--by",
+            "23:00",
+        ];
+        assert!(Args::try_parse_from(both).is_err());
+    }
+
+    #[test]
+    fn only_a_full_pause_skips_the_tick() {
+        assert!(pause_skips_tick(true, PauseMode::Full));
+        assert!(!pause_skips_tick(true, PauseMode::SetsOnly));
+        for mode in [PauseMode::Full, PauseMode::SetsOnly] {
+            assert!(!pause_skips_tick(false, mode));
+        }
+    }
+
+    #[test]
+    fn ramp_steps_follow_the_easing() {
+        let mut config = crate::config::Config::default().transition;
+        config.easing = "linear".to_string();
+        let minute = Duration::from_secs(60);
+        let step = |elapsed| super::ramp_step(&config, 6500, 2500, elapsed, minute);
+
+        assert_eq!(step(Duration::ZERO), 6500);
+        assert_eq!(step(Duration::from_secs(15)), 5500);
+        assert_eq!(step(Duration::from_secs(30)), 4500);
+        assert_eq!(step(minute), 2500);
+        // A late final step still lands on the target
+        assert_eq!(step(Duration::from_secs(75)), 2500);
+    }
+
+    #[test]
+    fn control_lines() {
+        assert_eq!(parse_control("pause"), Some(ControlCommand::Pause));
+        assert_eq!(parse_control(" resume "), Some(ControlCommand::Resume));
+        assert_eq!(
+            parse_control("progress 0.25"),
+            Some(ControlCommand::ForceProgress(Some(0.25)))
+        );
+        assert_eq!(
+            parse_control("progress release"),
+            Some(ControlCommand::ForceProgress(None))
+        );
+        assert_eq!(
+            parse_control("set 2000 by 23:00"),
+            Some(ControlCommand::SetBy(
+                2000,
+                chrono::NaiveTime::from_hms_opt(23, 0, 0).unwrap()
+            ))
+        );
+        assert_eq!(
+            parse_control("location 52.52 -13.4"),
+            Some(ControlCommand::SetLocation(52.52, -13.4))
+        );
+        assert_eq!(parse_control("set 3000"), Some(ControlCommand::Set(3000)));
+        assert_eq!(
+            parse_control("set 2500 over 90"),
+            Some(ControlCommand::SetOver(2500, Duration::from_secs(90)))
+        );
+        assert_eq!(parse_control("set 2500 over 0"), None);
+        assert_eq!(parse_control("location 95 13.4"), None);
+        assert_eq!(parse_control("location 52.52"), None);
+        assert_eq!(parse_control("set 2000 by 25:00"), None);
+        assert_eq!(parse_control("progress 1.5"), None);
+        assert_eq!(parse_control("bogus"), None);
+    }
+
+    #[test]
+    fn control_falls_back_to_the_file_without_a_socket() {
+        let status = std::env::temp_dir().join(format!("candela-fallback-{}", std::process::id()));
+        let status = status.to_str().unwrap();
+        send_control(status, "pause");
+        let control = super::control_file_from_status(status);
+        assert_eq!(std::fs::read_to_string(&control).unwrap(), "pause\n");
+        let _ = std::fs::remove_file(control);
+    }
+
+    #[test]
+    fn relocating_moves_the_schedule_but_keeps_the_temperature() {
+        use chrono::TimeZone;
+
+        // An evening that is already night on the equator but still day at 60°N
+        let mut moved = crate::config::Config::default();
+        (moved.location.latitude, moved.location.longitude) = (60.0, 10.0);
+        let (here, there) = (
+            crate::scheduler::Schedule::new(crate::config::Config::default()).unwrap(),
+            crate::scheduler::Schedule::new(moved).unwrap(),
+        );
+        let midnight = chrono::Local.with_ymd_and_hms(2024, 6, 3, 0, 0, 0).unwrap();
+        let evening = (0..48 * 6)
+            .map(|step| midnight + chrono::Duration::minutes(10 * step))
+            .find(|&at| {
+                here.current_phase_at(at) == crate::scheduler::Phase::Night
+                    && there.current_phase_at(at) == crate::scheduler::Phase::Day
+            })
+            .unwrap();
+        let time = TickTime {
+            wall: evening,
+            monotonic: std::time::Instant::now(),
+        };
+        let mut daemon = test_daemon(crate::config::Config::default(), time);
+        let sink = RecordingSink::default();
+        daemon.tick(&sink, time, false, false);
+        let night = daemon.config.temperature.resolved_night();
+        assert_eq!(
+            daemon.scheduler.current_phase_at(evening),
+            crate::scheduler::Phase::Night
+        );
+        assert_eq!(daemon.transition.current_temperature(), night);
+
+        relocate(&mut daemon.config, &mut daemon.scheduler, 60.0, 10.0).unwrap();
+
+        let location = &daemon.config.location;
+        assert_eq!((location.latitude, location.longitude), (60.0, 10.0));
+        assert_eq!(
+            daemon.scheduler.current_phase_at(evening),
+            crate::scheduler::Phase::Day
+        );
+        assert_eq!(daemon.transition.current_temperature(), night);
+
+        // Later ticks ease towards day rather than jumping there
+        let later = |minutes| TickTime {
+            wall: evening + chrono::Duration::minutes(minutes),
+            monotonic: time.monotonic + Duration::from_secs(60 * minutes.unsigned_abs()),
+        };
+        daemon.tick(&sink, later(1), false, false);
+        assert_eq!(daemon.transition.current_temperature(), night);
+        daemon.tick(&sink, later(5), false, false);
+        let temp = daemon.transition.current_temperature();
+        assert!(
+            temp > night && temp < daemon.config.temperature.day,
+            "{temp}"
+        );
+    }
+
+    #[test]
+    fn manual_override_suspends_the_transition_until_the_schedule_moves_on() {
+        use chrono::TimeZone;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.transition.easing = "linear".to_string();
+        let scheduler = crate::scheduler::Schedule::new(config.clone()).unwrap();
+        let mut transition = crate::transition::Transition::new_with_temp(config, 6500);
+        let at = |hour, minute| {
+            chrono::Local
+                .with_ymd_and_hms(2024, 6, 3, hour, minute, 0)
+                .unwrap()
+        };
+
+        let tick = |wall| TickTime {
+            wall,
+            monotonic: std::time::Instant::now(),
+        };
+
+        let mut control = Control::Scheduled;
+        control.drive(&mut transition, &scheduler, tick(at(21, 30)), 1500);
+        assert_eq!(transition.current_temperature(), 4000);
+
+        control = Control::Overridden(Override {
+            start_temp: transition.current_temperature(),
+            target: 3000,
+            started: std::time::Instant::now(),
+            duration: Duration::ZERO,
+            schedule_target: scheduler.target_temperature_at(at(21, 30)),
+        });
+        for now in [at(21, 45), at(23, 0)] {
+            assert!(!control.expire(scheduler.target_temperature_at(now)));
+            control.drive(&mut transition, &scheduler, tick(now), 1500);
+            assert_eq!(transition.current_temperature(), 3000);
+        }
+
+        let morning = at(7, 30);
+        assert!(control.expire(scheduler.target_temperature_at(morning)));
+        assert!(matches!(control, Control::Scheduled));
+        control.drive(&mut transition, &scheduler, tick(morning), 6500);
+        assert_eq!(transition.current_temperature(), 4000);
+    }
+
+    #[test]
+    fn status_path_reload_keeps_transition() {
+        let old = crate::config::Config::default();
+        let mut new = old.clone();
+        new.daemon.status_file = "/run/user/1000/candela.status".to_string();
+        new.daemon.status_update_interval = 5;
+        assert!(!reload_restarts_transition(&old, &new));
+
+        let mut transition = crate::transition::Transition::new_with_temp(old.clone(), 6500);
+        transition.align_with_schedule(
+            6500,
+            1500,
+            std::time::Duration::from_secs(1800),
+            std::time::Duration::from_secs(3600),
+            std::time::Instant::now(),
+        );
+        let before = transition.current_temperature();
+
+        reload_transition(&mut transition, &old, &new);
+
+        assert_eq!(transition.current_temperature(), before);
+        assert_eq!(transition.target_temperature(), 1500);
+    }
+
+    #[test]
+    fn curve_changes_restart_transition() {
+        let old = crate::config::Config::default();
+        let mut new = old.clone();
+        new.temperature.night = 2700;
+        assert!(reload_restarts_transition(&old, &new));
+
+        let mut transition = crate::transition::Transition::new_with_temp(old.clone(), 6500);
+        transition.align_with_schedule(
+            6500,
+            1500,
+            std::time::Duration::from_secs(1800),
+            std::time::Duration::from_secs(3600),
+            std::time::Instant::now(),
+        );
+        let before = transition.current_temperature();
+
+        reload_transition(&mut transition, &old, &new);
+
+        assert_eq!(transition.current_temperature(), before);
+        assert_eq!(transition.target_temperature(), before);
+
+        let mut new = old.clone();
+        new.transition.easing = "linear".to_string();
+        assert!(reload_restarts_transition(&old, &new));
+    }
+
+    #[test]
+    fn status_writes_follow_wall_clock_interval() {
+        let mut daemon = crate::config::Daemon {
+            status_update_interval_seconds: Some(12),
+            ..crate::config::Daemon::default()
+        };
+        let interval = status_write_interval(&daemon);
+        let start = std::time::Instant::now();
+
+        let mut last_write = None;
+        let mut written = Vec::new();
+        for second in (0..=40).step_by(5) {
+            let now = start + Duration::from_secs(second);
+            if status_write_due(last_write, now, interval) {
+                last_write = Some(now);
+                written.push(second);
+            }
+        }
+        assert_eq!(written, [0, 15, 30]);
+
+        // A long hold still writes as soon as it wakes up
+        let after_hold = start + Duration::from_secs(3600);
+        assert!(status_write_due(last_write, after_hold, interval));
+
+        daemon.status_update_interval_seconds = None;
+        daemon.status_update_interval = 3;
+        daemon.tick_interval_seconds = 5;
+        assert_eq!(status_write_interval(&daemon), Duration::from_secs(15));
+        daemon.status_update_interval = 0;
+        assert_eq!(status_write_interval(&daemon), Duration::ZERO);
+    }
 
-        let deadline = std::time::Instant::now() + sleep_duration;
-        loop {
-            if shutdown.load(Ordering::SeqCst) {
-                break;
+    #[test]
+    fn config_drift_compares_reported_hash() {
+        let hash = 0x1234_abcd_u64;
+        assert_eq!(config_drifted(Some("000000001234abcd"), hash), Some(false));
+        assert_eq!(config_drifted(Some("000000001234abce"), hash), Some(true));
+        assert_eq!(config_drifted(None, hash), None);
+        assert_eq!(config_drifted(Some("garbage"), hash), None);
+    }
+
+    #[test]
+    fn startup_grace_holds_initial_temperature() {
+        let config = crate::config::Config::default();
+        let mut transition = crate::transition::Transition::new_with_temp(config, 4000);
+        let grace = Duration::from_secs(30);
+        let started = std::time::Instant::now();
+
+        let mut last_set = None;
+        let mut sets = Vec::new();
+        for second in (0..30).step_by(5) {
+            let now = started + Duration::from_secs(second);
+            if startup_grace_remaining(started, now, grace).is_none() {
+                transition.update(1500, now);
             }
-            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
-            if remaining.is_zero() {
-                break;
+            let temp = transition.current_temperature();
+            if should_set_temperature(true, 1, last_set, temp, 1500) {
+                last_set = Some(temp);
+                sets.push(temp);
             }
-            thread::sleep(remaining.min(tick_interval));
         }
+        assert_eq!(sets, [4000]);
+
+        let after = started + grace;
+        assert_eq!(startup_grace_remaining(started, after, grace), None);
+        assert_eq!(
+            startup_grace_remaining(started, started, Duration::ZERO),
+            None
+        );
     }
 
-    if let Err(e) = result {
-        log::error!("Error setting signal handler: {e}");
+    #[test]
+    fn deadline_duration_crosses_midnight() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let now = date.and_hms_opt(23, 30, 0).unwrap();
+        let time = |h, m| chrono::NaiveTime::from_hms_opt(h, m, 0).unwrap();
+
+        assert_eq!(
+            duration_until(time(23, 45), now),
+            Duration::from_secs(15 * 60)
+        );
+        assert_eq!(
+            duration_until(time(0, 30), now),
+            Duration::from_secs(60 * 60)
+        );
+        // A deadline equal to now means the same time tomorrow
+        assert_eq!(
+            duration_until(time(23, 30), now),
+            Duration::from_secs(24 * 60 * 60)
+        );
     }
 
-    Ok(())
-}
+    #[test]
+    fn reset_pauses_the_daemon_before_restoring_day_and_full_backlight() {
+        let mut config = crate::config::Config::default();
+        config.temperature.day = 5800;
+        config.gamma = Some(crate::config::Gamma { day: 80, night: 50 });
+        config.temperature.weekdays.insert(
+            chrono::Weekday::Sat,
+            crate::config::WeekdayTemperature {
+                day: Some(6200),
+                night: None,
+            },
+        );
 
-#[cfg(test)]
-mod tests {
-    use super::should_set_temperature;
+        assert_eq!(
+            reset_steps(&config, chrono::Weekday::Mon),
+            [
+                ResetStep::PauseDaemon,
+                ResetStep::Temperature(5800),
+                // Full gamma even when the schedule dims the day
+                ResetStep::Gamma(100),
+                ResetStep::Backlight(100),
+            ]
+        );
+        assert_eq!(
+            reset_steps(&config, chrono::Weekday::Sat)[1],
+            ResetStep::Temperature(6200)
+        );
+    }
 
     #[test]
-    fn optimize_skips_same_temperature() {
-        assert!(!should_set_temperature(true, Some(2000), 2000));
+    fn dry_run_reset_describes_each_step() {
+        let steps = reset_steps(&crate::config::Config::default(), chrono::Weekday::Mon);
+        let lines: Vec<String> = steps.iter().map(|step| format!("Would {step}")).collect();
+        assert_eq!(
+            lines,
+            [
+                "Would pause the daemon",
+                "Would set the temperature to 6500K",
+                "Would set gamma to 100%",
+                "Would set the backlight to 100%",
+            ]
+        );
     }
 
     #[test]
-    fn optimize_sets_when_temperature_changes() {
-        assert!(should_set_temperature(true, Some(2000), 2100));
+    fn dump_lists_transition_and_state_fields() {
+        let snapshot = crate::transition::Snapshot {
+            start_temp: 6500,
+            target: 1500,
+            start_timestamp: 1_717_200_000,
+            progress: 0.5,
+            in_transition: true,
+        };
+        let human = render_dump(Some(&snapshot), None, false);
+        assert!(human.starts_with("transition:\n  start_temp = 6500\n  target = 1500\n"));
+        assert!(human.contains("  progress = 0.50\n  in_transition = true\nstate file:\n  (none)"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_dump(Some(&snapshot), None, true)).unwrap();
+        assert_eq!(json["transition"]["start_timestamp"], 1_717_200_000);
+        assert!(json["state"].is_null());
     }
 
     #[test]
-    fn always_sets_when_optimization_disabled() {
-        assert!(should_set_temperature(false, Some(2000), 2000));
+    fn once_lands_where_the_schedule_is() {
+        use chrono::TimeZone;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.schedule.wakeup = "07:00".to_string();
+        config.schedule.bedtime = "22:00".to_string();
+        config.transition.duration_minutes = 60.into();
+        let schedule = crate::scheduler::Schedule::new(config.clone()).unwrap();
+        let at = |hour, minute| {
+            chrono::Local
+                .with_ymd_and_hms(2024, 6, 3, hour, minute, 0)
+                .unwrap()
+        };
+
+        let noon = scheduled_transition(&config, &schedule, at(12, 0));
+        assert_eq!(noon.current_temperature(), config.temperature.day);
+
+        let evening = scheduled_transition(&config, &schedule, at(21, 30));
+        assert_eq!(
+            evening.current_temperature(),
+            schedule.temperature_at(at(21, 30))
+        );
+        assert_eq!(
+            evening.target_temperature(),
+            config.temperature.resolved_night()
+        );
+        assert!((evening.progress() - 0.5).abs() < 0.01);
+
+        let args = Args::try_parse_from(["candela", "--once", "daemon"]).unwrap();
+        assert!(args.once);
+    }
+
+    #[test]
+    fn watch_prints_only_on_change() {
+        let mut last = None;
+        let seen: Vec<bool> = [
+            (6500, "day"),
+            (6500, "day"),
+            (6400, "day"),
+            (6400, "manual"),
+        ]
+        .into_iter()
+        .map(|status| replace_if_changed(&mut last, status))
+        .collect();
+        assert_eq!(seen, [true, false, true, true]);
+        assert_eq!(last, Some((6400, "manual")));
+    }
+
+    #[test]
+    fn hold_message_names_the_next_transition() {
+        use chrono::TimeZone;
+
+        let next = chrono::Local
+            .with_ymd_and_hms(2024, 6, 1, 20, 43, 0)
+            .unwrap();
+        assert_eq!(
+            hold_message(6500, next, Duration::from_secs(3 * 3600 + 12 * 60 + 30)),
+            "Holding 6500K, next transition at 20:43, sleeping 3h12m"
+        );
+        assert_eq!(
+            hold_message(1500, next, Duration::from_secs(45 * 60)),
+            "Holding 1500K, next transition at 20:43, sleeping 0h45m"
+        );
+    }
+
+    #[test]
+    fn aligned_sleep_wakes_on_a_minute_boundary() {
+        use chrono::TimeZone;
+
+        let now = chrono::Local
+            .with_ymd_and_hms(2024, 6, 1, 12, 0, 17)
+            .unwrap()
+            + chrono::Duration::milliseconds(250);
+        for secs in [0, 5, 42, 43, 60, 3600] {
+            let sleep = Duration::from_secs(secs);
+            let aligned = align_to_minute(now, sleep);
+            let wake = now + chrono::Duration::from_std(aligned).unwrap();
+            assert_eq!(wake.timestamp_millis() % 60_000, 0, "sleep {secs}s");
+            assert!(aligned >= sleep && aligned < sleep + Duration::from_secs(60));
+        }
+
+        let on_boundary = chrono::Local
+            .with_ymd_and_hms(2024, 6, 1, 12, 1, 0)
+            .unwrap();
+        let minute = Duration::from_secs(60);
+        assert_eq!(align_to_minute(on_boundary, minute), minute);
+    }
+
+    #[test]
+    fn tick_trace_records_a_known_tick() {
+        use chrono::TimeZone;
+
+        let mut config = crate::config::Config::default();
+        config.mode = crate::config::Mode::Fixed;
+        config.transition.easing = "linear".to_string();
+        let scheduler = crate::scheduler::Schedule::new(config.clone()).unwrap();
+        let mut transition = crate::transition::Transition::new_with_temp(config, 6500);
+        let now = chrono::Local
+            .with_ymd_and_hms(2024, 6, 3, 21, 30, 0)
+            .unwrap();
+        let time = TickTime {
+            wall: now,
+            monotonic: std::time::Instant::now(),
+        };
+        Control::Scheduled.drive(&mut transition, &scheduler, time, 1500);
+        let window = scheduler.transition_window_at(now);
+
+        let trace = TickTrace::new(
+            now,
+            scheduler.current_phase_at(now),
+            window.as_ref(),
+            &transition,
+            transition.current_temperature(),
+            Duration::from_secs(5),
+            true,
+        );
+
+        assert_eq!(trace.phase, "transitioning_to_night");
+        let window = trace.window.as_ref().expect("evening window");
+        let start = chrono::Local
+            .with_ymd_and_hms(2024, 6, 3, 21, 0, 0)
+            .unwrap();
+        assert_eq!(
+            window.start,
+            start.to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+        );
+        assert_eq!(window.duration_seconds, 3600);
+        assert_eq!((window.start_temp, window.target_temp), (6500, 1500));
+        assert_eq!(
+            (trace.start_temp, trace.target_temp, trace.current_temp),
+            (6500, 1500, 4000)
+        );
+        assert!((trace.progress - 0.5).abs() < 0.01);
+        assert!((trace.sleep_seconds - 5.0).abs() < f64::EPSILON);
+        assert!(trace.set_issued);
+        let json = serde_json::to_string(&trace).unwrap();
+        assert!(
+            json.contains(r#""phase":"transitioning_to_night""#),
+            "{json}"
+        );
+        assert!(json.contains(r#""current_temp":4000"#), "{json}");
+    }
+
+    #[test]
+    fn timings_render_only_measured_steps() {
+        let timings = TickTimings {
+            solar: Some(Duration::from_micros(100)),
+            set: Some(Duration::from_millis(12)),
+            status: Some(Duration::from_micros(300)),
+            ..TickTimings::default()
+        };
+
+        assert_eq!(timings.render(), "solar=0.1ms set=12.0ms status=0.3ms");
+    }
+
+    #[test]
+    fn disabled_timing_records_nothing() {
+        let mut slot = None;
+        assert_eq!(timed(false, &mut slot, || 7), 7);
+        assert_eq!(slot, None);
+        assert_eq!(timed(true, &mut slot, || 7), 7);
+        assert!(slot.is_some());
+    }
+
+    #[test]
+    fn locked_session_skips_ticks() {
+        assert_eq!(lock_action(false, true), LockAction::Skip);
+        assert_eq!(lock_action(true, true), LockAction::Skip);
+    }
+
+    #[test]
+    fn unlock_applies_immediately() {
+        assert_eq!(lock_action(true, false), LockAction::Unlocked);
+        assert_eq!(lock_action(false, false), LockAction::Tick);
+    }
+
+    #[test]
+    fn plain_now_is_bare_number() {
+        assert_eq!(render_now(4200, false, OutputFormat::Plain), "4200");
+        assert_eq!(render_now(4200, false, OutputFormat::Human), "4200K");
+    }
+
+    #[test]
+    fn phase_uses_the_daemon_vocabulary() {
+        use crate::scheduler::Phase;
+
+        let cases = [
+            (Phase::Day, "day"),
+            (Phase::Night, "night"),
+            (Phase::TransitioningToNight, "transitioning_to_night"),
+            (Phase::TransitioningToDay, "transitioning_to_day"),
+        ];
+        for (phase, name) in cases {
+            assert_eq!(render_phase(phase, OutputFormat::Human), name);
+            assert_eq!(render_phase(phase, OutputFormat::Plain), name);
+            assert_eq!(
+                render_phase(phase, OutputFormat::Json),
+                format!(r#"{{"phase":"{name}"}}"#)
+            );
+        }
+    }
+
+    #[test]
+    fn sun_renders_times_and_day_length() {
+        let at = |t| chrono::DateTime::parse_from_rfc3339(t).unwrap();
+        let (sunrise, sunset) = (
+            at("2024-12-21T08:15:00+01:00"),
+            at("2024-12-21T16:33:00+01:00"),
+        );
+
+        assert_eq!(
+            render_sun(sunrise, sunset, OutputFormat::Human),
+            "sunrise 08:15\nsunset 16:33\nsolar noon 12:24\nday length 8h18m"
+        );
+        assert_eq!(
+            render_sun(sunrise, sunset, OutputFormat::Plain),
+            "08:15 16:33 12:24 498"
+        );
+        assert_eq!(
+            render_sun(sunrise, sunset, OutputFormat::Json),
+            r#"{"sunrise":"2024-12-21T08:15:00+01:00","sunset":"2024-12-21T16:33:00+01:00","#
+                .to_string()
+                + r#""solar_noon":"2024-12-21T12:24:00+01:00","day_length_minutes":498}"#
+        );
+    }
+
+    #[test]
+    fn metrics_are_well_formed_prometheus_lines() {
+        let metrics = render_metrics(4200, "transitioning_to_night", 1500, 0.75);
+        assert!(metrics.ends_with('\n'));
+
+        let mut declared = Vec::new();
+        for line in metrics.lines() {
+            if let Some(comment) = line.strip_prefix("# ") {
+                let mut words = comment.splitn(3, ' ');
+                let (kind, name) = (words.next().unwrap(), words.next().unwrap());
+                assert!(matches!(kind, "HELP" | "TYPE"), "{line}");
+                assert!(words.next().is_some_and(|rest| !rest.is_empty()), "{line}");
+                declared.push(name.to_string());
+                continue;
+            }
+            let (series, value) = line.rsplit_once(' ').unwrap();
+            let name = series.split('{').next().unwrap();
+            assert!(
+                declared.iter().any(|d| d == name),
+                "{name} has no HELP/TYPE"
+            );
+            assert!(
+                name.chars().all(|c| c.is_ascii_lowercase() || c == '_'),
+                "{line}"
+            );
+            assert!(value.parse::<f64>().is_ok(), "{line}");
+        }
+
+        assert!(metrics.contains("\ncandela_temperature_kelvin 4200\n"));
+        assert!(metrics.contains("\ncandela_transition_progress 0.75\n"));
+        assert!(metrics.contains("\ncandela_phase{phase=\"transitioning_to_night\"} 1\n"));
+        assert!(metrics.contains("\ncandela_phase{phase=\"day\"} 0\n"));
+    }
+
+    #[test]
+    fn plain_status_is_tab_separated() {
+        let none = StatusExtras::default();
+        assert_eq!(
+            render_status(4200, "night", 1500, 0.75, &none, OutputFormat::Plain),
+            "4200\tnight\t1500\t0.75"
+        );
+        assert_eq!(
+            render_status(4200, "night", 1500, 0.75, &none, OutputFormat::Human),
+            "temp=4200\nphase=night\ntarget=1500\nprogress=0.75"
+        );
+    }
+
+    #[test]
+    fn sigterm_handler_requests_shutdown() {
+        use std::sync::atomic::Ordering;
+
+        super::request_shutdown(libc::SIGTERM);
+        assert!(super::SHUTDOWN_REQUESTED.swap(false, Ordering::SeqCst));
+    }
+
+    #[test]
+    fn commands_see_the_location_the_schedule_runs_on() {
+        // `sun` and doctor read config.location, resolved at load like the daemon's
+        let mut config: crate::config::Config =
+            toml::from_str("[location]\ncity = \"oslo\"\n").unwrap();
+        resolve_location(&mut config, Persistence::Full, false).unwrap();
+        assert_eq!(
+            (config.location.latitude, config.location.longitude),
+            (59.91, 10.75)
+        );
+
+        let mut typo: crate::config::Config =
+            toml::from_str("[location]\ncity = \"olso\"\n").unwrap();
+        assert!(resolve_location(&mut typo, Persistence::Full, false).is_err());
+
+        let daemon = crate::config::Config::default().daemon;
+        let cache = location_cache(&daemon, Persistence::Full);
+        assert!(cache.is_some_and(|p| p.ends_with("location")));
+        assert_eq!(location_cache(&daemon, Persistence::NoState), None);
+        assert_eq!(location_cache(&daemon, Persistence::Ephemeral), None);
+    }
+
+    #[test]
+    fn exit_temperature_defaults_to_the_day_in_effect() {
+        let mut config: crate::config::Config =
+            toml::from_str("[temperature.sunday]\nday = 5000\n").unwrap();
+        assert_eq!(exit_temperature(&config, chrono::Weekday::Sun), None);
+
+        config.daemon.restore_on_exit = true;
+        assert_eq!(exit_temperature(&config, chrono::Weekday::Sun), Some(5000));
+        assert_eq!(exit_temperature(&config, chrono::Weekday::Mon), Some(6500));
+        config.daemon.restore_temperature = Some(4500);
+        assert_eq!(exit_temperature(&config, chrono::Weekday::Sun), Some(4500));
+    }
+
+    #[test]
+    fn status_older_than_three_ticks_is_stale() {
+        let mut daemon = crate::config::Config::default().daemon;
+        daemon.tick_interval_seconds = 5;
+        assert_eq!(stale_after(&daemon), 15);
+        daemon.status_update_interval_seconds = Some(60);
+        assert_eq!(stale_after(&daemon), 180);
+
+        assert!(!is_stale(Some(1000), 1015, 15));
+        assert!(is_stale(Some(1000), 1016, 15));
+        assert!(!is_stale(None, 1_000_000, 15));
+        // A clock that went backwards isn't staleness
+        assert!(!is_stale(Some(1000), 900, 15));
+    }
+
+    #[test]
+    fn stale_marker_follows_the_output_format() {
+        let extras = StatusExtras {
+            updated_at: Some(1_717_270_200),
+            stale: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_status(4200, "night", 1500, 0.75, &extras, OutputFormat::Human),
+            "temp=4200\nphase=night\ntarget=1500\nprogress=0.75\nupdated_at=1717270200\n\
+             stale=true"
+        );
+        assert_eq!(
+            render_status(4200, "night", 1500, 0.75, &extras, OutputFormat::Json),
+            r#"{"temp":4200,"phase":"night","target":1500,"progress":0.75,"#.to_string()
+                + r#""updated_at":1717270200,"stale":true}"#
+        );
+        assert_eq!(
+            render_status(4200, "night", 1500, 0.75, &extras, OutputFormat::Plain),
+            "4200\tnight\t1500\t0.75"
+        );
+        assert_eq!(
+            render_now(4200, true, OutputFormat::Json),
+            r#"{"temp":4200,"stale":true}"#
+        );
+        assert_eq!(render_now(4200, true, OutputFormat::Plain), "4200");
+        assert_eq!(render_now(4200, false, OutputFormat::Human), "4200K");
+    }
+
+    #[test]
+    fn eta_is_written_and_surfaced_in_status() {
+        let now = 1_717_270_200;
+        assert_eq!(
+            status_eta(Duration::from_millis(89_500), now),
+            (90, 1_717_270_290)
+        );
+        assert_eq!(status_eta(Duration::ZERO, now), (0, now));
+        let status = Status {
+            eta: Some((90, 1_717_270_290)),
+            ..Status::manual(5432, now)
+        };
+        assert!(status
+            .render(crate::config::StatusFormat::Text)
+            .contains("eta_seconds=90\ntarget_time=1717270290\n"));
+
+        let path = std::env::temp_dir().join(format!("candela-eta-{}", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        std::fs::write(&path, "temp=5432\neta_seconds=90\ntarget_time=1717270290\n").unwrap();
+        let eta = read_status_eta(path_str);
+        assert_eq!(eta, Some((90, 1_717_270_290)));
+        std::fs::write(&path, "temp=5432\n").unwrap();
+        assert_eq!(read_status_eta(path_str), None);
+        let _ = std::fs::remove_file(&path);
+
+        let extras = StatusExtras {
+            eta,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_status(4200, "night", 1500, 0.75, &extras, OutputFormat::Json),
+            r#"{"temp":4200,"phase":"night","target":1500,"progress":0.75,"eta_seconds":90,"#
+                .to_string()
+                + r#""target_time":1717270290}"#
+        );
+        assert!(
+            render_status(4200, "night", 1500, 0.75, &extras, OutputFormat::Human)
+                .ends_with("\neta_seconds=90\ntarget_time=1717270290")
+        );
+        assert_eq!(
+            render_status(4200, "night", 1500, 0.75, &extras, OutputFormat::Plain),
+            "4200\tnight\t1500\t0.75"
+        );
+    }
+
+    #[test]
+    fn melanopic_factor_is_appended_when_requested() {
+        let extras = StatusExtras {
+            melanopic: Some(0.455),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_status(2856, "night", 1500, 0.75, &extras, OutputFormat::Json),
+            r#"{"temp":2856,"phase":"night","target":1500,"progress":0.75,"melanopic_factor":0.46}"#
+        );
+        assert!(
+            render_status(2856, "night", 1500, 0.75, &extras, OutputFormat::Human)
+                .ends_with("\nmelanopic_factor=0.46")
+        );
+    }
+
+    #[test]
+    fn plain_conflicts_with_json() {
+        assert!(Args::try_parse_from(["candela", "--plain", "--json", "now"]).is_err());
     }
 }
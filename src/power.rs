@@ -0,0 +1,101 @@
+use std::path::Path;
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Whether the machine is running on battery, i.e. any system battery under
+/// `/sys/class/power_supply` reports `Discharging`. Device batteries (mice,
+/// headsets) don't count, and missing sysfs entries (desktops, containers)
+/// count as mains power.
+pub fn on_battery() -> bool {
+    discharging_in(Path::new(POWER_SUPPLY_DIR))
+}
+
+fn discharging_in(dir: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let read = |name| std::fs::read_to_string(entry.path().join(name)).unwrap_or_default();
+        read("type").trim() == "Battery"
+            && read("scope").trim() != "Device"
+            && read("status").trim() == "Discharging"
+    })
+}
+
+/// `tick` stretched by `multiplier` while on battery; unchanged otherwise.
+pub fn battery_tick(tick: std::time::Duration, multiplier: u32) -> std::time::Duration {
+    if multiplier > 1 && on_battery() {
+        tick.saturating_mul(multiplier)
+    } else {
+        tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(root: &Path, name: &str, attributes: &[(&str, &str)]) {
+        let dir = root.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (attribute, value) in attributes {
+            std::fs::write(dir.join(attribute), format!("{value}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn discharging_battery_is_detected() {
+        let root = std::env::temp_dir().join(format!("candela-power-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        supply(&root, "AC", &[("type", "Mains"), ("online", "1")]);
+        supply(
+            &root,
+            "BAT0",
+            &[("type", "Battery"), ("status", "Charging")],
+        );
+        assert!(!discharging_in(&root));
+        supply(
+            &root,
+            "BAT1",
+            &[("type", "Battery"), ("status", "Discharging")],
+        );
+        assert!(discharging_in(&root));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn peripheral_batteries_are_ignored() {
+        let root = std::env::temp_dir().join(format!("candela-devices-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        supply(&root, "BAT0", &[("type", "Battery"), ("status", "Full")]);
+        // A wireless mouse running on its own battery
+        let mouse = [
+            ("type", "Battery"),
+            ("scope", "Device"),
+            ("status", "Discharging"),
+        ];
+        supply(&root, "hidpp_battery_0", &mouse);
+        // A supply that reports a status but isn't a battery
+        supply(
+            &root,
+            "ucsi-source-psy-1",
+            &[("type", "USB"), ("status", "Discharging")],
+        );
+        assert!(!discharging_in(&root));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn missing_power_supply_dir_is_mains() {
+        assert!(!discharging_in(Path::new(
+            "/nonexistent/candela/power_supply"
+        )));
+    }
+
+    #[test]
+    fn multiplier_of_one_leaves_tick_alone() {
+        let tick = std::time::Duration::from_secs(5);
+        assert_eq!(battery_tick(tick, 1), tick);
+        assert_eq!(battery_tick(tick, 0), tick);
+    }
+}
@@ -1,5 +1,11 @@
-use crate::config::{Config, Mode};
-use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone};
+use crate::config::{Config, Mode, Polar, TEMPERATURE_RANGE};
+use crate::state;
+use chrono::{
+    DateTime, Datelike, Duration, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset,
+    TimeZone, Utc, Weekday,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Mutex, PoisonError};
 use sunrise::{Coordinates, SolarDay, SolarEvent};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,25 +25,153 @@ impl Phase {
             Self::TransitioningToDay => "transitioning_to_day",
         }
     }
+
+    pub const fn is_transitioning(self) -> bool {
+        matches!(self, Self::TransitioningToNight | Self::TransitioningToDay)
+    }
 }
 
 pub struct TransitionWindow {
     pub start: DateTime<Local>,
+    pub duration: Duration,
     pub start_temp: u16,
     pub target_temp: u16,
 }
 
+impl TransitionWindow {
+    /// How far through the window `now` is, from 0.0 to 1.0. An empty
+    /// window is already complete.
+    pub fn progress_at(&self, now: DateTime<Local>) -> f64 {
+        let total = self.duration.num_milliseconds();
+        if total <= 0 {
+            return 1.0;
+        }
+        #[allow(
+            clippy::cast_precision_loss,
+            reason = "millisecond counts within a day are exact in f64"
+        )]
+        let progress = (now - self.start).num_milliseconds() as f64 / total as f64;
+        progress.clamp(0.0, 1.0)
+    }
+}
+
+static CAPPED_DURATION_WARNING: std::sync::Once = std::sync::Once::new();
+
+/// Per-date and per-weekday wakeup/bedtime overrides read from `schedule.file`.
+///
+/// Each non-empty, non-comment line is `<date|weekday> <wakeup> <bedtime>`,
+/// e.g. `2024-12-25 09:00 23:30` or `sat 09:00 23:00`. Date entries take
+/// precedence over weekday entries.
+#[derive(Debug, Default)]
+struct ScheduleOverrides {
+    dates: HashMap<NaiveDate, (NaiveTime, NaiveTime)>,
+    weekdays: HashMap<Weekday, (NaiveTime, NaiveTime)>,
+}
+
+impl ScheduleOverrides {
+    fn parse(content: &str) -> Result<Self, String> {
+        let mut overrides = Self::default();
+
+        for (index, raw) in content.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [key, wakeup, bedtime] = fields[..] else {
+                return Err(format!(
+                    "Invalid schedule file line {}: expected '<date|weekday> <wakeup> <bedtime>'",
+                    index + 1
+                ));
+            };
+            let times = (
+                parse_time("wakeup", wakeup)?,
+                parse_time("bedtime", bedtime)?,
+            );
+
+            if let Ok(date) = NaiveDate::parse_from_str(key, "%Y-%m-%d") {
+                overrides.dates.insert(date, times);
+            } else if let Ok(weekday) = key.parse::<Weekday>() {
+                overrides.weekdays.insert(weekday, times);
+            } else {
+                return Err(format!(
+                    "Invalid schedule file line {}: '{key}' is not a date or weekday",
+                    index + 1
+                ));
+            }
+        }
+
+        Ok(overrides)
+    }
+
+    fn get(&self, date: NaiveDate) -> Option<(NaiveTime, NaiveTime)> {
+        self.dates
+            .get(&date)
+            .or_else(|| self.weekdays.get(&date.weekday()))
+            .copied()
+    }
+}
+
 pub struct Schedule {
     config: Config,
     wakeup_time: NaiveTime,
     bedtime_time: NaiveTime,
+    overrides: ScheduleOverrides,
+    weekend_wakeup: Option<NaiveTime>,
+    /// Offset of the evening start from solar noon, replacing sunset.
+    evening_offset: Option<Duration>,
     coordinates: Coordinates,
+    /// Manual-mode keyframes sorted by time.
+    keyframes: Vec<(NaiveTime, u16)>,
+    /// Fixed-mode `schedule.stops` sorted by time.
+    stops: Vec<(NaiveTime, u16)>,
 }
 
 impl Schedule {
     pub fn new(config: Config) -> Result<Self, String> {
         let wakeup_time = parse_time("wakeup", &config.schedule.wakeup)?;
         let bedtime_time = parse_time("bedtime", &config.schedule.bedtime)?;
+        let overrides = match config.schedule.file.as_deref() {
+            Some(file) => {
+                let path = state::expand_path(file)
+                    .ok_or_else(|| format!("Invalid schedule file path '{file}'"))?;
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read schedule file '{file}': {e}"))?;
+                ScheduleOverrides::parse(&content)?
+            }
+            None => ScheduleOverrides::default(),
+        };
+        if config.mode == Mode::Fixed {
+            let duration =
+                Duration::minutes(i64::from(config.transition.duration_minutes.longest()));
+            let days = std::iter::once((wakeup_time, bedtime_time))
+                .chain(overrides.dates.values().copied())
+                .chain(overrides.weekdays.values().copied());
+            for (wakeup, bedtime) in days {
+                if let Some(warning) = check_awake_window(wakeup, bedtime, duration)? {
+                    log::warn!("{warning}");
+                }
+            }
+        }
+        config.temperature.validate_night_offset()?;
+        if config.transition.easing == "lut" {
+            crate::transition::validate_lut(&config.transition.easing_lut)?;
+        }
+        let weekend_wakeup = config
+            .auto
+            .weekend_wakeup
+            .as_deref()
+            .map(|value| parse_time("weekend_wakeup", value))
+            .transpose()?;
+        let evening_offset = config
+            .auto
+            .evening_start
+            .as_deref()
+            .map(parse_solar_noon_offset)
+            .transpose()?;
+        let keyframes = parse_keyframes(&config)?;
+        let stops = parse_stops(&config, wakeup_time, bedtime_time)?;
         let coordinates = Coordinates::new(config.location.latitude, config.location.longitude)
             .ok_or_else(|| {
                 format!(
@@ -50,30 +184,237 @@ impl Schedule {
             config,
             wakeup_time,
             bedtime_time,
+            overrides,
+            weekend_wakeup,
+            evening_offset,
             coordinates,
+            keyframes,
+            stops,
         })
     }
 
-    fn current_phase(&self) -> Phase {
-        self.current_phase_at(Local::now())
+    /// Wakeup and bedtime for `date`, honoring any schedule file override.
+    fn times_for(&self, date: NaiveDate) -> (NaiveTime, NaiveTime) {
+        self.overrides
+            .get(date)
+            .unwrap_or((self.wakeup_time, self.bedtime_time))
+    }
+
+    fn sun_times(&self, now: DateTime<Local>) -> (DateTime<Local>, DateTime<Local>) {
+        sunrise_sunset_local(
+            &self.coordinates,
+            self.config.auto.sun_elevation_degrees,
+            now,
+        )
+    }
+
+    /// Auto-mode morning start and evening start for `now`'s date: solar
+    /// sunrise/sunset, except that `auto.weekend_wakeup` replaces sunrise on
+    /// weekends and `auto.evening_start` moves the evening relative to solar
+    /// noon.
+    fn auto_times(&self, now: DateTime<Local>) -> (DateTime<Local>, DateTime<Local>) {
+        let (sunrise, sunset) = self.sun_times(now);
+        let date = now.date_naive();
+        let is_weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+
+        let morning = self
+            .weekend_wakeup
+            .filter(|_| is_weekend)
+            .and_then(|wakeup| local_datetime(date, wakeup))
+            .unwrap_or(sunrise);
+        let evening = self
+            .evening_offset
+            .map_or(sunset, |offset| solar_noon(sunrise, sunset) + offset);
+
+        (morning, evening)
+    }
+
+    /// Configured (to-day, to-night) transition durations.
+    fn configured_durations(&self) -> (Duration, Duration) {
+        let minutes = self.config.transition.duration_minutes;
+        let duration = |minutes: u32| Duration::minutes(i64::from(minutes));
+        (duration(minutes.to_day()), duration(minutes.to_night()))
+    }
+
+    /// Effective auto-mode (morning, evening) transition durations for
+    /// `now`'s date: the configured durations, capped so the morning ramp
+    /// ends by sunset and the evening ramp ends by the next morning.
+    fn auto_durations(&self, now: DateTime<Local>) -> (Duration, Duration) {
+        let (to_day, to_night) = self.configured_durations();
+        let (morning, sunset) = self.auto_times(now);
+        let next_morning = now
+            .date_naive()
+            .succ_opt()
+            .and_then(|tomorrow| local_datetime(tomorrow, NaiveTime::from_hms_opt(12, 0, 0)?))
+            .map_or(sunset + to_night, |tomorrow_noon| {
+                self.auto_times(tomorrow_noon).0
+            });
+
+        let day_length = (sunset - morning).max(Duration::zero());
+        let night_length = (next_morning - sunset).max(Duration::zero());
+        if to_day > day_length || to_night > night_length {
+            CAPPED_DURATION_WARNING.call_once(|| {
+                log::warn!(
+                    "Transition duration of {} minutes exceeds the time between sunrise and \
+                     sunset; capping transitions to the available window",
+                    self.config.transition.duration_minutes.longest()
+                );
+            });
+        }
+
+        (to_day.min(day_length), to_night.min(night_length))
+    }
+
+    /// The polar phase (`Day` or `Night`) holding all of `now`'s date in auto
+    /// mode, or `None` if the sun rises and sets as usual.
+    fn polar_phase(&self, now: DateTime<Local>) -> Option<Phase> {
+        if self.config.mode != Mode::Auto {
+            return None;
+        }
+        polar_phase_on(
+            self.config.location.latitude,
+            self.config.auto.sun_elevation_degrees,
+            now.date_naive(),
+        )
     }
 
     pub fn current_phase_at(&self, now: DateTime<Local>) -> Phase {
         match self.config.mode {
-            Mode::Auto => self.auto_phase(now),
+            Mode::Auto => match self.polar_phase(now) {
+                Some(phase) if self.config.auto.polar == Polar::Hold => phase,
+                Some(_) => self.fixed_phase(now),
+                None => self.auto_phase(now),
+            },
+            Mode::Fixed if self.has_stops() => self.segment_phase(self.stop_segment(now)),
             Mode::Fixed => self.fixed_phase(now),
+            Mode::Manual => self.segment_phase(self.keyframe_segment(now)),
+        }
+    }
+
+    /// The keyframes before and after `now` as local datetimes. The last
+    /// keyframe of a day wraps around midnight to the first of the next.
+    fn keyframe_segment(&self, now: DateTime<Local>) -> Option<Segment> {
+        segment_around(now, |_| self.keyframes.clone())
+    }
+
+    /// Whether fixed mode runs through `schedule.stops` rather than the
+    /// plain wakeup/bedtime pair.
+    fn has_stops(&self) -> bool {
+        self.config.mode == Mode::Fixed && !self.stops.is_empty()
+    }
+
+    /// The fixed-mode day with stops as keyframes: the morning ramp from
+    /// night to day, a ramp ending at each stop's time, and the ramp ending
+    /// at bedtime, with holds in between. Equal consecutive temperatures are
+    /// holds. Ramps that don't fit start as soon as the previous one ends.
+    /// Points are laid out by their offset from wakeup, so a bedtime past
+    /// midnight wraps, then sorted by time of day.
+    fn stop_keyframes(&self, date: NaiveDate) -> Vec<(NaiveTime, u16)> {
+        let (wakeup, bedtime) = self.times_for(date);
+        let (to_day, to_night) = fixed_durations(wakeup, bedtime, self.configured_durations());
+        let temperature = self.config.temperature.on(date.weekday());
+        let window = awake_window(wakeup, bedtime);
+        let mut stops: Vec<_> = self
+            .stops
+            .iter()
+            .map(|&(time, temp)| (awake_window(wakeup, time), temp))
+            .collect();
+        stops.sort_by_key(|(offset, _)| *offset);
+
+        let mut points = vec![
+            (Duration::zero(), temperature.night),
+            (to_day, temperature.day),
+        ];
+        let mut level = temperature.day;
+        let ramp_to = |points: &mut Vec<(Duration, u16)>, level: u16, at: Duration, duration| {
+            let last = points.last().map_or_else(Duration::zero, |point| point.0);
+            let start = if at - last > duration {
+                at - duration
+            } else {
+                last
+            };
+            points.push((start, level));
+        };
+        for (at, temp) in stops {
+            let last = points.last().map_or_else(Duration::zero, |point| point.0);
+            if at <= last || at >= window {
+                continue;
+            }
+            // OFF ramps as the identity temperature, so compare on that scale
+            let warmer = crate::transition::interpolation_temperature(temp)
+                > crate::transition::interpolation_temperature(level);
+            ramp_to(
+                &mut points,
+                level,
+                at,
+                if warmer { to_day } else { to_night },
+            );
+            points.push((at, temp));
+            level = temp;
+        }
+        ramp_to(&mut points, level, window, to_night);
+        points.push((window, temperature.night));
+
+        let mut keyframes: Vec<_> = points
+            .into_iter()
+            .map(|(offset, temp)| (wakeup + offset, temp))
+            .collect();
+        // Stable, so points at the same time keep their order
+        keyframes.sort_by_key(|(time, _)| *time);
+        keyframes
+    }
+
+    fn stop_segment(&self, now: DateTime<Local>) -> Option<Segment> {
+        segment_around(now, |date| self.stop_keyframes(date))
+    }
+
+    /// Phase of a keyframe segment: transitioning while the temperature
+    /// changes, otherwise day or night by which side of the midpoint it holds.
+    fn segment_phase(&self, segment: Option<Segment>) -> Phase {
+        let Some(segment) = segment else {
+            return Phase::Day;
+        };
+        let temperature = &self.config.temperature;
+        let midpoint = temperature.day.midpoint(temperature.resolved_night());
+        match segment.end_temp.cmp(&segment.start_temp) {
+            std::cmp::Ordering::Greater => Phase::TransitioningToDay,
+            std::cmp::Ordering::Less => Phase::TransitioningToNight,
+            std::cmp::Ordering::Equal if segment.start_temp >= midpoint => Phase::Day,
+            std::cmp::Ordering::Equal => Phase::Night,
         }
     }
 
+    /// Whether `auto.min_night_minutes` skips the night nearest `now` (last
+    /// night before solar noon, tonight after it).
+    fn skips_night(&self, now: DateTime<Local>) -> bool {
+        let minimum = Duration::minutes(i64::from(self.config.auto.min_night_minutes));
+        if minimum.is_zero() {
+            return false;
+        }
+        let (morning, evening) = self.auto_times(now);
+        let noon_of = |date: NaiveDate| local_datetime(date, NaiveTime::from_hms_opt(12, 0, 0)?);
+        let night = if now < solar_noon(morning, evening) {
+            let yesterday = now.date_naive().pred_opt().and_then(noon_of);
+            yesterday.map(|yesterday| morning - self.auto_times(yesterday).1)
+        } else {
+            let tomorrow = now.date_naive().succ_opt().and_then(noon_of);
+            tomorrow.map(|tomorrow| self.auto_times(tomorrow).0 - evening)
+        };
+        night.is_some_and(|night| night < minimum)
+    }
+
     fn auto_phase(&self, now: DateTime<Local>) -> Phase {
-        let (sunrise, sunset) = sunrise_sunset_local(&self.coordinates, now);
-        let duration = Duration::minutes(i64::from(self.config.transition.duration_minutes));
+        if self.skips_night(now) {
+            return Phase::Day;
+        }
+        let (sunrise, sunset) = self.auto_times(now);
+        let (morning_duration, evening_duration) = self.auto_durations(now);
 
-        if now >= sunset + duration {
+        if now >= sunset + evening_duration {
             Phase::Night
         } else if now >= sunset {
             Phase::TransitioningToNight
-        } else if now >= sunrise + duration {
+        } else if now >= sunrise + morning_duration {
             Phase::Day
         } else if now >= sunrise {
             Phase::TransitioningToDay
@@ -83,63 +424,124 @@ impl Schedule {
     }
 
     fn fixed_phase(&self, now: DateTime<Local>) -> Phase {
-        let now_time = now.time();
-
-        let transition_duration = Duration::minutes(i64::from(self.config.transition.duration_minutes));
-        let transition_start = self.bedtime_time - transition_duration;
-        let transition_end = self.wakeup_time + transition_duration;
-
-        if now_time >= self.wakeup_time && now_time < transition_end {
+        let date = now.date_naive();
+        let (wakeup_time, bedtime_time) = self.times_for(date);
+
+        let (to_day, to_night) =
+            fixed_durations(wakeup_time, bedtime_time, self.configured_durations());
+        // Compare instants rather than wall-clock times so a DST change
+        // agrees with `fixed_transition_window`
+        let (Some(wakeup), Some(bedtime)) = (
+            local_datetime(date, wakeup_time),
+            local_datetime(date, bedtime_time),
+        ) else {
+            return Phase::Night;
+        };
+        let transition_start = bedtime - to_night;
+        let transition_end = wakeup + to_day;
+
+        if now >= wakeup && now < transition_end {
             Phase::TransitioningToDay
-        } else if now_time >= transition_end && now_time < transition_start {
+        } else if now >= transition_end && now < transition_start {
             Phase::Day
-        } else if now_time >= transition_start && now_time < self.bedtime_time {
+        } else if now >= transition_start && now < bedtime {
             Phase::TransitioningToNight
         } else {
             Phase::Night
         }
     }
 
-    pub fn target_temperature(&self) -> u16 {
-        let phase = self.current_phase();
-        match phase {
-            Phase::Day | Phase::TransitioningToDay => self.config.temperature.day,
-            Phase::Night | Phase::TransitioningToNight => self.config.temperature.night,
+    /// Target for the phase at `now`, honouring weekday temperature overrides.
+    pub fn target_temperature_at(&self, now: DateTime<Local>) -> u16 {
+        if self.config.mode == Mode::Manual {
+            return self
+                .keyframe_segment(now)
+                .map_or(self.config.temperature.day, |segment| segment.end_temp);
+        }
+        if self.has_stops() {
+            return self
+                .stop_segment(now)
+                .map_or(self.config.temperature.day, |segment| segment.end_temp);
+        }
+        let temperature = self.config.temperature.on(now.weekday());
+        match self.current_phase_at(now) {
+            Phase::Day | Phase::TransitioningToDay => temperature.day,
+            Phase::Night | Phase::TransitioningToNight => temperature.night,
         }
     }
 
+    /// Temperature an undisturbed daemon shows at `now`: the target, or the
+    /// eased point reached so far through the transition window at `now`.
+    pub fn temperature_at(&self, now: DateTime<Local>) -> u16 {
+        let Some(window) = self.transition_window_at(now) else {
+            return self.target_temperature_at(now);
+        };
+        crate::transition::temperature_at(
+            window.start_temp,
+            window.target_temp,
+            window.progress_at(now),
+            &self.config.transition,
+        )
+    }
+
+    /// Progress through the transition window at `now`; 1.0 outside one.
+    pub fn progress_at(&self, now: DateTime<Local>) -> f64 {
+        self.transition_window_at(now)
+            .map_or(1.0, |window| window.progress_at(now))
+    }
+
     pub fn transition_window_at(&self, now: DateTime<Local>) -> Option<TransitionWindow> {
-        let duration = Duration::minutes(i64::from(self.config.transition.duration_minutes));
-        if duration.is_zero() {
-            return None;
-        }
+        let durations = self.configured_durations();
 
         match self.config.mode {
-            Mode::Auto => self.auto_transition_window(now, duration),
-            Mode::Fixed => self.fixed_transition_window(now, duration),
+            Mode::Manual => self.keyframe_segment(now).map(|segment| TransitionWindow {
+                start: segment.start,
+                duration: segment.end - segment.start,
+                start_temp: segment.start_temp,
+                target_temp: segment.end_temp,
+            }),
+            Mode::Auto | Mode::Fixed if durations.0.is_zero() && durations.1.is_zero() => None,
+            Mode::Fixed if self.has_stops() => self
+                .stop_segment(now)
+                .filter(|segment| segment.start_temp != segment.end_temp)
+                .map(|segment| TransitionWindow {
+                    start: segment.start,
+                    duration: segment.end - segment.start,
+                    start_temp: segment.start_temp,
+                    target_temp: segment.end_temp,
+                }),
+            Mode::Auto => match self.polar_phase(now) {
+                Some(_) if self.config.auto.polar == Polar::Hold => None,
+                Some(_) => self.fixed_transition_window(now, durations),
+                None => self.auto_transition_window(now),
+            },
+            Mode::Fixed => self.fixed_transition_window(now, durations),
         }
     }
 
-    fn auto_transition_window(
-        &self,
-        now: DateTime<Local>,
-        duration: Duration,
-    ) -> Option<TransitionWindow> {
-        let (sunrise, sunset) = sunrise_sunset_local(&self.coordinates, now);
+    fn auto_transition_window(&self, now: DateTime<Local>) -> Option<TransitionWindow> {
+        if self.skips_night(now) {
+            return None;
+        }
+        let (sunrise, sunset) = self.auto_times(now);
+        let temperature = self.config.temperature.on(now.weekday());
+        let (morning_duration, evening_duration) = self.auto_durations(now);
 
-        if now >= sunset && now < sunset + duration {
+        if now >= sunset && now < sunset + evening_duration {
             return Some(TransitionWindow {
                 start: sunset,
-                start_temp: self.config.temperature.day,
-                target_temp: self.config.temperature.night,
+                duration: evening_duration,
+                start_temp: temperature.day,
+                target_temp: temperature.night,
             });
         }
 
-        if now >= sunrise && now < sunrise + duration {
+        if now >= sunrise && now < sunrise + morning_duration {
             return Some(TransitionWindow {
                 start: sunrise,
-                start_temp: self.config.temperature.night,
-                target_temp: self.config.temperature.day,
+                duration: morning_duration,
+                start_temp: temperature.night,
+                target_temp: temperature.day,
             });
         }
 
@@ -148,24 +550,75 @@ impl Schedule {
 
     pub fn next_transition_start(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
         match self.config.mode {
-            Mode::Auto => self.auto_next_transition_start(now),
+            Mode::Auto => match self.polar_phase(now) {
+                Some(_) if self.config.auto.polar == Polar::Hold => None,
+                Some(_) => self.fixed_next_transition_start(now),
+                None => self.auto_next_transition_start(now),
+            },
+            Mode::Fixed if self.has_stops() => self
+                .stop_segment(now)
+                .filter(|segment| segment.start_temp == segment.end_temp)
+                .map(|segment| segment.end),
             Mode::Fixed => self.fixed_next_transition_start(now),
+            Mode::Manual => self.keyframe_segment(now).map(|segment| segment.end),
         }
     }
 
+    /// The next boundary of any kind after `now`: the start of the next
+    /// transition, or the end of the one in progress. Falls back to the next
+    /// local midnight when nothing is scheduled (a polar hold, a single
+    /// keyframe), so the schedule is still re-evaluated once a day.
+    pub fn next_phase_change(&self, now: DateTime<Local>) -> DateTime<Local> {
+        self.next_transition_start(now)
+            .or_else(|| {
+                self.transition_window_at(now)
+                    .map(|window| window.start + window.duration)
+            })
+            .filter(|next| *next > now)
+            .unwrap_or_else(|| next_midnight(now))
+    }
+
+    /// Start of the next transition after `now` that ends in `target`
+    /// (`Phase::Day` or `Phase::Night`; any when `None`), skipping one
+    /// already in progress. Gives up after a few days' worth of transitions.
+    pub fn next_transition_into(
+        &self,
+        now: DateTime<Local>,
+        target: Option<Phase>,
+    ) -> Option<DateTime<Local>> {
+        let mut at = now;
+        for _ in 0..16 {
+            let Some(start) = self.next_transition_start(at) else {
+                // Mid-transition: look again once it has finished
+                let window = self.transition_window_at(at)?;
+                at = window.start + window.duration + Duration::seconds(1);
+                continue;
+            };
+            let ends_in = match self.current_phase_at(start + Duration::seconds(1)) {
+                Phase::TransitioningToNight => Phase::Night,
+                Phase::TransitioningToDay => Phase::Day,
+                phase @ (Phase::Day | Phase::Night) => phase,
+            };
+            if target.is_none_or(|target| target == ends_in) {
+                return Some(start);
+            }
+            at = start + Duration::seconds(1);
+        }
+        None
+    }
+
     fn auto_next_transition_start(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
-        let (sunrise, sunset) = sunrise_sunset_local(&self.coordinates, now);
-        let duration = Duration::minutes(i64::from(self.config.transition.duration_minutes));
+        let (sunrise, sunset) = self.auto_times(now);
+        let (_, evening_duration) = self.auto_durations(now);
 
         let phase = self.auto_phase(now);
         match phase {
             Phase::Day => Some(sunset),
-            Phase::Night if now >= sunset + duration => {
+            Phase::Night if now >= sunset + evening_duration => {
                 // Night after sunset — next transition is tomorrow's sunrise
                 let tomorrow = now.date_naive().succ_opt()?;
                 let tomorrow_noon = local_datetime(tomorrow, NaiveTime::from_hms_opt(12, 0, 0)?)?;
-                let (tomorrow_sunrise, _) =
-                    sunrise_sunset_local(&self.coordinates, tomorrow_noon);
+                let (tomorrow_sunrise, _) = self.auto_times(tomorrow_noon);
                 Some(tomorrow_sunrise)
             }
             Phase::Night => {
@@ -178,23 +631,24 @@ impl Schedule {
 
     fn fixed_next_transition_start(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
         let date = now.date_naive();
-        let duration = Duration::minutes(i64::from(self.config.transition.duration_minutes));
+        let (wakeup_time, bedtime_time) = self.times_for(date);
+        let (_, to_night) = fixed_durations(wakeup_time, bedtime_time, self.configured_durations());
 
         let phase = self.fixed_phase(now);
         match phase {
             Phase::Day => {
                 // Next transition is bedtime - duration (start of TransitioningToNight)
-                let bedtime_dt = local_datetime(date, self.bedtime_time)?;
-                Some(bedtime_dt - duration)
+                let bedtime_dt = local_datetime(date, bedtime_time)?;
+                Some(bedtime_dt - to_night)
             }
-            Phase::Night if now.time() >= self.bedtime_time => {
+            Phase::Night if now.time() >= bedtime_time => {
                 // Night after bedtime — next transition is tomorrow's wakeup
                 let tomorrow = date.succ_opt()?;
-                local_datetime(tomorrow, self.wakeup_time)
+                local_datetime(tomorrow, self.times_for(tomorrow).0)
             }
             Phase::Night => {
                 // Night before wakeup — next transition is today's wakeup
-                local_datetime(date, self.wakeup_time)
+                local_datetime(date, wakeup_time)
             }
             Phase::TransitioningToNight | Phase::TransitioningToDay => None,
         }
@@ -203,27 +657,32 @@ impl Schedule {
     fn fixed_transition_window(
         &self,
         now: DateTime<Local>,
-        duration: Duration,
+        durations: (Duration, Duration),
     ) -> Option<TransitionWindow> {
         let date = now.date_naive();
-        let wakeup_dt = local_datetime(date, self.wakeup_time)?;
-        let bedtime_dt = local_datetime(date, self.bedtime_time)?;
+        let (wakeup_time, bedtime_time) = self.times_for(date);
+        let (to_day, to_night) = fixed_durations(wakeup_time, bedtime_time, durations);
+        let temperature = self.config.temperature.on(now.weekday());
+        let wakeup_dt = local_datetime(date, wakeup_time)?;
+        let bedtime_dt = local_datetime(date, bedtime_time)?;
 
-        let wakeup_end = wakeup_dt + duration;
+        let wakeup_end = wakeup_dt + to_day;
         if now >= wakeup_dt && now < wakeup_end {
             return Some(TransitionWindow {
                 start: wakeup_dt,
-                start_temp: self.config.temperature.night,
-                target_temp: self.config.temperature.day,
+                duration: to_day,
+                start_temp: temperature.night,
+                target_temp: temperature.day,
             });
         }
 
-        let bedtime_start = bedtime_dt - duration;
+        let bedtime_start = bedtime_dt - to_night;
         if now >= bedtime_start && now < bedtime_dt {
             return Some(TransitionWindow {
                 start: bedtime_start,
-                start_temp: self.config.temperature.day,
-                target_temp: self.config.temperature.night,
+                duration: to_night,
+                start_temp: temperature.day,
+                target_temp: temperature.night,
             });
         }
 
@@ -231,35 +690,359 @@ impl Schedule {
     }
 }
 
+/// Rejects a fixed-mode day whose wakeup and bedtime coincide, and returns a
+/// warning when the awake window can't fit both transitions (it should be at
+/// least twice the transition duration).
+fn check_awake_window(
+    wakeup: NaiveTime,
+    bedtime: NaiveTime,
+    duration: Duration,
+) -> Result<Option<String>, String> {
+    let fmt = |time: NaiveTime| time.format("%H:%M").to_string();
+    if wakeup == bedtime {
+        return Err(format!(
+            "wakeup and bedtime are both {}; the awake window must be at least twice the \
+             transition duration ({} minutes)",
+            fmt(wakeup),
+            (duration * 2).num_minutes()
+        ));
+    }
+
+    let awake = awake_window(wakeup, bedtime);
+    Ok((awake < duration * 2).then(|| {
+        format!(
+            "awake window {}-{} ({} minutes) is shorter than twice the transition duration \
+             ({} minutes); both transitions will be shortened to meet at its midpoint",
+            fmt(wakeup),
+            fmt(bedtime),
+            awake.num_minutes(),
+            (duration * 2).num_minutes()
+        )
+    }))
+}
+
+/// Time from `wakeup` to `bedtime`, wrapping past midnight.
+fn awake_window(wakeup: NaiveTime, bedtime: NaiveTime) -> Duration {
+    let awake = bedtime - wakeup;
+    if awake < Duration::zero() {
+        awake + Duration::days(1)
+    } else {
+        awake
+    }
+}
+
+/// Fixed-mode (to-day, to-night) transition lengths for a day: `configured`,
+/// each capped at half the awake window. When the transitions would overlap
+/// there is no Day phase; the morning ramp peaks at the window midpoint and
+/// the evening ramp starts from there.
+fn fixed_durations(
+    wakeup: NaiveTime,
+    bedtime: NaiveTime,
+    (to_day, to_night): (Duration, Duration),
+) -> (Duration, Duration) {
+    let half = awake_window(wakeup, bedtime) / 2;
+    (to_day.min(half), to_night.min(half))
+}
+
+/// Keyframes between two consecutive manual-mode points.
+struct Segment {
+    start: DateTime<Local>,
+    start_temp: u16,
+    end: DateTime<Local>,
+    end_temp: u16,
+}
+
+/// Parses and sorts the manual-mode keyframes. Manual mode needs at least one,
+/// and two keyframes can't share a time.
+fn parse_keyframes(config: &Config) -> Result<Vec<(NaiveTime, u16)>, String> {
+    let mut keyframes = config
+        .keyframes
+        .iter()
+        .map(|keyframe| {
+            let time = parse_time("keyframe", &keyframe.time)?;
+            Ok((
+                time,
+                check_temperature("Keyframe", time, keyframe.temperature)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    keyframes.sort_by_key(|(time, _)| *time);
+
+    if config.mode == Mode::Manual && keyframes.is_empty() {
+        return Err("Manual mode needs at least one [[keyframes]] entry".to_string());
+    }
+    if let Some(pair) = keyframes.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(format!(
+            "Duplicate keyframe time {}",
+            pair[0].0.format("%H:%M")
+        ));
+    }
+    Ok(keyframes)
+}
+
+/// `kelvin` if hyprsunset accepts it (or it is `OFF`), for the `label`
+/// point at `time`.
+fn check_temperature(label: &str, time: NaiveTime, kelvin: u16) -> Result<u16, String> {
+    if kelvin == crate::hyprctl::OFF || TEMPERATURE_RANGE.contains(&kelvin) {
+        return Ok(kelvin);
+    }
+    Err(format!(
+        "{label} at {} has {kelvin}K, outside hyprsunset's {}-{}K range",
+        time.format("%H:%M"),
+        TEMPERATURE_RANGE.start(),
+        TEMPERATURE_RANGE.end()
+    ))
+}
+
+/// Parses and sorts `schedule.stops`, which have to fall strictly between
+/// wakeup and bedtime (wrapping past midnight) at distinct times.
+fn parse_stops(
+    config: &Config,
+    wakeup: NaiveTime,
+    bedtime: NaiveTime,
+) -> Result<Vec<(NaiveTime, u16)>, String> {
+    let mut stops = config
+        .schedule
+        .stops
+        .iter()
+        .map(|stop| {
+            let time = parse_time("stop", &stop.time)?;
+            Ok((time, check_temperature("Stop", time, stop.temperature)?))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    stops.sort_by_key(|(time, _)| *time);
+
+    let window = awake_window(wakeup, bedtime);
+    if let Some((time, _)) = stops.iter().find(|(time, _)| {
+        let offset = awake_window(wakeup, *time);
+        offset.is_zero() || offset >= window
+    }) {
+        return Err(format!(
+            "Stop at {} is outside the wakeup-bedtime window",
+            time.format("%H:%M")
+        ));
+    }
+    if let Some(pair) = stops.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(format!("Duplicate stop time {}", pair[0].0.format("%H:%M")));
+    }
+    Ok(stops)
+}
+
+/// The keyframes before and after `now` among `keyframes_on(date)`, as local
+/// datetimes. Before the day's first keyframe the previous day's last one
+/// applies; after its last, the next day's first.
+fn segment_around(
+    now: DateTime<Local>,
+    keyframes_on: impl Fn(NaiveDate) -> Vec<(NaiveTime, u16)>,
+) -> Option<Segment> {
+    let date = now.date_naive();
+    let time = now.time();
+    let today = keyframes_on(date);
+
+    let (prev_date, (prev_time, prev_temp)) =
+        if let Some(&keyframe) = today.iter().rev().find(|(at, _)| *at <= time) {
+            (date, keyframe)
+        } else {
+            let yesterday = date.pred_opt()?;
+            (yesterday, *keyframes_on(yesterday).last()?)
+        };
+    let (next_date, (next_time, next_temp)) =
+        if let Some(&keyframe) = today.iter().find(|(at, _)| *at > time) {
+            (date, keyframe)
+        } else {
+            let tomorrow = date.succ_opt()?;
+            (tomorrow, *keyframes_on(tomorrow).first()?)
+        };
+
+    Some(Segment {
+        start: local_datetime(prev_date, prev_time)?,
+        start_temp: prev_temp,
+        end: local_datetime(next_date, next_time)?,
+        end_temp: next_temp,
+    })
+}
+
+/// Time-of-day formats accepted after `%H:%M`: `19:30:15` and `7:30 PM`.
+const EXTRA_TIME_FORMATS: [&str; 2] = ["%H:%M:%S", "%I:%M %p"];
+
 fn parse_time(label: &str, value: &str) -> Result<NaiveTime, String> {
     NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|e| {
+            EXTRA_TIME_FORMATS
+                .iter()
+                .find_map(|format| NaiveTime::parse_from_str(value, format).ok())
+                .ok_or(e)
+        })
         .map_err(|e| format!("Invalid {label} time '{value}': {e}"))
 }
 
-fn sunrise_sunset_local(coordinates: &Coordinates, now: DateTime<Local>) -> (DateTime<Local>, DateTime<Local>) {
-    let solar_day = SolarDay::new(*coordinates, now.date_naive());
+/// Parses an `auto.evening_start` anchor, `solar_noon` optionally followed by
+/// `+<N>h`, `-<N>h`, `+<N>m` or `-<N>m`, into its offset from solar noon.
+fn parse_solar_noon_offset(value: &str) -> Result<Duration, String> {
+    let invalid =
+        || format!("Invalid evening_start '{value}': expected solar_noon[+|-]<N>h or <N>m");
+    let rest = value
+        .trim()
+        .strip_prefix("solar_noon")
+        .ok_or_else(invalid)?;
+    if rest.is_empty() {
+        return Ok(Duration::zero());
+    }
 
-    let sunrise = solar_day
-        .event_time(SolarEvent::Sunrise)
-        .with_timezone(&Local);
-    let sunset = solar_day
-        .event_time(SolarEvent::Sunset)
-        .with_timezone(&Local);
+    let (sign, amount) = if let Some(amount) = rest.strip_prefix('+') {
+        (1, amount)
+    } else if let Some(amount) = rest.strip_prefix('-') {
+        (-1, amount)
+    } else {
+        return Err(invalid());
+    };
+    let (number, to_duration): (_, fn(i64) -> Duration) =
+        if let Some(number) = amount.strip_suffix('h') {
+            (number, Duration::hours)
+        } else if let Some(number) = amount.strip_suffix('m') {
+            (number, Duration::minutes)
+        } else {
+            return Err(invalid());
+        };
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+    Ok(to_duration(sign * number))
+}
+
+/// Midpoint of `sunrise` and `sunset`.
+fn solar_noon(sunrise: DateTime<Local>, sunset: DateTime<Local>) -> DateTime<Local> {
+    sunrise + (sunset - sunrise) / 2
+}
+
+/// `Phase::Day` if the sun stays above `elevation_degrees` all of `date` at
+/// `latitude` (polar day), `Phase::Night` if it stays below (polar night).
+/// Uses the NOAA declination approximation, independent of the sunrise
+/// crate, whose event times don't mean anything on such days.
+fn polar_phase_on(latitude: f64, elevation_degrees: f64, date: NaiveDate) -> Option<Phase> {
+    let year_angle = std::f64::consts::TAU / 365.0 * f64::from(date.ordinal0());
+    let terms = [
+        1.0,
+        year_angle.cos(),
+        year_angle.sin(),
+        (2.0 * year_angle).cos(),
+        (2.0 * year_angle).sin(),
+    ];
+    let coefficients = [0.006_918, -0.399_912, 0.070_257, -0.006_758, 0.000_907];
+    let declination: f64 = terms
+        .iter()
+        .zip(coefficients)
+        .map(|(term, c)| term * c)
+        .sum();
+
+    let latitude = latitude.to_radians();
+    let elevation = elevation_degrees.to_radians();
+    let cos_hour_angle = latitude.sin().mul_add(-declination.sin(), elevation.sin())
+        / (latitude.cos() * declination.cos());
+    if cos_hour_angle < -1.0 {
+        Some(Phase::Day)
+    } else if cos_hour_angle > 1.0 {
+        Some(Phase::Night)
+    } else {
+        None
+    }
+}
+
+/// Local sunrise and sunset on `now`'s date, taken as the moments the sun
+/// crosses `elevation_degrees`.
+fn sunrise_sunset_local(
+    coordinates: &Coordinates,
+    elevation_degrees: f64,
+    now: DateTime<Local>,
+) -> (DateTime<Local>, DateTime<Local>) {
+    let (sunrise, sunset) = sunrise_sunset(coordinates, elevation_degrees, now.date_naive());
+    (sunrise.with_timezone(&Local), sunset.with_timezone(&Local))
+}
+
+fn sunrise_sunset(
+    coordinates: &Coordinates,
+    elevation_degrees: f64,
+    date: NaiveDate,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let solar_day = SolarDay::new(*coordinates, date);
+    // `SolarEvent::Elevation` takes the depression below the horizon, in
+    // radians: -0.833° of elevation is 0.833° of depression
+    let elevation = (-elevation_degrees).to_radians();
+
+    let sunrise = solar_day.event_time(SolarEvent::Elevation {
+        elevation,
+        morning: true,
+    });
+    let sunset = solar_day.event_time(SolarEvent::Elevation {
+        elevation,
+        morning: false,
+    });
 
     (sunrise, sunset)
 }
 
+/// Sunrise and sunset (UTC) at arbitrary coordinates on `date`, for
+/// `candela sun`.
+pub fn sun_times(
+    latitude: f64,
+    longitude: f64,
+    elevation_degrees: f64,
+    date: NaiveDate,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let coordinates = Coordinates::new(latitude, longitude)
+        .ok_or_else(|| format!("Invalid coordinates: latitude={latitude} longitude={longitude}"))?;
+    Ok(sunrise_sunset(&coordinates, elevation_degrees, date))
+}
+
 fn local_datetime(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Local>> {
-    let naive = date.and_time(time);
-    Local.from_local_datetime(&naive)
-        .single()
-        .or_else(|| Local.from_local_datetime(&naive).earliest())
-        .or_else(|| Local.from_local_datetime(&naive).latest())
+    resolve_local(&Local, date.and_time(time))
+}
+
+/// Wall-clock times already warned about for falling in a DST gap.
+static WARNED_GAPS: Mutex<BTreeSet<NaiveDateTime>> = Mutex::new(BTreeSet::new());
+
+/// `naive` as an instant in `tz`. A time repeated by a fall-back change
+/// resolves to its first occurrence; one skipped by a spring-forward gap is
+/// shifted forward by the length of the gap (02:30 becomes 03:30 when 02:00
+/// jumps to 03:00), with a warning the first time.
+fn resolve_local<Tz: TimeZone>(tz: &Tz, naive: NaiveDateTime) -> Option<DateTime<Tz>> {
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => Some(datetime),
+        LocalResult::None => {
+            // Read the skipped time with the offset in force before the gap
+            let before = tz
+                .from_local_datetime(&(naive - Duration::days(1)))
+                .earliest()?;
+            let offset = Duration::seconds(i64::from(before.offset().fix().local_minus_utc()));
+            let shifted = tz.from_utc_datetime(&(naive - offset));
+            if WARNED_GAPS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(naive)
+            {
+                log::warn!(
+                    "{} falls in a daylight saving gap; using {} instead",
+                    naive.format("%Y-%m-%d %H:%M"),
+                    shifted.naive_local().format("%H:%M")
+                );
+            }
+            Some(shifted)
+        }
+    }
+}
+
+fn next_midnight(now: DateTime<Local>) -> DateTime<Local> {
+    now.date_naive()
+        .succ_opt()
+        .and_then(|tomorrow| local_datetime(tomorrow, NaiveTime::MIN))
+        .unwrap_or(now + Duration::days(1))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{DurationMinutes, PhaseDurations};
     use chrono::{Duration, Local, TimeZone, Timelike};
 
     /// Config with coordinates aligned to the local timezone so solar events
@@ -279,7 +1062,7 @@ mod tests {
         let schedule = Schedule::new(config).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (_, sunset) = sunrise_sunset_local(&schedule.coordinates, base);
+        let (_, sunset) = schedule.sun_times(base);
         let after_sunset = sunset + Duration::hours(2);
 
         assert_eq!(schedule.current_phase_at(after_sunset), Phase::Night);
@@ -288,12 +1071,13 @@ mod tests {
     #[test]
     fn auto_phase_after_sunrise_is_transitioning_to_day() {
         let mut config = auto_test_config();
-        config.transition.duration_minutes = 60;
+        config.transition.duration_minutes = 60.into();
         let schedule = Schedule::new(config.clone()).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (sunrise, _) = sunrise_sunset_local(&schedule.coordinates, base);
-        let half_transition = Duration::minutes(i64::from(config.transition.duration_minutes / 2));
+        let (sunrise, _) = schedule.sun_times(base);
+        let half_transition =
+            Duration::minutes(i64::from(config.transition.duration_minutes.to_day() / 2));
         let during_transition = sunrise + half_transition;
 
         assert_eq!(
@@ -308,12 +1092,181 @@ mod tests {
         let schedule = Schedule::new(config).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (sunrise, sunset) = sunrise_sunset_local(&schedule.coordinates, base);
+        let (sunrise, sunset) = schedule.sun_times(base);
         let midpoint = sunrise + (sunset - sunrise) / 2;
 
         assert_eq!(schedule.current_phase_at(midpoint), Phase::Day);
     }
 
+    #[test]
+    fn lower_sun_elevation_widens_daylight() {
+        let config = auto_test_config();
+        let standard = Schedule::new(config.clone()).expect("valid config");
+        let mut civil = config;
+        civil.auto.sun_elevation_degrees = -6.0;
+        let civil = Schedule::new(civil).expect("valid config");
+
+        let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let (standard_sunrise, standard_sunset) = standard.sun_times(base);
+        let (civil_sunrise, civil_sunset) = civil.sun_times(base);
+
+        assert!(civil_sunrise < standard_sunrise);
+        assert!(civil_sunset > standard_sunset);
+    }
+
+    #[test]
+    fn weekend_wakeup_replaces_sunrise_on_weekends_only() {
+        let mut config = auto_test_config();
+        config.auto.weekend_wakeup = Some("10:00".to_string());
+        let schedule = Schedule::new(config).expect("valid config");
+
+        // 2024-06-01 is a Saturday, 2024-06-03 a Monday; 08:00 is after sunrise
+        let saturday = Local.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap();
+        let monday = Local.with_ymd_and_hms(2024, 6, 3, 8, 0, 0).unwrap();
+        assert!(schedule.sun_times(saturday).0 < saturday);
+
+        assert_eq!(schedule.current_phase_at(saturday), Phase::Night);
+        assert_ne!(schedule.current_phase_at(monday), Phase::Night);
+
+        let saturday_wakeup = saturday.with_hour(10).unwrap();
+        assert_eq!(
+            schedule.current_phase_at(saturday_wakeup),
+            Phase::TransitioningToDay
+        );
+        assert_eq!(
+            schedule.next_transition_start(saturday),
+            Some(saturday_wakeup)
+        );
+    }
+
+    #[test]
+    fn sun_times_match_reference_values() {
+        // Greenwich on the 2024 summer solstice: sunrise 03:43, sunset 20:21 UTC
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let (sunrise, sunset) = sun_times(51.4769, 0.0, -0.833, date).unwrap();
+        let at = |h, m| Utc.from_utc_datetime(&date.and_hms_opt(h, m, 0).unwrap());
+        assert!(
+            (sunrise - at(3, 43)).num_minutes().abs() <= 3,
+            "sunrise {sunrise}"
+        );
+        assert!(
+            (sunset - at(20, 21)).num_minutes().abs() <= 3,
+            "sunset {sunset}"
+        );
+        assert!(sun_times(91.0, 0.0, -0.833, date).is_err());
+    }
+
+    #[test]
+    fn solar_noon_offsets_parse() {
+        assert_eq!(parse_solar_noon_offset("solar_noon"), Ok(Duration::zero()));
+        assert_eq!(
+            parse_solar_noon_offset("solar_noon+4h"),
+            Ok(Duration::hours(4))
+        );
+        assert_eq!(
+            parse_solar_noon_offset("solar_noon-90m"),
+            Ok(Duration::minutes(-90))
+        );
+        let invalid = [
+            "sunset+1h",
+            "solar_noon+4",
+            "solar_noon*4h",
+            "solar_noon+h",
+            "solar_noon+-4h",
+            "noon",
+        ];
+        for invalid in invalid {
+            assert!(parse_solar_noon_offset(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn evening_start_is_anchored_to_solar_noon() {
+        let mut config = auto_test_config();
+        config.auto.evening_start = Some("solar_noon+4h".to_string());
+        let schedule = Schedule::new(config).expect("valid config");
+
+        let base = Local.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap();
+        let (sunrise, sunset) = schedule.sun_times(base);
+        let evening = sunrise + (sunset - sunrise) / 2 + Duration::hours(4);
+        assert!(evening < sunset);
+
+        assert_eq!(schedule.next_transition_start(base), Some(evening));
+        assert_eq!(
+            schedule.current_phase_at(evening - Duration::minutes(1)),
+            Phase::Day
+        );
+        assert_eq!(
+            schedule.current_phase_at(evening + Duration::minutes(1)),
+            Phase::TransitioningToNight
+        );
+    }
+
+    #[test]
+    fn nights_shorter_than_the_minimum_stay_at_day_temperature() {
+        let mut config = auto_test_config();
+        config.location.latitude = 60.0;
+        let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let tomorrow_noon = Local.with_ymd_and_hms(2024, 6, 2, 12, 0, 0).unwrap();
+        let schedule = Schedule::new(config.clone()).expect("valid config");
+        let (_, sunset) = schedule.sun_times(base);
+        let (next_sunrise, _) = schedule.sun_times(tomorrow_noon);
+        let night_minutes = u32::try_from((next_sunrise - sunset).num_minutes()).unwrap();
+        let late_evening = sunset + Duration::minutes(90);
+        let early_morning = next_sunrise + Duration::minutes(10);
+
+        config.auto.min_night_minutes = night_minutes - 10;
+        let schedule = Schedule::new(config.clone()).expect("valid config");
+        assert_eq!(schedule.current_phase_at(late_evening), Phase::Night);
+
+        config.auto.min_night_minutes = night_minutes + 10;
+        let schedule = Schedule::new(config).expect("valid config");
+        for now in [sunset + Duration::minutes(1), late_evening, early_morning] {
+            assert_eq!(schedule.current_phase_at(now), Phase::Day);
+            assert!(schedule.transition_window_at(now).is_none());
+            assert_eq!(schedule.target_temperature_at(now), 6500);
+        }
+    }
+
+    #[test]
+    fn duration_longer_than_night_is_capped_at_next_sunrise() {
+        let mut config = auto_test_config();
+        config.location.latitude = 60.0;
+        config.transition.duration_minutes = (12 * 60).into();
+        let schedule = Schedule::new(config).expect("valid config");
+
+        let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let (_, sunset) = schedule.sun_times(base);
+        let tomorrow_noon = Local.with_ymd_and_hms(2024, 6, 2, 12, 0, 0).unwrap();
+        let (next_sunrise, _) = schedule.sun_times(tomorrow_noon);
+        assert!(next_sunrise - sunset < Duration::hours(12));
+
+        let window = schedule
+            .transition_window_at(sunset + Duration::minutes(1))
+            .expect("evening window");
+        assert_eq!(window.duration, next_sunrise - sunset);
+
+        let (morning_duration, _) = schedule.auto_durations(base);
+        assert_eq!(morning_duration, Duration::hours(12));
+    }
+
+    #[test]
+    fn times_parse_with_seconds_and_in_12_hour_form() {
+        let time = |h, m, s| NaiveTime::from_hms_opt(h, m, s).unwrap();
+        assert_eq!(parse_time("wakeup", "07:00"), Ok(time(7, 0, 0)));
+        assert_eq!(parse_time("wakeup", "07:00:30"), Ok(time(7, 0, 30)));
+        assert_eq!(parse_time("wakeup", "7:00 AM"), Ok(time(7, 0, 0)));
+        assert_eq!(parse_time("bedtime", "10:30 pm"), Ok(time(22, 30, 0)));
+        assert_eq!(parse_time("bedtime", "12:15 AM"), Ok(time(0, 15, 0)));
+
+        let error = parse_time("bedtime", "half past ten").unwrap_err();
+        assert!(
+            error.starts_with("Invalid bedtime time 'half past ten'"),
+            "{error}"
+        );
+        assert!(parse_time("wakeup", "13:00 PM").is_err());
+    }
+
     #[test]
     fn fixed_schedule_rejects_invalid_time() {
         let mut config = Config::default();
@@ -327,11 +1280,11 @@ mod tests {
     #[test]
     fn auto_phase_at_sunset_is_transitioning_to_night() {
         let mut config = auto_test_config();
-        config.transition.duration_minutes = 60;
+        config.transition.duration_minutes = 60.into();
         let schedule = Schedule::new(config).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (_, sunset) = sunrise_sunset_local(&schedule.coordinates, base);
+        let (_, sunset) = schedule.sun_times(base);
 
         assert_eq!(
             schedule.current_phase_at(sunset),
@@ -342,12 +1295,13 @@ mod tests {
     #[test]
     fn auto_phase_at_transition_end_is_night() {
         let mut config = auto_test_config();
-        config.transition.duration_minutes = 60;
+        config.transition.duration_minutes = 60.into();
         let schedule = Schedule::new(config.clone()).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (_, sunset) = sunrise_sunset_local(&schedule.coordinates, base);
-        let end = sunset + Duration::minutes(i64::from(config.transition.duration_minutes));
+        let (_, sunset) = schedule.sun_times(base);
+        let end =
+            sunset + Duration::minutes(i64::from(config.transition.duration_minutes.to_night()));
 
         assert_eq!(schedule.current_phase_at(end), Phase::Night);
     }
@@ -358,7 +1312,7 @@ mod tests {
         let schedule = Schedule::new(config).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (sunrise, _) = sunrise_sunset_local(&schedule.coordinates, base);
+        let (sunrise, _) = schedule.sun_times(base);
 
         assert_eq!(
             schedule.current_phase_at(sunrise),
@@ -372,7 +1326,7 @@ mod tests {
         config.mode = Mode::Fixed;
         config.schedule.wakeup = "07:00".to_string();
         config.schedule.bedtime = "22:00".to_string();
-        config.transition.duration_minutes = 60;
+        config.transition.duration_minutes = 60.into();
         let schedule = Schedule::new(config).expect("valid config");
 
         let day = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
@@ -391,6 +1345,38 @@ mod tests {
         assert_eq!(schedule.current_phase_at(bedtime), Phase::Night);
     }
 
+    #[test]
+    fn next_phase_change_covers_transition_starts_and_ends() {
+        let mut config = Config::default();
+        config.mode = Mode::Fixed;
+        config.schedule.wakeup = "07:00".to_string();
+        config.schedule.bedtime = "22:00".to_string();
+        config.transition.duration_minutes = 60.into();
+        let schedule = Schedule::new(config).expect("valid config");
+
+        let at = |day, hour, minute| {
+            Local
+                .with_ymd_and_hms(2024, 6, day, hour, minute, 0)
+                .unwrap()
+        };
+        assert_eq!(schedule.next_phase_change(at(1, 3, 0)), at(1, 7, 0));
+        assert_eq!(schedule.next_phase_change(at(1, 7, 30)), at(1, 8, 0));
+        assert_eq!(schedule.next_phase_change(at(1, 12, 0)), at(1, 21, 0));
+        assert_eq!(schedule.next_phase_change(at(1, 21, 0)), at(1, 22, 0));
+        assert_eq!(schedule.next_phase_change(at(1, 22, 0)), at(2, 7, 0));
+    }
+
+    #[test]
+    fn next_phase_change_without_a_boundary_is_midnight() {
+        let mut config = auto_test_config();
+        config.location.latitude = 78.0;
+        let schedule = Schedule::new(config).expect("valid config");
+
+        let noon = Local.with_ymd_and_hms(2024, 6, 21, 12, 0, 0).unwrap();
+        let midnight = Local.with_ymd_and_hms(2024, 6, 22, 0, 0, 0).unwrap();
+        assert_eq!(schedule.next_phase_change(noon), midnight);
+    }
+
     // --- next_transition_start tests (auto mode) ---
 
     #[test]
@@ -399,7 +1385,7 @@ mod tests {
         let schedule = Schedule::new(config).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (_, sunset) = sunrise_sunset_local(&schedule.coordinates, base);
+        let (_, sunset) = schedule.sun_times(base);
 
         let result = schedule.next_transition_start(base);
         assert_eq!(result, Some(sunset));
@@ -408,15 +1394,15 @@ mod tests {
     #[test]
     fn next_transition_during_night_after_sunset_is_tomorrow_sunrise() {
         let mut config = auto_test_config();
-        config.transition.duration_minutes = 30;
+        config.transition.duration_minutes = 30.into();
         let schedule = Schedule::new(config).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (_, sunset) = sunrise_sunset_local(&schedule.coordinates, base);
+        let (_, sunset) = schedule.sun_times(base);
         let night = sunset + Duration::hours(2);
 
         let tomorrow_noon = Local.with_ymd_and_hms(2024, 6, 2, 12, 0, 0).unwrap();
-        let (tomorrow_sunrise, _) = sunrise_sunset_local(&schedule.coordinates, tomorrow_noon);
+        let (tomorrow_sunrise, _) = schedule.sun_times(tomorrow_noon);
 
         let result = schedule.next_transition_start(night);
         assert_eq!(result, Some(tomorrow_sunrise));
@@ -428,7 +1414,7 @@ mod tests {
         let schedule = Schedule::new(config).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (sunrise, _) = sunrise_sunset_local(&schedule.coordinates, base);
+        let (sunrise, _) = schedule.sun_times(base);
         let early_morning = base.with_hour(2).unwrap().with_minute(0).unwrap();
 
         assert_eq!(schedule.current_phase_at(early_morning), Phase::Night);
@@ -440,11 +1426,11 @@ mod tests {
     #[test]
     fn next_transition_during_transition_is_none() {
         let mut config = auto_test_config();
-        config.transition.duration_minutes = 60;
+        config.transition.duration_minutes = 60.into();
         let schedule = Schedule::new(config).expect("valid config");
 
         let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
-        let (_, sunset) = sunrise_sunset_local(&schedule.coordinates, base);
+        let (_, sunset) = schedule.sun_times(base);
         let during = sunset + Duration::minutes(15);
 
         assert_eq!(
@@ -461,7 +1447,7 @@ mod tests {
         config.mode = Mode::Fixed;
         config.schedule.wakeup = "07:00".to_string();
         config.schedule.bedtime = "22:00".to_string();
-        config.transition.duration_minutes = 60;
+        config.transition.duration_minutes = 60.into();
         config
     }
 
@@ -507,10 +1493,558 @@ mod tests {
         let schedule = Schedule::new(config).expect("valid config");
 
         let wakeup = Local.with_ymd_and_hms(2024, 6, 1, 7, 30, 0).unwrap();
+        assert_eq!(schedule.current_phase_at(wakeup), Phase::TransitioningToDay);
+        assert_eq!(schedule.next_transition_start(wakeup), None);
+    }
+
+    #[test]
+    fn next_transition_into_skips_the_current_one_and_filters_by_phase() {
+        let schedule = Schedule::new(fixed_test_config()).expect("valid config");
+        let at = |day, hour| Local.with_ymd_and_hms(2024, 6, day, hour, 0, 0).unwrap();
+        let wakeup = at(1, 7) + Duration::minutes(30);
+
+        assert_eq!(schedule.next_transition_into(wakeup, None), Some(at(1, 21)));
+        assert_eq!(
+            schedule.next_transition_into(wakeup, Some(Phase::Night)),
+            Some(at(1, 21))
+        );
+        assert_eq!(
+            schedule.next_transition_into(wakeup, Some(Phase::Day)),
+            Some(at(2, 7))
+        );
+        assert_eq!(
+            schedule.next_transition_into(at(1, 12), Some(Phase::Day)),
+            Some(at(2, 7))
+        );
+    }
+
+    // --- polar day and night ---
+
+    #[test]
+    fn polar_day_and_night_are_detected_at_78_north() {
+        let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+        assert_eq!(polar_phase_on(78.0, -0.833, date(6, 21)), Some(Phase::Day));
+        assert_eq!(
+            polar_phase_on(78.0, -0.833, date(12, 21)),
+            Some(Phase::Night)
+        );
+        assert_eq!(polar_phase_on(78.0, -0.833, date(3, 20)), None);
+        assert_eq!(polar_phase_on(48.0, -0.833, date(6, 21)), None);
+        // Southern hemisphere seasons are flipped
+        assert_eq!(
+            polar_phase_on(-78.0, -0.833, date(6, 21)),
+            Some(Phase::Night)
+        );
+    }
+
+    #[test]
+    fn polar_hold_keeps_one_phase_all_day() {
+        let mut config = auto_test_config();
+        config.location.latitude = 78.0;
+        let schedule = Schedule::new(config).expect("valid config");
+
+        for (month, phase, temperature) in [(6, Phase::Day, 6500), (12, Phase::Night, 1500)] {
+            let start = Local.with_ymd_and_hms(2024, month, 20, 0, 0, 0).unwrap();
+            for step in 0..(3 * 48) {
+                let now = start + Duration::minutes(30 * step);
+                assert_eq!(schedule.current_phase_at(now), phase, "{now}");
+                assert_eq!(schedule.temperature_at(now), temperature, "{now}");
+                assert!(schedule.transition_window_at(now).is_none(), "{now}");
+            }
+        }
+    }
+
+    #[test]
+    fn polar_fixed_follows_wakeup_and_bedtime() {
+        let mut config = auto_test_config();
+        config.location.latitude = 78.0;
+        config.auto.polar = crate::config::Polar::Fixed;
+        let schedule = Schedule::new(config).expect("valid config");
+
+        for month in [6, 12] {
+            let at = |hour| Local.with_ymd_and_hms(2024, month, 21, hour, 0, 0).unwrap();
+            assert_eq!(schedule.current_phase_at(at(3)), Phase::Night);
+            assert_eq!(schedule.current_phase_at(at(12)), Phase::Day);
+            assert_eq!(schedule.current_phase_at(at(23)), Phase::Night);
+            assert_eq!(schedule.next_transition_start(at(12)), Some(at(21)));
+        }
+    }
+
+    // --- daylight saving ---
+
+    /// Central European time for 2024: CEST from 31 March to 27 October,
+    /// both changes at 01:00 UTC.
+    #[derive(Clone, Copy)]
+    struct Cet2024;
+
+    impl Cet2024 {
+        fn hours(hours: i32) -> chrono::FixedOffset {
+            chrono::FixedOffset::east_opt(hours * 3600).unwrap()
+        }
+    }
+
+    impl TimeZone for Cet2024 {
+        type Offset = chrono::FixedOffset;
+
+        fn from_offset(_: &Self::Offset) -> Self {
+            Self
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<Self::Offset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<Self::Offset> {
+            let fits = |offset: Self::Offset| {
+                let utc = *local - Duration::seconds(i64::from(offset.local_minus_utc()));
+                self.offset_from_utc_datetime(&utc) == offset
+            };
+            match (fits(Self::hours(2)), fits(Self::hours(1))) {
+                (true, true) => LocalResult::Ambiguous(Self::hours(2), Self::hours(1)),
+                (true, false) => LocalResult::Single(Self::hours(2)),
+                (false, true) => LocalResult::Single(Self::hours(1)),
+                (false, false) => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> Self::Offset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> Self::Offset {
+            let change = |month, day| {
+                NaiveDate::from_ymd_opt(2024, month, day)
+                    .unwrap()
+                    .and_hms_opt(1, 0, 0)
+                    .unwrap()
+            };
+            if *utc >= change(3, 31) && *utc < change(10, 27) {
+                Self::hours(2)
+            } else {
+                Self::hours(1)
+            }
+        }
+    }
+
+    fn naive(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn time_in_spring_forward_gap_shifts_past_it() {
+        let resolved = resolve_local(&Cet2024, naive(3, 31, 2, 30)).unwrap();
+        assert_eq!(resolved.naive_local(), naive(3, 31, 3, 30));
+        assert_eq!(resolved.naive_utc(), naive(3, 31, 1, 30));
+
+        // Either side of the gap is untouched
+        let before = resolve_local(&Cet2024, naive(3, 31, 1, 59)).unwrap();
+        assert_eq!(before.naive_local(), naive(3, 31, 1, 59));
+        let after = resolve_local(&Cet2024, naive(3, 31, 3, 0)).unwrap();
+        assert_eq!(after.naive_utc(), naive(3, 31, 1, 0));
+    }
+
+    #[test]
+    fn time_in_fall_back_overlap_uses_first_occurrence() {
+        let resolved = resolve_local(&Cet2024, naive(10, 27, 2, 30)).unwrap();
+        assert_eq!(resolved.naive_local(), naive(10, 27, 2, 30));
+        assert_eq!(resolved.naive_utc(), naive(10, 27, 0, 30));
+
+        let after = resolve_local(&Cet2024, naive(10, 27, 3, 0)).unwrap();
+        assert_eq!(after.naive_utc(), naive(10, 27, 2, 0));
+    }
+
+    // --- schedule file overrides ---
+
+    #[test]
+    fn schedule_file_parses_dates_and_weekdays() {
+        let overrides = ScheduleOverrides::parse(
+            "# holidays\n2024-12-25 09:00 23:30\n\nsat 09:00 23:00 # sleep in\n",
+        )
+        .expect("valid schedule file");
+
+        let christmas = NaiveDate::from_ymd_opt(2024, 12, 25).unwrap();
+        let saturday = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+
         assert_eq!(
-            schedule.current_phase_at(wakeup),
+            overrides.get(christmas),
+            Some((
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(23, 30, 0).unwrap()
+            ))
+        );
+        assert!(overrides.get(saturday).is_some());
+        assert_eq!(overrides.get(monday), None);
+    }
+
+    #[test]
+    fn schedule_file_rejects_malformed_lines() {
+        assert!(ScheduleOverrides::parse("2024-12-25 09:00").is_err());
+        assert!(ScheduleOverrides::parse("someday 09:00 23:00").is_err());
+        assert!(ScheduleOverrides::parse("mon 9am 23:00").is_err());
+    }
+
+    #[test]
+    fn schedule_file_override_date_uses_its_times() {
+        let path = std::env::temp_dir().join(format!("candela-schedule-{}", std::process::id()));
+        std::fs::write(&path, "2024-06-01 10:00 23:00\n").unwrap();
+
+        let mut config = fixed_test_config();
+        config.schedule.file = Some(path.to_string_lossy().into_owned());
+        let schedule = Schedule::new(config).expect("valid config");
+        let _ = std::fs::remove_file(&path);
+
+        // 2024-06-01 is overridden: 07:30 is still night, 10:30 is mid-transition
+        let overridden = Local.with_ymd_and_hms(2024, 6, 1, 7, 30, 0).unwrap();
+        assert_eq!(schedule.current_phase_at(overridden), Phase::Night);
+        let late_wakeup = Local.with_ymd_and_hms(2024, 6, 1, 10, 30, 0).unwrap();
+        assert_eq!(
+            schedule.current_phase_at(late_wakeup),
             Phase::TransitioningToDay
         );
-        assert_eq!(schedule.next_transition_start(wakeup), None);
+
+        // 2024-06-02 falls back to the static 07:00 wakeup
+        let fallback = Local.with_ymd_and_hms(2024, 6, 2, 7, 30, 0).unwrap();
+        assert_eq!(
+            schedule.current_phase_at(fallback),
+            Phase::TransitioningToDay
+        );
+    }
+
+    fn manual_temperature(schedule: &Schedule, now: DateTime<Local>) -> u16 {
+        let window = schedule.transition_window_at(now).expect("manual window");
+        let elapsed = (now - window.start).to_std().unwrap().as_secs_f64();
+        let progress = elapsed / window.duration.to_std().unwrap().as_secs_f64();
+        let linear = crate::config::Transition {
+            easing: "linear".to_string(),
+            ..crate::config::Transition::default()
+        };
+        crate::transition::temperature_at(window.start_temp, window.target_temp, progress, &linear)
+    }
+
+    #[test]
+    fn manual_keyframes_interpolate_and_wrap_past_midnight() {
+        let mut config = Config::default();
+        config.mode = Mode::Manual;
+        config.keyframes = [("20:00", 2000), ("08:00", 6500), ("23:00", 1500)]
+            .into_iter()
+            .map(|(time, temperature)| crate::config::Keyframe {
+                time: time.to_string(),
+                temperature,
+            })
+            .collect();
+        let schedule = Schedule::new(config).expect("valid config");
+        let at = |h, m| Local.with_ymd_and_hms(2024, 6, 1, h, m, 0).unwrap();
+
+        assert_eq!(manual_temperature(&schedule, at(8, 0)), 6500);
+        assert_eq!(manual_temperature(&schedule, at(14, 0)), 4250);
+        assert_eq!(manual_temperature(&schedule, at(21, 30)), 1750);
+        // 23:00 -> 08:00 wraps midnight: 4h of 9h from 1500K to 6500K
+        assert_eq!(manual_temperature(&schedule, at(3, 0)), 3722);
+        assert_eq!(
+            schedule.current_phase_at(at(3, 0)),
+            Phase::TransitioningToDay
+        );
+        assert_eq!(
+            schedule.current_phase_at(at(14, 0)),
+            Phase::TransitioningToNight
+        );
+        assert_eq!(schedule.target_temperature_at(at(3, 0)), 6500);
+    }
+
+    #[test]
+    fn fixed_schedule_rejects_equal_wakeup_and_bedtime() {
+        let mut config = fixed_test_config();
+        config.schedule.bedtime = "07:00".to_string();
+
+        let err = Schedule::new(config).err().expect("equal times rejected");
+        assert!(err.contains("twice the transition duration"), "{err}");
+    }
+
+    #[test]
+    fn short_awake_window_warns() {
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        let hour = Duration::hours(1);
+
+        let warning = check_awake_window(time(7), time(8), hour).unwrap();
+        assert!(warning.is_some_and(|w| w.contains("120 minutes")));
+        assert_eq!(check_awake_window(time(7), time(9), hour), Ok(None));
+        assert_eq!(check_awake_window(time(7), time(22), hour), Ok(None));
+    }
+
+    #[test]
+    fn overlapping_fixed_transitions_meet_at_the_window_midpoint() {
+        let mut config = fixed_test_config();
+        config.schedule.bedtime = "08:00".to_string();
+        config.transition.duration_minutes = 90.into();
+        let schedule = Schedule::new(config).expect("short window only warns");
+        let at = |h, m| Local.with_ymd_and_hms(2024, 6, 1, h, m, 0).unwrap();
+
+        assert_eq!(
+            schedule.current_phase_at(at(7, 29)),
+            Phase::TransitioningToDay
+        );
+        assert_eq!(
+            schedule.current_phase_at(at(7, 30)),
+            Phase::TransitioningToNight
+        );
+        assert_eq!(schedule.current_phase_at(at(8, 0)), Phase::Night);
+        assert_eq!(
+            schedule.next_transition_start(at(8, 0)),
+            Some(at(7, 0) + Duration::days(1))
+        );
+
+        let morning = schedule
+            .transition_window_at(at(7, 15))
+            .expect("morning ramp");
+        assert_eq!(
+            (morning.start, morning.duration),
+            (at(7, 0), Duration::minutes(30))
+        );
+        let evening = schedule
+            .transition_window_at(at(7, 45))
+            .expect("evening ramp");
+        assert_eq!(
+            (evening.start, evening.duration),
+            (at(7, 30), Duration::minutes(30))
+        );
+
+        // The midpoint is the coolest point reached, with no jump on either side
+        let day = schedule.config.temperature.day;
+        assert_eq!(schedule.temperature_at(at(7, 30)), day);
+        let before = schedule.temperature_at(at(7, 30) - Duration::seconds(1));
+        assert!(day - before < 10, "{before}");
+    }
+
+    #[test]
+    fn fixed_windows_use_the_duration_for_their_direction() {
+        let mut config = fixed_test_config();
+        config.transition.duration_minutes = DurationMinutes::PerPhase(PhaseDurations {
+            to_day: 20,
+            to_night: 90,
+        });
+        let schedule = Schedule::new(config).expect("valid config");
+        let at = |h, m| Local.with_ymd_and_hms(2024, 6, 1, h, m, 0).unwrap();
+
+        let morning = schedule
+            .transition_window_at(at(7, 10))
+            .expect("morning ramp");
+        assert_eq!(
+            (morning.start, morning.duration),
+            (at(7, 0), Duration::minutes(20))
+        );
+        assert_eq!(schedule.current_phase_at(at(7, 20)), Phase::Day);
+
+        let evening = schedule
+            .transition_window_at(at(21, 0))
+            .expect("evening ramp");
+        assert_eq!(
+            (evening.start, evening.duration),
+            (at(20, 30), Duration::minutes(90))
+        );
+        assert_eq!(schedule.current_phase_at(at(20, 29)), Phase::Day);
+        assert_eq!(
+            schedule.current_phase_at(at(20, 30)),
+            Phase::TransitioningToNight
+        );
+        assert_eq!(schedule.next_transition_start(at(12, 0)), Some(at(20, 30)));
+    }
+
+    #[test]
+    fn auto_windows_use_the_duration_for_their_direction() {
+        let mut config = auto_test_config();
+        config.transition.duration_minutes = DurationMinutes::PerPhase(PhaseDurations {
+            to_day: 15,
+            to_night: 120,
+        });
+        let schedule = Schedule::new(config).expect("valid config");
+        let base = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let (sunrise, sunset) = schedule.sun_times(base);
+
+        let morning = schedule.transition_window_at(sunrise + Duration::minutes(5));
+        assert_eq!(
+            morning.map(|w| (w.start, w.duration)),
+            Some((sunrise, Duration::minutes(15)))
+        );
+        assert_eq!(
+            schedule.current_phase_at(sunrise + Duration::minutes(15)),
+            Phase::Day
+        );
+
+        let evening = schedule.transition_window_at(sunset + Duration::minutes(90));
+        assert_eq!(
+            evening.map(|w| (w.start, w.duration)),
+            Some((sunset, Duration::minutes(120)))
+        );
+        assert_eq!(
+            schedule.current_phase_at(sunset + Duration::minutes(119)),
+            Phase::TransitioningToNight
+        );
+        assert_eq!(
+            schedule.current_phase_at(sunset + Duration::minutes(120)),
+            Phase::Night
+        );
+    }
+
+    #[test]
+    fn a_stop_at_off_ramps_like_a_rise_towards_identity() {
+        let mut config = fixed_test_config();
+        config.transition.duration_minutes =
+            crate::config::DurationMinutes::PerPhase(crate::config::PhaseDurations {
+                to_day: 30,
+                to_night: 90,
+            });
+        config.schedule.stops = vec![stop("18:00", 3000), stop("21:00", crate::hyprctl::OFF)];
+        let schedule = Schedule::new(config).expect("valid stops");
+        let at = |h, m| Local.with_ymd_and_hms(2024, 6, 1, h, m, 0).unwrap();
+
+        // 3000K to identity is a rise, so it takes the 30-minute to_day ramp
+        let window = schedule
+            .transition_window_at(at(20, 45))
+            .expect("ramp to off");
+        assert_eq!(
+            (window.start, window.duration),
+            (at(20, 30), Duration::minutes(30))
+        );
+        assert_eq!(
+            (window.start_temp, window.target_temp),
+            (3000, crate::hyprctl::OFF)
+        );
+    }
+
+    fn stop(time: &str, temperature: u16) -> crate::config::Keyframe {
+        crate::config::Keyframe {
+            time: time.to_string(),
+            temperature,
+        }
+    }
+
+    #[test]
+    fn fixed_stops_interpolate_between_consecutive_points() {
+        let mut config = fixed_test_config();
+        config.schedule.stops = vec![stop("21:00", 3000)];
+        let schedule = Schedule::new(config).expect("valid stops");
+        let at = |d, h, m| Local.with_ymd_and_hms(2024, 6, d, h, m, 0).unwrap();
+
+        assert_eq!(schedule.current_phase_at(at(1, 12, 0)), Phase::Day);
+        assert_eq!(schedule.target_temperature_at(at(1, 12, 0)), 6500);
+        assert!(schedule.transition_window_at(at(1, 12, 0)).is_none());
+        assert_eq!(
+            schedule.next_transition_start(at(1, 12, 0)),
+            Some(at(1, 20, 0))
+        );
+
+        // Wind-down: day to the stop, then the stop to night by bedtime
+        let window = schedule
+            .transition_window_at(at(1, 20, 30))
+            .expect("ramp to stop");
+        assert_eq!(
+            (window.start, window.duration),
+            (at(1, 20, 0), Duration::hours(1))
+        );
+        assert_eq!((window.start_temp, window.target_temp), (6500, 3000));
+        assert_eq!(
+            schedule.current_phase_at(at(1, 20, 30)),
+            Phase::TransitioningToNight
+        );
+        assert_eq!(schedule.target_temperature_at(at(1, 20, 30)), 3000);
+        let window = schedule
+            .transition_window_at(at(1, 21, 30))
+            .expect("ramp to night");
+        assert_eq!((window.start_temp, window.target_temp), (3000, 1500));
+        assert_eq!(window.start + window.duration, at(1, 22, 0));
+
+        // The night hold wraps across midnight to the next wakeup
+        assert_eq!(schedule.current_phase_at(at(1, 23, 0)), Phase::Night);
+        assert_eq!(
+            schedule.next_transition_start(at(1, 23, 0)),
+            Some(at(2, 7, 0))
+        );
+        assert_eq!(schedule.current_phase_at(at(2, 3, 0)), Phase::Night);
+        assert_eq!(schedule.target_temperature_at(at(2, 3, 0)), 1500);
+        assert_eq!(
+            schedule.current_phase_at(at(2, 7, 30)),
+            Phase::TransitioningToDay
+        );
+        assert_eq!(schedule.temperature_at(at(2, 7, 30)), 4000);
+    }
+
+    #[test]
+    fn fixed_stops_wrap_past_midnight_with_a_late_bedtime() {
+        let mut config = fixed_test_config();
+        config.schedule.wakeup = "08:00".to_string();
+        config.schedule.bedtime = "01:00".to_string();
+        config.schedule.stops = vec![stop("00:30", 2500), stop("22:00", 3000)];
+        let schedule = Schedule::new(config).expect("stops inside a wrapped window");
+        let at = |d, h, m| Local.with_ymd_and_hms(2024, 6, d, h, m, 0).unwrap();
+
+        assert_eq!(schedule.target_temperature_at(at(1, 12, 0)), 6500);
+        let window = schedule
+            .transition_window_at(at(1, 21, 30))
+            .expect("ramp to the evening stop");
+        assert_eq!((window.start, window.start_temp), (at(1, 21, 0), 6500));
+        assert_eq!(
+            (window.duration, window.target_temp),
+            (Duration::hours(1), 3000)
+        );
+
+        // The stop after midnight ramps from the evening one
+        let window = schedule
+            .transition_window_at(at(2, 0, 15))
+            .expect("ramp to the stop past midnight");
+        assert_eq!((window.start, window.start_temp), (at(1, 23, 30), 3000));
+        assert_eq!(window.target_temp, 2500);
+        let window = schedule
+            .transition_window_at(at(2, 0, 45))
+            .expect("ramp to night");
+        assert_eq!((window.start_temp, window.target_temp), (2500, 1500));
+        assert_eq!(window.start + window.duration, at(2, 1, 0));
+        assert_eq!(schedule.current_phase_at(at(2, 3, 0)), Phase::Night);
+
+        let mut config = fixed_test_config();
+        config.schedule.wakeup = "08:00".to_string();
+        config.schedule.bedtime = "01:00".to_string();
+        config.schedule.stops = vec![stop("02:00", 3000)];
+        assert!(Schedule::new(config).is_err());
+    }
+
+    #[test]
+    fn fixed_stops_must_be_distinct_in_range_and_inside_the_awake_window() {
+        let mut config = fixed_test_config();
+        config.schedule.stops = vec![stop("06:00", 3000)];
+        assert!(Schedule::new(config.clone()).is_err());
+        config.schedule.stops = vec![stop("21:00", 3000), stop("21:00", 2500)];
+        assert!(Schedule::new(config.clone()).is_err());
+        config.schedule.stops = vec![stop("21:00", 25000)];
+        assert!(Schedule::new(config.clone()).is_err_and(|e| e.contains("25000K")));
+        config.schedule.stops = vec![stop("21:30", 2500), stop("21:00", 3000)];
+        assert!(Schedule::new(config).is_ok());
+    }
+
+    #[test]
+    fn manual_mode_rejects_missing_duplicate_or_out_of_range_keyframes() {
+        let mut config = Config::default();
+        config.mode = Mode::Manual;
+        assert!(Schedule::new(config.clone()).is_err());
+
+        let keyframe = crate::config::Keyframe {
+            time: "08:00".to_string(),
+            temperature: 6500,
+        };
+        config.keyframes = vec![keyframe.clone(), keyframe];
+        assert!(Schedule::new(config.clone()).is_err());
+
+        let cold = crate::config::Keyframe {
+            time: "23:00".to_string(),
+            temperature: 500,
+        };
+        config.keyframes = vec![cold];
+        assert_eq!(
+            Schedule::new(config).err().as_deref(),
+            Some("Keyframe at 23:00 has 500K, outside hyprsunset's 1000-20000K range")
+        );
     }
 }
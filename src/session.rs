@@ -0,0 +1,49 @@
+use crate::config::LockDetection;
+use std::process::Command;
+
+/// Whether the session is currently locked according to `detection`.
+/// Detection failures are treated as unlocked so the daemon keeps working.
+pub fn is_locked(detection: LockDetection, lock_command: &str) -> bool {
+    match detection {
+        LockDetection::Off => false,
+        LockDetection::Logind => logind_locked_hint(),
+        LockDetection::Command => command_reports_locked(lock_command),
+    }
+}
+
+fn logind_locked_hint() -> bool {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    log::debug!("Running: loginctl show-session {session} -p LockedHint --value");
+    Command::new("loginctl")
+        .args(["show-session", &session, "-p", "LockedHint", "--value"])
+        .output()
+        .is_ok_and(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).trim() == "yes")
+}
+
+fn command_reports_locked(lock_command: &str) -> bool {
+    if lock_command.is_empty() {
+        return false;
+    }
+    log::debug!("Running: sh -c {lock_command:?}");
+    Command::new("sh")
+        .args(["-c", lock_command])
+        .output()
+        .is_ok_and(|o| o.status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection_off_is_never_locked() {
+        assert!(!is_locked(LockDetection::Off, "true"));
+    }
+
+    #[test]
+    fn command_exit_status_decides_lock() {
+        assert!(is_locked(LockDetection::Command, "true"));
+        assert!(!is_locked(LockDetection::Command, "false"));
+        assert!(!is_locked(LockDetection::Command, ""));
+    }
+}
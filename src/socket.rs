@@ -0,0 +1,171 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a client waits for the daemon's reply.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the daemon waits for a client to send its command line.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The daemon's command socket, next to its status file.
+pub fn path_from_status(status_file: &str) -> PathBuf {
+    PathBuf::from(status_file).with_extension("sock")
+}
+
+/// Binds `path`, replacing a stale socket left behind by a crashed daemon.
+/// Fails with `AddrInUse` while another daemon still answers on it.
+pub fn bind(path: &Path) -> io::Result<UnixListener> {
+    if UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "another daemon is listening on it",
+        ));
+    }
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    UnixListener::bind(path)
+}
+
+/// A socket being served. Dropping it stops answering and removes the
+/// socket file.
+pub struct Server {
+    path: PathBuf,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wakes the accept loop so it sees the flag
+        let _ = UnixStream::connect(&self.path);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Answers connections on background threads, one line each: `status`
+/// replies with the status file, lines `accepts` recognises are forwarded to
+/// `commands` and acknowledged with `ok`, anything else gets an `error:` reply.
+/// Each connection gets its own thread, so a silent client can't hold up
+/// the rest.
+pub fn serve(
+    listener: UnixListener,
+    status_file: PathBuf,
+    accepts: fn(&str) -> bool,
+    commands: Sender<String>,
+) -> Server {
+    let path = listener
+        .local_addr()
+        .ok()
+        .and_then(|addr| addr.as_pathname().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let stopped = Arc::new(AtomicBool::new(false));
+    let server = Server {
+        path,
+        stopped: Arc::clone(&stopped),
+    };
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            let (status_file, commands) = (status_file.clone(), commands.clone());
+            std::thread::spawn(move || {
+                let result = stream
+                    .set_read_timeout(Some(REQUEST_TIMEOUT))
+                    .and_then(|()| answer(&stream, &status_file, accepts, &commands));
+                if let Err(e) = result {
+                    log::debug!("Control connection failed: {e}");
+                }
+            });
+        }
+    });
+    server
+}
+
+fn answer(
+    mut stream: &UnixStream,
+    status_file: &Path,
+    accepts: fn(&str) -> bool,
+    commands: &Sender<String>,
+) -> io::Result<()> {
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let line = line.trim();
+    let reply = if line == "status" {
+        fs::read_to_string(status_file).unwrap_or_default()
+    } else if accepts(line) && commands.send(line.to_string()).is_ok() {
+        "ok\n".to_string()
+    } else {
+        format!("error: unknown command {line:?}\n")
+    };
+    stream.write_all(reply.as_bytes())
+}
+
+/// Sends one command line to the daemon listening on `path` and returns its
+/// reply.
+pub fn send(path: &Path, line: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    writeln!(stream, "{}", line.trim_end())?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("candela-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn commands_are_forwarded_and_status_is_answered() {
+        let (socket, status) = (temp_path("serve.sock"), temp_path("serve.status"));
+        fs::write(&status, "temp=4000\n").unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let server = serve(
+            bind(&socket).unwrap(),
+            status.clone(),
+            |line| line == "pause",
+            tx,
+        );
+
+        // A client that never sends its line doesn't hold up the others
+        let _silent = UnixStream::connect(&socket).unwrap();
+        assert_eq!(send(&socket, "pause").unwrap(), "ok\n");
+        assert_eq!(rx.recv_timeout(REPLY_TIMEOUT).unwrap(), "pause");
+        assert_eq!(send(&socket, "status").unwrap(), "temp=4000\n");
+        assert!(send(&socket, "explode").unwrap().starts_with("error:"));
+        assert!(rx.try_recv().is_err());
+
+        // A second daemon can't take over a live socket
+        assert_eq!(bind(&socket).unwrap_err().kind(), io::ErrorKind::AddrInUse);
+
+        drop(server);
+        assert!(!socket.exists());
+        let _ = fs::remove_file(&status);
+    }
+
+    #[test]
+    fn stale_socket_is_replaced() {
+        let socket = temp_path("stale.sock");
+        // A socket file nobody listens on, as a crashed daemon leaves behind
+        drop(bind(&socket).unwrap());
+        assert!(socket.exists());
+        assert!(send(&socket, "pause").is_err());
+
+        let listener = bind(&socket).expect("stale socket replaced");
+        drop(listener);
+        let _ = fs::remove_file(&socket);
+    }
+}
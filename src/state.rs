@@ -1,14 +1,36 @@
+use crate::config;
 use crate::transition;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    ToDay,
+    ToNight,
+}
+
+impl Direction {
+    /// Direction of a transition that ends at `target_temp`.
+    pub const fn towards(target_temp: u16, temperature: &config::Temperature) -> Self {
+        if target_temp == temperature.night && target_temp != temperature.day {
+            Self::ToNight
+        } else {
+            Self::ToDay
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct State {
     pub transition_start_temp: u16,
     pub transition_start_timestamp: u64,
     pub elapsed_seconds: u64,
     pub target_temp: u16,
+    /// Absent in state files written before directions were recorded.
+    #[serde(default)]
+    pub direction: Option<Direction>,
 }
 
 impl State {
@@ -28,6 +50,13 @@ impl State {
         fs::write(&path, content)
     }
 
+    /// The saved transition's direction, inferred from its target for older
+    /// state files.
+    pub fn direction(&self, temperature: &config::Temperature) -> Direction {
+        self.direction
+            .unwrap_or_else(|| Direction::towards(self.target_temp, temperature))
+    }
+
     pub fn age_seconds(&self) -> u64 {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -37,44 +66,122 @@ impl State {
     }
 }
 
+static HOME_WARNING: std::sync::Once = std::sync::Once::new();
+
 pub fn expand_path(path: &str) -> Option<PathBuf> {
-    if path.starts_with('~') {
-        dirs::home_dir().map(|home| home.join(&path[2..]))
-    } else {
-        Some(PathBuf::from(path))
+    let cache_home = std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from);
+    let expanded = expand_path_with(path, dirs::home_dir(), cache_home);
+    if expanded.is_none() {
+        HOME_WARNING.call_once(|| {
+            log::warn!(
+                "Cannot expand '{path}': no home directory; set an absolute path in the config"
+            );
+        });
+    }
+    expanded
+}
+
+/// Expands a leading `~` using `home`. Without a home directory, `~/.cache/...`
+/// falls back to `cache_home` (`$XDG_CACHE_HOME`); other `~` paths can't be
+/// expanded.
+fn expand_path_with(
+    path: &str,
+    home: Option<PathBuf>,
+    cache_home: Option<PathBuf>,
+) -> Option<PathBuf> {
+    let Some(rest) = path.strip_prefix('~') else {
+        return Some(PathBuf::from(path));
+    };
+    let rest = rest.trim_start_matches('/');
+
+    if let Some(home) = home {
+        return Some(home.join(rest));
+    }
+
+    let cached = rest.strip_prefix(".cache/")?;
+    cache_home
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join(cached))
+}
+
+/// Appends `line` to the history file at `path`, keeping only the last
+/// `max_lines` lines.
+pub fn append_history(path: &str, line: &str, max_lines: usize) -> Result<(), std::io::Error> {
+    let path = expand_path(path)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid path"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let mut lines: Vec<&str> = existing.lines().chain(std::iter::once(line)).collect();
+    let excess = lines.len().saturating_sub(max_lines);
+    lines.drain(..excess);
+
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    fs::write(&path, content)
+}
+
+/// Index of the last applied `candela cycle` step, if one was recorded.
+pub fn load_cycle_index(path: &str) -> Option<usize> {
+    let path = expand_path(path)?;
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+pub fn save_cycle_index(path: &str, index: usize) -> Result<(), std::io::Error> {
+    let path = expand_path(path)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid path"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, format!("{index}\n"))
+}
+
+/// The step after `last`, wrapping around `len` steps. Starts at the first
+/// step when nothing (or an out-of-range index) was recorded.
+pub const fn next_cycle_index(last: Option<usize>, len: usize) -> usize {
+    match last {
+        Some(index) if index + 1 < len => index + 1,
+        _ => 0,
     }
 }
 
 #[allow(
     clippy::cast_precision_loss,
-    clippy::cast_possible_wrap,
-    clippy::cast_possible_truncation,
-    clippy::cast_sign_loss,
-    clippy::cast_lossless,
-    reason = "temperature values are small enough that casts between u16/i16/f64 are safe"
+    reason = "elapsed/duration seconds are far below f64's exact integer range"
 )]
 pub fn calculate_temperature_from_state(
     state: &State,
     transition_duration_seconds: u64,
-    easing: &str,
+    config: &config::Transition,
 ) -> u16 {
     if state.elapsed_seconds >= transition_duration_seconds {
         return state.target_temp;
     }
 
     let progress = state.elapsed_seconds as f64 / transition_duration_seconds as f64;
-    let eased_progress = transition::apply_easing(progress, easing);
-
-    let temp_range = state.target_temp as i16 - state.transition_start_temp as i16;
-    let temp_delta = (temp_range as f64 * eased_progress) as i16;
-
-    (state.transition_start_temp as i16 + temp_delta) as u16
+    transition::temperature_at(
+        state.transition_start_temp,
+        state.target_temp,
+        progress,
+        config,
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn eased(easing: &str) -> config::Transition {
+        config::Transition {
+            easing: easing.to_string(),
+            ..config::Transition::default()
+        }
+    }
+
     #[test]
     fn calculate_temperature_uses_saved_state_mid_transition() {
         let state = State {
@@ -82,9 +189,10 @@ mod tests {
             transition_start_timestamp: 0,
             elapsed_seconds: 1800,
             target_temp: 1500,
+            direction: Some(Direction::ToNight),
         };
 
-        let temp = calculate_temperature_from_state(&state, 3600, "linear");
+        let temp = calculate_temperature_from_state(&state, 3600, &eased("linear"));
 
         assert_eq!(temp, 4000);
     }
@@ -96,13 +204,107 @@ mod tests {
             transition_start_timestamp: 0,
             elapsed_seconds: 4000,
             target_temp: 1500,
+            direction: Some(Direction::ToNight),
         };
 
-        let temp = calculate_temperature_from_state(&state, 3600, "linear");
+        let temp = calculate_temperature_from_state(&state, 3600, &eased("linear"));
 
         assert_eq!(temp, 1500);
     }
 
+    #[test]
+    fn legacy_state_without_direction_infers_it() {
+        let legacy = "transition_start_temp = 1500\ntransition_start_timestamp = 0\n\
+                      elapsed_seconds = 60\ntarget_temp = 6500\n";
+        let state: State = toml::from_str(legacy).unwrap();
+        let temperature = config::Temperature::default();
+
+        assert_eq!(state.direction, None);
+        assert_eq!(state.direction(&temperature), Direction::ToDay);
+    }
+
+    #[test]
+    fn recorded_direction_round_trips() {
+        let state = State {
+            transition_start_temp: 6500,
+            transition_start_timestamp: 0,
+            elapsed_seconds: 60,
+            target_temp: 1500,
+            direction: Some(Direction::ToNight),
+        };
+
+        let restored: State = toml::from_str(&toml::to_string(&state).unwrap()).unwrap();
+
+        assert_eq!(restored.direction, Some(Direction::ToNight));
+    }
+
+    #[test]
+    fn expand_path_uses_home() {
+        let home = Some(PathBuf::from("/home/user"));
+
+        assert_eq!(
+            expand_path_with("~/.cache/candela/state.toml", home, None),
+            Some(PathBuf::from("/home/user/.cache/candela/state.toml"))
+        );
+        assert_eq!(
+            expand_path_with("/var/lib/state.toml", None, None),
+            Some(PathBuf::from("/var/lib/state.toml"))
+        );
+    }
+
+    #[test]
+    fn expand_path_without_home_falls_back_to_xdg_cache() {
+        let cache = Some(PathBuf::from("/run/cache"));
+
+        assert_eq!(
+            expand_path_with("~/.cache/candela/state.toml", None, cache.clone()),
+            Some(PathBuf::from("/run/cache/candela/state.toml"))
+        );
+        assert_eq!(expand_path_with("~/candela/state.toml", None, cache), None);
+        assert_eq!(
+            expand_path_with("~/.cache/candela/state.toml", None, None),
+            None
+        );
+    }
+
+    #[test]
+    fn append_history_rotates_to_max_lines() {
+        let path = std::env::temp_dir().join(format!("candela-history-{}.csv", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let _ = fs::remove_file(&path);
+
+        for temp in [6500, 5000, 4000, 3000] {
+            append_history(&path, &format!("0,{temp},day"), 3).unwrap();
+        }
+
+        let content = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(content, "0,5000,day\n0,4000,day\n0,3000,day\n");
+    }
+
+    #[test]
+    fn cycle_index_advances_and_wraps_across_invocations() {
+        let path = std::env::temp_dir().join(format!("candela-cycle-{}", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let _ = fs::remove_file(&path);
+        let steps = [6500, 4000, 2700, 1900];
+
+        let mut applied = Vec::new();
+        for _ in 0..6 {
+            let index = next_cycle_index(load_cycle_index(&path), steps.len());
+            save_cycle_index(&path, index).unwrap();
+            applied.push(steps[index]);
+        }
+
+        let _ = fs::remove_file(&path);
+        assert_eq!(applied, [6500, 4000, 2700, 1900, 6500, 4000]);
+    }
+
+    #[test]
+    fn stale_cycle_index_restarts_at_first_step() {
+        assert_eq!(next_cycle_index(Some(7), 3), 0);
+    }
+
     #[test]
     fn easing_applied_for_ease_in() {
         let state = State {
@@ -110,11 +312,35 @@ mod tests {
             transition_start_timestamp: 0,
             elapsed_seconds: 1800,
             target_temp: 1500,
+            direction: Some(Direction::ToNight),
         };
 
-        let temp = calculate_temperature_from_state(&state, 3600, "ease_in");
+        let temp = calculate_temperature_from_state(&state, 3600, &eased("ease_in"));
 
         // ease_in at 0.5 progress -> eased 0.25
         assert_eq!(temp, 5250);
     }
+
+    #[test]
+    fn resumed_sine_transition_matches_the_live_curve() {
+        let state = State {
+            transition_start_temp: 6500,
+            transition_start_timestamp: 0,
+            elapsed_seconds: 900,
+            target_temp: 1500,
+            direction: Some(Direction::ToNight),
+        };
+        let mut config = config::Config::default();
+        config.transition.easing = "sine".to_string();
+        let mut live = transition::Transition::new_with_temp(config, 6500);
+        let seconds = std::time::Duration::from_secs;
+        let now = std::time::Instant::now();
+        live.align_with_schedule(6500, 1500, seconds(900), seconds(3600), now);
+
+        let resumed = calculate_temperature_from_state(&state, 3600, &eased("sine"));
+
+        assert_eq!(resumed, live.current_temperature());
+        // Not the linear value the resume path used to snap to
+        assert_ne!(resumed, 5250);
+    }
 }
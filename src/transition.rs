@@ -1,6 +1,11 @@
-use crate::config::Config;
+use crate::config::{Config, StepRounding, Temperature, TEMPERATURE_RANGE};
+use crate::hyprctl;
+use serde::Serialize;
 
-#[allow(clippy::struct_field_names, reason = "fields mirror the domain terminology")]
+#[allow(
+    clippy::struct_field_names,
+    reason = "fields mirror the domain terminology"
+)]
 pub struct Transition {
     config: Config,
     current_temperature: u16,
@@ -9,31 +14,28 @@ pub struct Transition {
     transition_start_timestamp: u64,
     phase_start_time: std::time::Instant,
     in_transition: bool,
+    duration: std::time::Duration,
+    forced_progress: Option<f64>,
 }
 
 impl Transition {
     pub fn new_with_temp(config: Config, initial_temp: u16) -> Self {
         Self {
-            config,
             current_temperature: initial_temp,
             target_temperature: initial_temp,
             transition_start_temp: initial_temp,
             transition_start_timestamp: current_unix_timestamp(),
             phase_start_time: std::time::Instant::now(),
             in_transition: false,
+            duration: duration_towards(&config, initial_temp, initial_temp),
+            forced_progress: None,
+            config,
         }
     }
 
-    #[allow(
-        clippy::cast_possible_wrap,
-        clippy::cast_possible_truncation,
-        clippy::cast_sign_loss,
-        clippy::cast_lossless,
-        reason = "temperature values are small enough that casts between u16/i16/f64 are safe"
-    )]
-    pub fn update(&mut self, target_temp: u16) {
-        let duration =
-            std::time::Duration::from_secs(60 * u64::from(self.config.transition.duration_minutes));
+    pub fn update(&mut self, target_temp: u16, now: std::time::Instant) {
+        let duration = duration_towards(&self.config, self.current_temperature, target_temp);
+        self.duration = duration;
 
         if duration.is_zero() {
             self.current_temperature = target_temp;
@@ -54,12 +56,12 @@ impl Transition {
         if !self.in_transition || self.target_temperature != target_temp {
             self.transition_start_temp = self.current_temperature;
             self.target_temperature = target_temp;
-            self.phase_start_time = std::time::Instant::now();
+            self.phase_start_time = now;
             self.transition_start_timestamp = current_unix_timestamp();
             self.in_transition = true;
         }
 
-        let elapsed = self.phase_start_time.elapsed();
+        let elapsed = now.saturating_duration_since(self.phase_start_time);
 
         if elapsed >= duration {
             self.current_temperature = self.target_temperature;
@@ -68,83 +70,108 @@ impl Transition {
         }
 
         let progress = elapsed.as_secs_f64() / duration.as_secs_f64();
-        let eased_progress = self.apply_easing(progress);
-
-        let temp_range = self.target_temperature as i16 - self.transition_start_temp as i16;
-        let temp_delta = (temp_range as f64 * eased_progress) as i16;
-
-        self.current_temperature = (self.transition_start_temp as i16 + temp_delta) as u16;
+        self.current_temperature = temperature_at(
+            self.transition_start_temp,
+            self.target_temperature,
+            progress,
+            &self.config.transition,
+        );
     }
 
-    #[allow(
-        clippy::cast_possible_wrap,
-        clippy::cast_possible_truncation,
-        clippy::cast_sign_loss,
-        clippy::cast_lossless,
-        reason = "temperature values are small enough that casts between u16/i16/f64 are safe"
-    )]
     pub fn align_with_schedule(
         &mut self,
         start_temp: u16,
         target_temp: u16,
         elapsed: std::time::Duration,
+        duration: std::time::Duration,
+        now: std::time::Instant,
     ) {
-        let duration =
-            std::time::Duration::from_secs(60 * u64::from(self.config.transition.duration_minutes));
+        self.duration = duration;
 
         if duration.is_zero() {
             self.current_temperature = target_temp;
             self.target_temperature = target_temp;
             self.transition_start_temp = start_temp;
             self.transition_start_timestamp = current_unix_timestamp();
-            self.phase_start_time = std::time::Instant::now();
+            self.phase_start_time = now;
             self.in_transition = false;
             return;
         }
 
-        let clamped_elapsed = if elapsed > duration { duration } else { elapsed };
+        let clamped_elapsed = if elapsed > duration {
+            duration
+        } else {
+            elapsed
+        };
         let progress = clamped_elapsed.as_secs_f64() / duration.as_secs_f64();
-        let eased_progress = self.apply_easing(progress);
-
-        let temp_range = target_temp as i16 - start_temp as i16;
-        let temp_delta = (temp_range as f64 * eased_progress) as i16;
 
-        self.current_temperature = (start_temp as i16 + temp_delta) as u16;
+        self.current_temperature =
+            temperature_at(start_temp, target_temp, progress, &self.config.transition);
         self.transition_start_temp = start_temp;
         self.target_temperature = target_temp;
-        self.phase_start_time = std::time::Instant::now()
-            .checked_sub(clamped_elapsed)
-            .unwrap_or_else(std::time::Instant::now);
-        self.transition_start_timestamp = current_unix_timestamp().saturating_sub(clamped_elapsed.as_secs());
+        self.phase_start_time = now.checked_sub(clamped_elapsed).unwrap_or(now);
+        self.transition_start_timestamp =
+            current_unix_timestamp().saturating_sub(clamped_elapsed.as_secs());
         self.in_transition = clamped_elapsed < duration;
     }
 
-    fn apply_easing(&self, t: f64) -> f64 {
-        apply_easing(t, &self.config.transition.easing)
+    /// Jumps straight to `temp`, abandoning any in-flight transition.
+    pub fn snap_to(&mut self, temp: u16) {
+        self.current_temperature = temp;
+        self.target_temperature = temp;
+        self.transition_start_temp = temp;
+        self.transition_start_timestamp = current_unix_timestamp();
+        self.phase_start_time = std::time::Instant::now();
+        self.in_transition = false;
+    }
+
+    /// Pins the current transition at `progress` (0.0–1.0), overriding
+    /// time-based progress until released with `None`. Releasing snaps straight
+    /// back to the time-based value.
+    pub fn force_progress(&mut self, progress: Option<f64>) {
+        self.forced_progress = progress.map(|p| p.clamp(0.0, 1.0));
     }
 
     pub fn progress(&self) -> f64 {
+        if let Some(progress) = self.forced_progress {
+            return progress;
+        }
+
         if !self.in_transition {
             return 1.0;
         }
 
         let elapsed = self.phase_start_time.elapsed();
-        let duration =
-            std::time::Duration::from_secs(60 * u64::from(self.config.transition.duration_minutes));
 
-        if duration.is_zero() {
+        if self.duration.is_zero() {
             return 1.0;
         }
 
-        if elapsed >= duration {
+        if elapsed >= self.duration {
             1.0
         } else {
-            elapsed.as_secs_f64() / duration.as_secs_f64()
+            elapsed.as_secs_f64() / self.duration.as_secs_f64()
+        }
+    }
+
+    /// Time left until the target is reached; zero outside a transition.
+    pub fn remaining(&self) -> std::time::Duration {
+        if !self.in_transition {
+            return std::time::Duration::ZERO;
         }
+        self.duration.mul_f64(1.0 - self.progress())
     }
 
-    pub const fn current_temperature(&self) -> u16 {
-        self.current_temperature
+    pub fn current_temperature(&self) -> u16 {
+        self.forced_progress
+            .map_or(self.current_temperature, |progress| {
+                temperature_at(
+                    self.transition_start_temp,
+                    self.target_temperature,
+                    progress,
+                    &self.config.transition,
+                )
+            })
     }
 
     pub const fn target_temperature(&self) -> u16 {
@@ -158,6 +185,214 @@ impl Transition {
     pub const fn transition_start_timestamp(&self) -> u64 {
         self.transition_start_timestamp
     }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            start_temp: self.transition_start_temp,
+            target: self.target_temperature,
+            start_timestamp: self.transition_start_timestamp,
+            progress: self.progress(),
+            in_transition: self.in_transition,
+        }
+    }
+}
+
+/// The daemon's transition fields, published through the status file for
+/// `candela dump-state`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub start_temp: u16,
+    pub target: u16,
+    pub start_timestamp: u64,
+    pub progress: f64,
+    pub in_transition: bool,
+}
+
+impl Snapshot {
+    /// Status file fields the status doesn't already carry (`target` and
+    /// `progress` are written as `target=`/`progress=`).
+    pub fn status_fields(&self) -> [(&'static str, serde_json::Value); 3] {
+        [
+            ("transition_start_temp", self.start_temp.into()),
+            ("transition_start_timestamp", self.start_timestamp.into()),
+            ("in_transition", self.in_transition.into()),
+        ]
+    }
+
+    /// Reads a snapshot back from status file content; `None` when the
+    /// daemon that wrote it predates these fields.
+    pub fn from_status(content: &str) -> Option<Self> {
+        let field = |key: &str| {
+            content.lines().find_map(|line| {
+                let (name, value) = line.split_once('=')?;
+                (name == key).then_some(value)
+            })
+        };
+        Some(Self {
+            start_temp: field("transition_start_temp")?.parse().ok()?,
+            target: field("target")?.parse().ok()?,
+            start_timestamp: field("transition_start_timestamp")?.parse().ok()?,
+            progress: field("progress")?.parse().ok()?,
+            in_transition: field("in_transition")?.parse().ok()?,
+        })
+    }
+}
+
+/// The configured length of a transition from `from` to `to`: `to_night`
+/// when it dims, otherwise `to_day`.
+fn duration_towards(config: &Config, from: u16, to: u16) -> std::time::Duration {
+    let minutes = config.transition.duration_minutes;
+    let minutes = if interpolation_temperature(to) < interpolation_temperature(from) {
+        minutes.to_night()
+    } else {
+        minutes.to_day()
+    };
+    std::time::Duration::from_secs(60 * u64::from(minutes))
+}
+
+/// Maps the `OFF` sentinel to the identity temperature so transitions into or
+/// out of "off" ramp from/to neutral white rather than towards 0K.
+pub const fn interpolation_temperature(kelvin: u16) -> u16 {
+    if kelvin == hyprctl::OFF {
+        hyprctl::IDENTITY_TEMPERATURE
+    } else {
+        kelvin
+    }
+}
+
+/// A 0-100 level (backlight, gamma) that follows the displayed `temp`:
+/// `night` at the night temperature, `day` at the day temperature and linear
+/// in between, so it shares the temperature's eased transition.
+pub fn follow_temperature(day: u8, night: u8, temp: u16, temperature: &Temperature) -> u8 {
+    let kelvin = |k| f64::from(interpolation_temperature(k));
+    let (day_kelvin, night_kelvin) = (kelvin(temperature.day), kelvin(temperature.night));
+    let fraction = if (day_kelvin - night_kelvin).abs() < f64::EPSILON {
+        1.0
+    } else {
+        ((kelvin(temp) - night_kelvin) / (day_kelvin - night_kelvin)).clamp(0.0, 1.0)
+    };
+    let (day, night) = (f64::from(day), f64::from(night));
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "clamped to 0-100 before the cast"
+    )]
+    let level = (day - night)
+        .mul_add(fraction, night)
+        .round()
+        .clamp(0.0, 100.0) as u8;
+    level
+}
+
+/// Quantizes `kelvin` to a multiple of `step` in the given direction. A step of
+/// 0 disables quantization.
+pub const fn quantize(kelvin: u16, step: u16, rounding: StepRounding) -> u16 {
+    if step == 0 {
+        return kelvin;
+    }
+
+    let floor = kelvin - kelvin % step;
+    let ceil = if floor == kelvin {
+        kelvin
+    } else {
+        floor.saturating_add(step)
+    };
+
+    match rounding {
+        StepRounding::Floor => floor,
+        StepRounding::Ceil => ceil,
+        StepRounding::Nearest => {
+            if kelvin - floor < ceil - kelvin {
+                floor
+            } else {
+                ceil
+            }
+        }
+    }
+}
+
+/// Temperature reached `progress` (0.0–1.0) of the way from `start` to
+/// `target` under the transition's easing (or lookup table), rounded to the
+/// nearest Kelvin. Progress at or past either end returns that endpoint
+/// exactly. With `auto_smooth_endpoints`, the eased progress is
+/// post-processed by `smooth_endpoints`.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "result is clamped to the u16 range before casting"
+)]
+pub fn temperature_at(
+    start: u16,
+    target: u16,
+    progress: f64,
+    config: &crate::config::Transition,
+) -> u16 {
+    if progress >= 1.0 {
+        return target;
+    }
+    if progress <= 0.0 {
+        return start;
+    }
+
+    let from = f64::from(interpolation_temperature(start));
+    let to = f64::from(interpolation_temperature(target));
+    let eased = if config.easing == "lut" {
+        apply_lut(progress, &config.easing_lut)
+    } else {
+        apply_easing(progress, &config.easing)
+    };
+    let eased = if config.auto_smooth_endpoints {
+        self::smooth_endpoints(progress, eased)
+    } else {
+        eased
+    };
+
+    (to - from)
+        .mul_add(eased, from)
+        .round()
+        .clamp(0.0, f64::from(u16::MAX)) as u16
+}
+
+/// Fraction of the transition at each end over which `smooth_endpoints` blends.
+const ENDPOINT_BLEND: f64 = 0.05;
+
+/// Blends eased progress `eased` (at raw progress `t`) into its endpoints with
+/// a smoothstep over the first and last `ENDPOINT_BLEND` of the transition, so
+/// the rate of change falls to zero at both ends instead of stopping abruptly.
+/// The curve is untouched in between, and stays monotonic if `eased` is.
+pub fn smooth_endpoints(t: f64, eased: f64) -> f64 {
+    let blend = |x: f64| apply_easing((x / ENDPOINT_BLEND).clamp(0.0, 1.0), "smooth");
+    if t < ENDPOINT_BLEND {
+        eased * blend(t)
+    } else if t > 1.0 - ENDPOINT_BLEND {
+        (1.0 - eased).mul_add(-blend(1.0 - t), 1.0)
+    } else {
+        eased
+    }
+}
+
+const NAMED_EASINGS: [&str; 12] = [
+    "linear",
+    "ease_in",
+    "ease_out",
+    "ease_in_out",
+    "ease_in_cubic",
+    "ease_out_cubic",
+    "ease_in_out_cubic",
+    "ease_in_expo",
+    "ease_out_expo",
+    "sine",
+    "smooth",
+    "smoother",
+];
+
+/// Whether `apply_easing` recognises `easing` (a named curve, a valid
+/// `cubic_bezier(...)` or `blend(...)`), rather than silently falling back to
+/// linear.
+pub fn is_known_easing(easing: &str) -> bool {
+    NAMED_EASINGS.contains(&easing)
+        || parse_cubic_bezier(easing).is_some()
+        || parse_blend(easing).is_some()
 }
 
 pub fn apply_easing(t: f64, easing: &str) -> f64 {
@@ -172,14 +407,101 @@ pub fn apply_easing(t: f64, easing: &str) -> f64 {
                 2.0f64.mul_add(-t, 4.0).mul_add(t, -1.0)
             }
         }
+        "ease_in_cubic" => t * t * t,
+        "ease_out_cubic" => 1.0 - (1.0 - t).powi(3),
+        "ease_in_out_cubic" => {
+            if t < 0.5 {
+                4.0 * t * t * t
+            } else {
+                1.0 - 2.0f64.mul_add(-t, 2.0).powi(3) / 2.0
+            }
+        }
+        // 2^(10t - 10) never quite reaches 0, so pin the start exactly
+        "ease_in_expo" => {
+            if t <= 0.0 {
+                0.0
+            } else {
+                10.0f64.mul_add(t, -10.0).exp2()
+            }
+        }
+        "ease_out_expo" => {
+            if t >= 1.0 {
+                1.0
+            } else {
+                1.0 - (-10.0 * t).exp2()
+            }
+        }
         "sine" => (1.0 - (t * std::f64::consts::PI).cos()) / 2.0,
         "smooth" => t * t * 2.0f64.mul_add(-t, 3.0),
         "smoother" => t * t * t * t.mul_add(6.0f64.mul_add(t, -15.0), 10.0),
-        _ => parse_cubic_bezier(easing)
-            .map_or(t, |[x1, y1, x2, y2]| eval_cubic_bezier(t, x1, y1, x2, y2)),
+        _ => {
+            if let Some((early, late)) = parse_blend(easing) {
+                let (early, late) = (apply_easing(t, early), apply_easing(t, late));
+                return t.mul_add(late - early, early);
+            }
+            parse_cubic_bezier(easing)
+                .map_or(t, |[x1, y1, x2, y2]| eval_cubic_bezier(t, x1, y1, x2, y2))
+        }
     }
 }
 
+/// Eased progress under a `[progress, kelvin]` lookup table: the table's
+/// temperature at `t`, interpolated linearly between points and normalised so
+/// the first point maps to 0.0 and the last to 1.0. This lets one table shape
+/// both directions. Falls back to linear for tables `validate_lut` rejects.
+pub fn apply_lut(t: f64, lut: &[(f64, f64)]) -> f64 {
+    if validate_lut(lut).is_err() {
+        return t;
+    }
+    let (first, last) = (lut[0].1, lut[lut.len() - 1].1);
+    let value = lut
+        .windows(2)
+        .find(|pair| t <= pair[1].0)
+        .map_or(last, |pair| {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+            (y1 - y0).mul_add((t - x0) / (x1 - x0), y0)
+        });
+    (value - first) / (last - first)
+}
+
+/// Checks a lookup table has at least two points, strictly increasing
+/// progress spanning 0.0 to 1.0, temperatures within hyprsunset's range, and
+/// different first and last temperatures.
+pub fn validate_lut(lut: &[(f64, f64)]) -> Result<(), String> {
+    if lut.len() < 2 {
+        return Err("easing_lut needs at least two points".to_string());
+    }
+    let (min, max) = (*TEMPERATURE_RANGE.start(), *TEMPERATURE_RANGE.end());
+    if let Some((_, kelvin)) = lut
+        .iter()
+        .find(|(_, kelvin)| !(f64::from(min)..=f64::from(max)).contains(kelvin))
+    {
+        return Err(format!(
+            "easing_lut temperature {kelvin} is outside {min}-{max}K"
+        ));
+    }
+    if lut.windows(2).any(|pair| pair[1].0 <= pair[0].0) {
+        return Err("easing_lut progress values must be strictly increasing".to_string());
+    }
+    let ((first_x, first_y), (last_x, last_y)) = (lut[0], lut[lut.len() - 1]);
+    if first_x.abs() > f64::EPSILON || (last_x - 1.0).abs() > f64::EPSILON {
+        return Err("easing_lut must span progress 0.0 to 1.0".to_string());
+    }
+    if (last_y - first_y).abs() < f64::EPSILON {
+        return Err("easing_lut first and last temperatures must differ".to_string());
+    }
+    Ok(())
+}
+
+/// `blend(a, b)`: two named easings, crossfaded linearly by progress from `a`
+/// at the start to `b` at the end.
+fn parse_blend(s: &str) -> Option<(&str, &str)> {
+    let inner = s.trim().strip_prefix("blend(")?.strip_suffix(')')?;
+    let (early, late) = inner.split_once(',')?;
+    let (early, late) = (early.trim(), late.trim());
+    (NAMED_EASINGS.contains(&early) && NAMED_EASINGS.contains(&late)).then_some((early, late))
+}
+
 fn parse_cubic_bezier(s: &str) -> Option<[f64; 4]> {
     let inner = s.trim().strip_prefix("cubic_bezier(")?.strip_suffix(')')?;
     let parts: Vec<&str> = inner.split(',').collect();
@@ -222,7 +544,7 @@ fn eval_cubic_bezier(x: f64, x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
     ay.mul_add(t, by).mul_add(t, cy) * t
 }
 
-fn current_unix_timestamp() -> u64 {
+pub fn current_unix_timestamp() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
@@ -232,15 +554,27 @@ fn current_unix_timestamp() -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Config;
-    use std::time::Duration;
+    use crate::config::{Config, StepRounding};
+    use crate::hyprctl;
+    use std::time::{Duration, Instant};
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn curve(easing: &str, lut: &[(f64, f64)], smooth: bool) -> crate::config::Transition {
+        crate::config::Transition {
+            easing: easing.to_string(),
+            easing_lut: lut.to_vec(),
+            auto_smooth_endpoints: smooth,
+            ..crate::config::Transition::default()
+        }
+    }
 
     #[test]
     fn update_sets_progress_complete_when_at_target() {
         let config = Config::default();
         let mut transition = Transition::new_with_temp(config, 1500);
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
 
         assert_eq!(transition.current_temperature(), 1500);
         assert_eq!(transition.progress(), 1.0);
@@ -250,32 +584,49 @@ mod tests {
     #[test]
     fn update_completes_after_elapsed_duration() {
         let mut config = Config::default();
-        config.transition.duration_minutes = 1;
+        config.transition.duration_minutes = 1.into();
         let mut transition = Transition::new_with_temp(config, 6500);
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
         transition.phase_start_time = std::time::Instant::now() - Duration::from_secs(60);
         transition.in_transition = true;
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
 
         assert_eq!(transition.current_temperature(), 1500);
         assert_eq!(transition.progress(), 1.0);
         assert!(!transition.in_transition);
     }
 
+    #[test]
+    fn remaining_counts_down_to_zero() {
+        let mut config = Config::default();
+        config.transition.duration_minutes = 1.into();
+        let mut transition = Transition::new_with_temp(config, 6500);
+        assert_eq!(transition.remaining(), Duration::ZERO);
+
+        transition.update(1500, Instant::now());
+        transition.force_progress(Some(0.25));
+        assert_eq!(transition.remaining(), Duration::from_secs(45));
+
+        transition.force_progress(None);
+        transition.phase_start_time = std::time::Instant::now() - Duration::from_secs(60);
+        transition.update(1500, Instant::now());
+        assert_eq!(transition.remaining(), Duration::ZERO);
+    }
+
     #[test]
     fn easing_linear_at_halfway() {
         let mut config = Config::default();
-        config.transition.duration_minutes = 1;
+        config.transition.duration_minutes = 1.into();
         config.transition.easing = "linear".to_string();
         let mut transition = Transition::new_with_temp(config, 6500);
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
         transition.phase_start_time = std::time::Instant::now() - Duration::from_secs(30);
         transition.in_transition = true;
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
 
         assert_eq!(transition.current_temperature(), 4000);
     }
@@ -283,15 +634,15 @@ mod tests {
     #[test]
     fn easing_ease_in_at_halfway() {
         let mut config = Config::default();
-        config.transition.duration_minutes = 1;
+        config.transition.duration_minutes = 1.into();
         config.transition.easing = "ease_in".to_string();
         let mut transition = Transition::new_with_temp(config, 6500);
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
         transition.phase_start_time = std::time::Instant::now() - Duration::from_secs(30);
         transition.in_transition = true;
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
 
         assert_eq!(transition.current_temperature(), 5250);
     }
@@ -299,15 +650,15 @@ mod tests {
     #[test]
     fn easing_ease_out_at_halfway() {
         let mut config = Config::default();
-        config.transition.duration_minutes = 1;
+        config.transition.duration_minutes = 1.into();
         config.transition.easing = "ease_out".to_string();
         let mut transition = Transition::new_with_temp(config, 6500);
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
         transition.phase_start_time = std::time::Instant::now() - Duration::from_secs(30);
         transition.in_transition = true;
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
 
         assert_eq!(transition.current_temperature(), 2750);
     }
@@ -315,15 +666,15 @@ mod tests {
     #[test]
     fn easing_ease_in_out_at_halfway() {
         let mut config = Config::default();
-        config.transition.duration_minutes = 1;
+        config.transition.duration_minutes = 1.into();
         config.transition.easing = "ease_in_out".to_string();
         let mut transition = Transition::new_with_temp(config, 6500);
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
         transition.phase_start_time = std::time::Instant::now() - Duration::from_secs(30);
         transition.in_transition = true;
 
-        transition.update(1500);
+        transition.update(1500, Instant::now());
 
         assert_eq!(transition.current_temperature(), 4000);
     }
@@ -331,13 +682,247 @@ mod tests {
     #[test]
     fn align_with_schedule_sets_expected_temperature() {
         let mut config = Config::default();
-        config.transition.duration_minutes = 60;
+        config.transition.duration_minutes = 60.into();
+        config.transition.easing = "linear".to_string();
+        let mut transition = Transition::new_with_temp(config, 6500);
+
+        let elapsed = Duration::from_secs(1800);
+        transition.align_with_schedule(6500, 1500, elapsed, HOUR, Instant::now());
+
+        assert_eq!(transition.current_temperature(), 4000);
+    }
+
+    #[test]
+    fn forced_progress_overrides_time_based_temperature() {
+        let mut config = Config::default();
+        config.transition.duration_minutes = 60.into();
         config.transition.easing = "linear".to_string();
         let mut transition = Transition::new_with_temp(config, 6500);
+        let elapsed = Duration::from_secs(900);
+        transition.align_with_schedule(6500, 1500, elapsed, HOUR, Instant::now());
+        assert_eq!(transition.current_temperature(), 5250);
+
+        transition.force_progress(Some(0.5));
+        assert_eq!(transition.current_temperature(), 4000);
+        assert!((transition.progress() - 0.5).abs() < f64::EPSILON);
+
+        transition.force_progress(None);
+        assert_eq!(transition.current_temperature(), 5250);
+    }
 
-        transition.align_with_schedule(6500, 1500, Duration::from_secs(1800));
+    #[test]
+    fn transition_to_off_ramps_towards_identity() {
+        let mut config = Config::default();
+        config.transition.duration_minutes = 60.into();
+        config.transition.easing = "linear".to_string();
+        let mut transition = Transition::new_with_temp(config, 1500);
 
+        let half = Duration::from_secs(1800);
+        transition.align_with_schedule(1500, hyprctl::OFF, half, HOUR, Instant::now());
         assert_eq!(transition.current_temperature(), 4000);
+
+        transition.align_with_schedule(1500, hyprctl::OFF, HOUR, HOUR, Instant::now());
+        assert_eq!(transition.current_temperature(), hyprctl::OFF);
+    }
+
+    #[test]
+    fn temperature_at_endpoints() {
+        assert_eq!(
+            temperature_at(6500, 1500, 0.0, &curve("smooth", &[], false)),
+            6500
+        );
+        assert_eq!(
+            temperature_at(6500, 1500, 1.0, &curve("smooth", &[], false)),
+            1500
+        );
+        assert_eq!(
+            temperature_at(6500, 1500, 1.5, &curve("linear", &[], false)),
+            1500
+        );
+    }
+
+    #[test]
+    fn temperature_at_applies_easing() {
+        assert_eq!(
+            temperature_at(6500, 1500, 0.5, &curve("linear", &[], false)),
+            4000
+        );
+        assert_eq!(
+            temperature_at(6500, 1500, 0.5, &curve("ease_in", &[], false)),
+            5250
+        );
+        assert_eq!(
+            temperature_at(6500, 1500, 0.5, &curve("ease_out", &[], false)),
+            2750
+        );
+        assert_eq!(
+            temperature_at(1500, 6500, 0.25, &curve("linear", &[], false)),
+            2750
+        );
+        assert_eq!(
+            temperature_at(6500, 1500, 0.5, &curve("smooth", &[], false)),
+            4000
+        );
+    }
+
+    #[test]
+    fn temperature_at_rounds_to_nearest() {
+        // 6500 - 5000 * (1/3) = 4833.33...
+        assert_eq!(
+            temperature_at(6500, 1500, 1.0 / 3.0, &curve("linear", &[], false)),
+            4833
+        );
+        // 1500 + 5000 * (1/3) = 3166.67...
+        assert_eq!(
+            temperature_at(1500, 6500, 1.0 / 3.0, &curve("linear", &[], false)),
+            3167
+        );
+    }
+
+    #[test]
+    fn lut_interpolates_between_points() {
+        let lut = [(0.0, 6500.0), (0.3, 5000.0), (1.0, 1500.0)];
+
+        assert_eq!(
+            temperature_at(6500, 1500, 0.3, &curve("lut", &lut, false)),
+            5000
+        );
+        assert_eq!(
+            temperature_at(6500, 1500, 0.15, &curve("lut", &lut, false)),
+            5750
+        );
+        assert_eq!(
+            temperature_at(6500, 1500, 0.65, &curve("lut", &lut, false)),
+            3250
+        );
+        // Normalised, so the same table shapes the morning ramp
+        assert_eq!(
+            temperature_at(1500, 6500, 0.3, &curve("lut", &lut, false)),
+            3000
+        );
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_the_status_file() {
+        let mut transition = Transition::new_with_temp(Config::default(), 6500);
+        transition.update(1500, Instant::now());
+        transition.force_progress(Some(0.25));
+        let snapshot = transition.snapshot();
+        assert_eq!((snapshot.start_temp, snapshot.target), (6500, 1500));
+        assert!(snapshot.in_transition);
+
+        let lines = snapshot
+            .status_fields()
+            .map(|(key, value)| format!("{key}={value}"));
+        let status = format!(
+            "temp=5000\ntarget=1500\nprogress=0.25\n{}",
+            lines.join("\n")
+        );
+        assert_eq!(Snapshot::from_status(&status), Some(snapshot.clone()));
+        assert_eq!(
+            Snapshot::from_status("temp=5000\ntarget=1500\nprogress=0.25\n"),
+            None
+        );
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["start_temp"], 6500);
+        assert_eq!(json["in_transition"], true);
+        assert_eq!(json["progress"], 0.25);
+    }
+
+    #[test]
+    fn known_easings() {
+        assert!(is_known_easing("smooth"));
+        assert!(is_known_easing("cubic_bezier(0.25, 0.1, 0.25, 1.0)"));
+        assert!(!is_known_easing("bouncy"));
+        assert!(is_known_easing("blend(ease_in, sine)"));
+        assert!(!is_known_easing("blend(ease_in, bouncy)"));
+        assert!(!is_known_easing("blend(ease_in)"));
+        assert!(!is_known_easing("blend(ease_in, sine, linear)"));
+    }
+
+    #[test]
+    fn blend_crossfades_between_its_curves() {
+        for t in [0.1, 0.25, 0.5, 0.75, 0.9] {
+            let blended = apply_easing(t, "blend(ease_in, sine)");
+            let (early, late) = (apply_easing(t, "ease_in"), apply_easing(t, "sine"));
+            assert!(
+                blended >= early.min(late) && blended <= early.max(late),
+                "t={t}"
+            );
+        }
+        assert!(apply_easing(0.0, "blend(ease_in, sine)").abs() < f64::EPSILON);
+        assert!((apply_easing(1.0, "blend(ease_in, sine)") - 1.0).abs() < f64::EPSILON);
+        // Early on the first curve dominates, late on the second
+        let near = |t: f64, easing| {
+            (apply_easing(t, "blend(ease_in, ease_out)") - apply_easing(t, easing)).abs()
+        };
+        assert!(near(0.1, "ease_in") < near(0.1, "ease_out"));
+        assert!(near(0.9, "ease_out") < near(0.9, "ease_in"));
+    }
+
+    #[test]
+    fn lut_validation() {
+        assert!(validate_lut(&[(0.0, 6500.0), (1.0, 1500.0)]).is_ok());
+        assert!(validate_lut(&[(0.0, 6500.0)]).is_err());
+        assert!(
+            validate_lut(&[(0.0, 6500.0), (0.6, 4000.0), (0.4, 3000.0), (1.0, 1500.0)]).is_err()
+        );
+        assert!(validate_lut(&[(0.1, 6500.0), (1.0, 1500.0)]).is_err());
+        assert!(validate_lut(&[(0.0, 6500.0), (0.5, 1500.0), (1.0, 6500.0)]).is_err());
+        assert_eq!(
+            validate_lut(&[(0.0, 6500.0), (1.0, 500.0)]),
+            Err("easing_lut temperature 500 is outside 1000-20000K".to_string())
+        );
+        assert!(validate_lut(&[(0.0, f64::NAN), (1.0, 1500.0)]).is_err());
+        // Invalid tables fall back to linear
+        assert!((apply_lut(0.25, &[(0.0, 1.0)]) - 0.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn quantize_rounding_modes() {
+        assert_eq!(quantize(4020, 50, StepRounding::Nearest), 4000);
+        assert_eq!(quantize(4030, 50, StepRounding::Nearest), 4050);
+        assert_eq!(quantize(4025, 50, StepRounding::Nearest), 4050);
+        assert_eq!(quantize(4049, 50, StepRounding::Floor), 4000);
+        assert_eq!(quantize(4001, 50, StepRounding::Ceil), 4050);
+        assert_eq!(quantize(4050, 50, StepRounding::Ceil), 4050);
+    }
+
+    #[test]
+    fn quantize_zero_step_is_passthrough() {
+        assert_eq!(quantize(4023, 0, StepRounding::Floor), 4023);
+    }
+
+    #[test]
+    fn cubic_and_expo_easings_hit_their_endpoints_and_rise_monotonically() {
+        let easings = [
+            "ease_in_cubic",
+            "ease_out_cubic",
+            "ease_in_out_cubic",
+            "ease_in_expo",
+            "ease_out_expo",
+        ];
+        for easing in easings {
+            assert!(is_known_easing(easing), "{easing}");
+            assert!(apply_easing(0.0, easing).abs() < f64::EPSILON, "{easing}");
+            assert!(
+                (apply_easing(1.0, easing) - 1.0).abs() < f64::EPSILON,
+                "{easing}"
+            );
+            let samples: Vec<f64> = (0..=100)
+                .map(|i| apply_easing(f64::from(i) / 100.0, easing))
+                .collect();
+            assert!(
+                samples.windows(2).all(|pair| pair[1] >= pair[0]),
+                "{easing}"
+            );
+        }
+        assert!((apply_easing(0.5, "ease_in_cubic") - 0.125).abs() < f64::EPSILON);
+        assert!((apply_easing(0.5, "ease_out_cubic") - 0.875).abs() < f64::EPSILON);
+        assert!((apply_easing(0.5, "ease_in_out_cubic") - 0.5).abs() < f64::EPSILON);
+        assert!((apply_easing(0.5, "ease_in_expo") - 1.0 / 32.0).abs() < f64::EPSILON);
+        assert!((apply_easing(0.5, "ease_out_expo") - 31.0 / 32.0).abs() < f64::EPSILON);
     }
 
     #[test]
@@ -373,6 +958,31 @@ mod tests {
         assert!((apply_easing(0.5, "smoother") - 0.5).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn smooth_endpoints_flattens_the_ends_only() {
+        let rate = |t: f64, smooth: bool| {
+            let at = |t| {
+                let eased = apply_easing(t, "linear");
+                if smooth {
+                    smooth_endpoints(t, eased)
+                } else {
+                    eased
+                }
+            };
+            (at(t + 1e-4) - at(t)) / 1e-4
+        };
+
+        assert!(rate(0.0, true) < rate(0.0, false) / 10.0);
+        assert!(rate(1.0 - 1e-4, true) < rate(1.0 - 1e-4, false) / 10.0);
+        assert!((smooth_endpoints(0.5, 0.5) - 0.5).abs() < f64::EPSILON);
+        assert!(smooth_endpoints(0.0, 0.0).abs() < f64::EPSILON);
+        assert!((smooth_endpoints(1.0, 1.0) - 1.0).abs() < f64::EPSILON);
+        assert_eq!(
+            temperature_at(6500, 1500, 0.5, &curve("linear", &[], true)),
+            4000
+        );
+    }
+
     #[test]
     fn easing_curves_differ_at_quarter() {
         let sine = apply_easing(0.25, "sine");
@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the config file's modification time is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long the modification time has to stay put before a reload, so an
+/// editor's burst of writes causes a single reload.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watches `path` on a background thread, calling `on_change` once each burst
+/// of edits has settled.
+pub fn spawn(path: PathBuf, on_change: impl Fn() + Send + 'static) {
+    std::thread::spawn(move || {
+        let mut watcher = MtimeWatcher::new(modified(&path));
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            if watcher.poll(modified(&path), Instant::now(), DEBOUNCE) {
+                log::info!("Config file {} changed, reloading", path.display());
+                on_change();
+            }
+        }
+    });
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Debounced change detection over successive modification-time samples.
+struct MtimeWatcher {
+    /// Modification time the last reload (or startup) saw.
+    loaded: Option<SystemTime>,
+    /// The newest unreloaded modification time and when it was first seen.
+    pending: Option<(Option<SystemTime>, Instant)>,
+}
+
+impl MtimeWatcher {
+    const fn new(loaded: Option<SystemTime>) -> Self {
+        Self {
+            loaded,
+            pending: None,
+        }
+    }
+
+    /// Records `mtime` sampled at `now`; true when a change has held steady
+    /// for `debounce`. A missing file (mid-save) is just another change.
+    fn poll(&mut self, mtime: Option<SystemTime>, now: Instant, debounce: Duration) -> bool {
+        if mtime == self.loaded {
+            self.pending = None;
+            return false;
+        }
+        match self.pending {
+            Some((seen, since)) if seen == mtime => {
+                if now.duration_since(since) < debounce || mtime.is_none() {
+                    return false;
+                }
+                self.loaded = mtime;
+                self.pending = None;
+                true
+            }
+            _ => {
+                self.pending = Some((mtime, now));
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_change_triggers_one_reload_after_it_settles() {
+        let path = std::env::temp_dir().join(format!("candela-watch-{}.toml", std::process::id()));
+        std::fs::write(&path, "mode = \"auto\"\n").unwrap();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut watcher = MtimeWatcher::new(modified(&path));
+        assert!(!watcher.poll(modified(&path), at(0), DEBOUNCE));
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+        assert!(!watcher.poll(modified(&path), at(1000), DEBOUNCE));
+        assert!(!watcher.poll(modified(&path), at(1200), DEBOUNCE));
+        assert!(watcher.poll(modified(&path), at(1600), DEBOUNCE));
+        assert!(!watcher.poll(modified(&path), at(2600), DEBOUNCE));
+
+        // Rapid successive edits restart the debounce
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert!(!watcher.poll(modified(&path), at(3000), DEBOUNCE));
+        file.set_modified(SystemTime::now() + Duration::from_secs(15))
+            .unwrap();
+        assert!(!watcher.poll(modified(&path), at(3400), DEBOUNCE));
+        assert!(!watcher.poll(modified(&path), at(3600), DEBOUNCE));
+        assert!(watcher.poll(modified(&path), at(3900), DEBOUNCE));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_file_missing_mid_save_does_not_reload() {
+        let start = Instant::now();
+        let mut watcher = MtimeWatcher::new(Some(SystemTime::UNIX_EPOCH));
+        assert!(!watcher.poll(None, start, DEBOUNCE));
+        assert!(!watcher.poll(None, start + Duration::from_secs(5), DEBOUNCE));
+        // Back with the same modification time: nothing to reload
+        assert!(!watcher.poll(Some(SystemTime::UNIX_EPOCH), start, DEBOUNCE));
+    }
+}
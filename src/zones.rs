@@ -0,0 +1,216 @@
+use crate::config::Config;
+use crate::hyprctl;
+use crate::scheduler;
+use chrono::{DateTime, Local};
+use std::collections::HashSet;
+use std::process::Command;
+
+/// One `[[schedules]]` entry (e.g. room lights next to the screen): its own
+/// curve, ticked alongside the main schedule and applied through a command
+/// instead of hyprsunset.
+pub struct Zone {
+    pub name: String,
+    command: String,
+    schedule: scheduler::Schedule,
+    last_sent: Option<u16>,
+}
+
+/// A zone's temperature, target and transition progress at one tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    pub temp: u16,
+    pub target: u16,
+    pub progress: f64,
+}
+
+impl Reading {
+    /// Status file fields for zone `name`, keyed as `schedule.<name>.<field>`
+    /// so existing `key=value` readers skip them.
+    pub fn status_fields(&self, name: &str) -> [(String, serde_json::Value); 3] {
+        [
+            (format!("schedule.{name}.temp"), self.temp.into()),
+            (format!("schedule.{name}.target"), self.target.into()),
+            (format!("schedule.{name}.progress"), self.progress.into()),
+        ]
+    }
+}
+
+/// Builds the zones of `config.schedules`. Names must be unique, non-empty
+/// and free of `=`/`.` so they can key status fields.
+pub fn from_config(config: &Config) -> Result<Vec<Zone>, String> {
+    let mut names = HashSet::new();
+    config
+        .schedules
+        .iter()
+        .map(|entry| {
+            let name = &entry.name;
+            if name.is_empty() || name.contains(['=', '.']) || name.contains(char::is_whitespace) {
+                return Err(format!("schedule name {name:?} must be a non-empty word"));
+            }
+            if !names.insert(name.as_str()) {
+                return Err(format!("schedule name {name:?} is used twice"));
+            }
+            let schedule = scheduler::Schedule::new(entry.config(config))
+                .map_err(|e| format!("schedule {name:?}: {e}"))?;
+            Ok(Zone {
+                name: name.clone(),
+                command: entry.command.clone(),
+                schedule,
+                last_sent: None,
+            })
+        })
+        .collect()
+}
+
+impl Zone {
+    /// Where an undisturbed zone is at `now`.
+    pub fn reading(&self, now: DateTime<Local>) -> Reading {
+        Reading {
+            temp: self.schedule.temperature_at(now),
+            target: self.schedule.target_temperature_at(now),
+            progress: self.schedule.progress_at(now),
+        }
+    }
+
+    /// Whether the zone is partway through a transition at `now`.
+    pub fn is_transitioning(&self, now: DateTime<Local>) -> bool {
+        self.schedule.current_phase_at(now).is_transitioning()
+    }
+
+    /// When the zone next starts or finishes a transition after `now`.
+    pub fn next_phase_change(&self, now: DateTime<Local>) -> DateTime<Local> {
+        self.schedule.next_phase_change(now)
+    }
+
+    /// Runs the zone's command for `temp` unless it was the last one sent
+    /// (with `optimize_updates`). The command runs with `CANDELA_SCHEDULE`
+    /// and `CANDELA_TEMPERATURE` set, under the same concurrency limit and
+    /// timeout as hyprctl. A failed command is retried on the next tick.
+    pub fn apply(&mut self, temp: u16, optimize_updates: bool) {
+        if optimize_updates && self.last_sent == Some(temp) {
+            return;
+        }
+        if self.command.is_empty() {
+            self.last_sent = Some(temp);
+            return;
+        }
+        let name = &self.name;
+        log::debug!("Running schedule {name} command: sh -c {:?}", self.command);
+        let output = hyprctl::run_bounded(
+            Command::new("sh")
+                .args(["-c", &self.command])
+                .env("CANDELA_SCHEDULE", name)
+                .env("CANDELA_TEMPERATURE", temp.to_string()),
+        );
+        match output {
+            Ok(output) if output.status.success() => self.last_sent = Some(temp),
+            Ok(output) => log::warn!("Schedule {name} command exited with {}", output.status),
+            Err(e) => log::error!("Failed to run schedule {name} command: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Mode, NamedSchedule, Transition};
+    use chrono::TimeZone;
+
+    fn fixed(duration_minutes: u32) -> Transition {
+        Transition {
+            duration_minutes: duration_minutes.into(),
+            easing: "linear".to_string(),
+            ..Transition::default()
+        }
+    }
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.mode = Mode::Fixed;
+        config.transition = fixed(60);
+        config.schedules = vec![
+            NamedSchedule {
+                name: "desk".to_string(),
+                ..NamedSchedule::default()
+            },
+            NamedSchedule {
+                name: "ambient".to_string(),
+                transition: Some(fixed(120)),
+                ..NamedSchedule::default()
+            },
+        ];
+        config
+    }
+
+    #[test]
+    fn schedules_with_different_durations_advance_independently() {
+        let zones = from_config(&config()).unwrap();
+        // 30 minutes into the 22:00 bedtime ramps: the 60-minute one started
+        // at 21:00, the 120-minute one at 20:00
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 21, 30, 0).unwrap();
+        let (desk, ambient) = (zones[0].reading(now), zones[1].reading(now));
+
+        assert!((desk.progress - 0.5).abs() < 1e-9);
+        assert!((ambient.progress - 0.75).abs() < 1e-9);
+        assert_eq!((desk.target, ambient.target), (1500, 1500));
+        assert_eq!((desk.temp, ambient.temp), (4000, 2750));
+
+        let later = Local.with_ymd_and_hms(2024, 6, 1, 23, 0, 0).unwrap();
+        assert_eq!(zones[0].reading(later), zones[1].reading(later));
+    }
+
+    #[test]
+    fn zone_status_fields_are_keyed_by_name() {
+        let reading = Reading {
+            temp: 2750,
+            target: 1500,
+            progress: 0.75,
+        };
+        let keys = reading.status_fields("ambient").map(|(key, _)| key);
+        assert_eq!(
+            keys,
+            [
+                "schedule.ambient.temp",
+                "schedule.ambient.target",
+                "schedule.ambient.progress"
+            ]
+        );
+        assert_eq!(reading.status_fields("ambient")[2].1, 0.75);
+    }
+
+    #[test]
+    fn failed_commands_are_sent_again() {
+        let mut zones = from_config(&config()).unwrap();
+        let zone = &mut zones[0];
+        zone.command = "exit 1".to_string();
+        zone.apply(3000, true);
+        assert_eq!(zone.last_sent, None);
+
+        zone.command = "test \"$CANDELA_TEMPERATURE\" = 3000".to_string();
+        zone.apply(3000, true);
+        assert_eq!(zone.last_sent, Some(3000));
+    }
+
+    #[test]
+    fn schedule_names_must_be_unique_words() {
+        let mut config = config();
+        config.schedules[1].name = "desk".to_string();
+        assert!(from_config(&config)
+            .err()
+            .is_some_and(|e| e.contains("used twice")));
+        config.schedules[1].name = "room.lights".to_string();
+        assert!(from_config(&config).is_err());
+        config.schedules[1].name = "lights".to_string();
+        assert_eq!(from_config(&config).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn optimized_zone_skips_repeated_temperatures() {
+        let mut zone = from_config(&config()).unwrap().remove(0);
+        zone.apply(3000, true);
+        assert_eq!(zone.last_sent, Some(3000));
+        zone.apply(3000, true);
+        zone.apply(2900, true);
+        assert_eq!(zone.last_sent, Some(2900));
+    }
+}